@@ -0,0 +1,375 @@
+//! Block to _bite_ the live network at.
+//!
+//! Supported values:
+//! - block number (e.g. `29798496`)
+//! - block hash (e.g. `0xc890...d138`)
+//! - `finalized` or `finalized-<N>` (N blocks before the finalized one)
+//! - timestamp, rfc3339 (e.g. `2025-06-01T12:00:00Z`) or unix seconds with `@` prefix (e.g. `@1748779200`)
+
+use std::{fmt, future::Future, str::FromStr};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::BiteError,
+    utils::{
+        get_block_hash, get_header_from_block, get_header_from_hash, get_storage_at, header_number,
+        rpc_call, with_rpc_failover, RpcResponseError,
+    },
+};
+
+const FINALIZED: &str = "finalized";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BiteAt {
+    Number(u32),
+    Hash(String),
+    /// Depth from the finalized block
+    Finalized(u32),
+    /// Unix timestamp in milliseconds
+    Timestamp(u64),
+}
+
+impl FromStr for BiteAt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(number) = s.parse::<u32>() {
+            return Ok(BiteAt::Number(number));
+        }
+
+        if let Some(hash) = s.strip_prefix("0x") {
+            if hash.len() != 64 || hex::decode(hash).is_err() {
+                return Err(format!("invalid block hash '{s}'"));
+            }
+            return Ok(BiteAt::Hash(s.to_lowercase()));
+        }
+
+        if let Some(rest) = s.strip_prefix(FINALIZED) {
+            if rest.is_empty() {
+                return Ok(BiteAt::Finalized(0));
+            }
+            return rest
+                .strip_prefix('-')
+                .and_then(|depth| depth.parse().ok())
+                .map(BiteAt::Finalized)
+                .ok_or_else(|| format!("invalid value '{s}', expected finalized-<N>"));
+        }
+
+        if let Some(secs) = s.strip_prefix('@') {
+            return secs
+                .parse::<u64>()
+                .map(|secs| BiteAt::Timestamp(secs * 1000))
+                .map_err(|_| format!("invalid unix timestamp '{s}'"));
+        }
+
+        chrono::DateTime::parse_from_rfc3339(s)
+            .ok()
+            .and_then(|datetime| u64::try_from(datetime.timestamp_millis()).ok())
+            .map(BiteAt::Timestamp)
+            .ok_or_else(|| {
+                format!("invalid value '{s}', expected a block number, hash, finalized[-N] or timestamp")
+            })
+    }
+}
+
+impl fmt::Display for BiteAt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BiteAt::Number(number) => write!(f, "{number}"),
+            BiteAt::Hash(hash) => write!(f, "{hash}"),
+            BiteAt::Finalized(0) => write!(f, "{FINALIZED}"),
+            BiteAt::Finalized(depth) => write!(f, "{FINALIZED}-{depth}"),
+            BiteAt::Timestamp(ts) => match chrono::DateTime::from_timestamp_millis(*ts as i64) {
+                Some(datetime) => write!(f, "{}", datetime.to_rfc3339()),
+                None => write!(f, "@{}", ts / 1000),
+            },
+        }
+    }
+}
+
+// In the manifest the block number can be set as integer or string.
+impl Serialize for BiteAt {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BiteAt::Number(number) => serializer.serialize_u32(*number),
+            _ => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BiteAt {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u32),
+            Str(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(number) => Ok(BiteAt::Number(number)),
+            Raw::Str(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl BiteAt {
+    /// Resolve to the header of the block (using the supplied endpoints).
+    pub async fn resolve_header(&self, endpoints: &[String]) -> Result<Value, anyhow::Error> {
+        let header = match self {
+            BiteAt::Number(number) => get_header_from_block(*number, endpoints).await?,
+            BiteAt::Hash(hash) => get_header_from_hash(hash, endpoints).await?,
+            BiteAt::Finalized(depth) => {
+                let finalized = finalized_number(endpoints).await?;
+                let number = finalized.checked_sub(*depth).ok_or_else(|| {
+                    anyhow!("depth {depth} is greater than the finalized block {finalized}")
+                })?;
+                get_header_from_block(number, endpoints).await?
+            }
+            BiteAt::Timestamp(ts) => {
+                let number = block_at_timestamp(*ts, endpoints).await?;
+                get_header_from_block(number, endpoints).await?
+            }
+        };
+        info!(
+            "bite at {self} resolved to block #{}",
+            header_number(&header)?
+        );
+
+        Ok(header)
+    }
+}
+
+async fn finalized_number(endpoints: &[String]) -> Result<u32, anyhow::Error> {
+    let hash = with_rpc_failover(endpoints, |endpoint| async move {
+        rpc_call(&endpoint, "chain_getFinalizedHead", serde_json::json!([]))
+            .await?
+            .and_then(|hash| hash.as_str().map(str::to_string))
+            .ok_or_else(|| anyhow!("invalid finalized head response"))
+    })
+    .await?;
+    header_number(&get_header_from_hash(&hash, endpoints).await?)
+}
+
+/// Read a (u32 or u64) storage value at the block number, `None` if the value is not
+/// available (not set yet or the state was pruned by the rpc node).
+async fn storage_number_at(
+    key: &str,
+    number: u32,
+    endpoints: &[String],
+) -> Result<Option<u64>, anyhow::Error> {
+    let hash = get_block_hash(number, endpoints).await?;
+    let value = match get_storage_at(key, &hash, endpoints).await {
+        Ok(value) => value,
+        Err(e) if is_state_unavailable(&e) => {
+            debug!("state of block #{number} not available: {e:#}");
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    let Some(mut value) = value else {
+        return Ok(None);
+    };
+    if value.len() > 8 {
+        return Err(anyhow!("invalid value for {key} at block #{number}"));
+    }
+    // LE encoded, so we can pad to decode u32 values
+    value.resize(8, 0);
+    Ok(Some(u64::from_le_bytes(
+        value.try_into().expect("len is 8. qed"),
+    )))
+}
+
+/// The rpc node answered but doesn't have the state of the block (non archive nodes).
+fn is_state_unavailable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<RpcResponseError>()
+        .map(|e| {
+            let error = e.error.to_string().to_lowercase();
+            ["discarded", "pruned", "unknown block"]
+                .iter()
+                .any(|reason| error.contains(reason))
+        })
+        .unwrap_or_default()
+}
+
+/// Last block (up to the finalized one) where the storage value is `<= target`, the values
+/// need to be monotonic. Returns the block and the value.
+async fn last_block_with_storage_le(
+    key: &str,
+    target: u64,
+    endpoints: &[String],
+) -> Result<(u32, u64), anyhow::Error> {
+    let finalized = finalized_number(endpoints).await?;
+    last_block_with_value_le(target, finalized, |number| {
+        storage_number_at(key, number, endpoints)
+    })
+    .await
+}
+
+/// Last block (up to `finalized`) where `value_at(block) <= target`.
+///
+/// The window is bounded probing backwards from `finalized` (1, 2, 4... blocks) and then
+/// binary searched, so only the state of recent blocks is read when the target is recent
+/// (public rpc nodes are pruned). Missing values are treated as below the target.
+async fn last_block_with_value_le<F, Fut>(
+    target: u64,
+    finalized: u32,
+    value_at: F,
+) -> Result<(u32, u64), anyhow::Error>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Option<u64>, anyhow::Error>>,
+{
+    let last = value_at(finalized)
+        .await?
+        .ok_or_else(|| anyhow!("value not present at the finalized block #{finalized}"))?;
+    if last <= target {
+        return Ok((finalized, last));
+    }
+
+    let (mut hi, mut step) = (finalized, 1_u32);
+    let (mut lo, mut found) = loop {
+        let number = hi.saturating_sub(step);
+        let value = value_at(number).await?;
+        debug!("probe: #{number} -> {value:?}");
+        match value {
+            Some(value) if value > target => {
+                if number == 0 {
+                    return Err(anyhow!(
+                        "target {target} is before the first block (value: {value})"
+                    ));
+                }
+                hi = number;
+                step = step.saturating_mul(2);
+            }
+            _ => break (number, value),
+        }
+    };
+
+    while lo + 1 < hi {
+        let mid = lo + (hi - lo) / 2;
+        let value = value_at(mid).await?;
+        debug!("binary search: #{mid} -> {value:?}");
+        match value {
+            Some(value) if value > target => hi = mid,
+            _ => {
+                lo = mid;
+                found = value;
+            }
+        }
+    }
+
+    found.map(|value| (lo, value)).ok_or_else(|| {
+        BiteError::Rpc(format!(
+            "target {target} is older than the state available in the rpc nodes (first block with state: #{hi}), an archive rpc is needed for older targets (`--rc-rpc` / `--para-rpc`)"
+        ))
+        .into()
+    })
+}
+
+fn storage_key(pallet: &str, item: &str) -> String {
+    array_bytes::bytes2hex("0x", substorager::storage_value_key(pallet, item))
+}
+
+/// Last block with `Timestamp::Now <= ts`.
+async fn block_at_timestamp(ts: u64, endpoints: &[String]) -> Result<u32, anyhow::Error> {
+    let key = storage_key("Timestamp", "Now");
+    let (number, _) = last_block_with_storage_le(&key, ts, endpoints).await?;
+    Ok(number)
+}
+
+/// Header of the last para block built on top of a relay parent <= `relay_number`, to keep the
+/// para state consistent with the relaychain one.
+pub async fn para_header_for_relay_block(
+    relay_number: u32,
+    endpoints: &[String],
+) -> Result<Value, anyhow::Error> {
+    let key = storage_key("ParachainSystem", "LastRelayChainBlockNumber");
+    let (number, relay_parent) =
+        last_block_with_storage_le(&key, relay_number as u64, endpoints).await?;
+    if relay_parent != relay_number as u64 {
+        warn!("no para block with relay parent #{relay_number}, using #{number} (relay parent #{relay_parent})");
+    }
+    info!("para block #{number} matches relay block #{relay_number}");
+    get_header_from_block(number, endpoints).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bite_at_should_parse() {
+        assert_eq!("29798496".parse(), Ok(BiteAt::Number(29798496)));
+        assert_eq!("finalized".parse(), Ok(BiteAt::Finalized(0)));
+        assert_eq!("finalized-10".parse(), Ok(BiteAt::Finalized(10)));
+        assert_eq!("@1748779200".parse(), Ok(BiteAt::Timestamp(1748779200000)));
+        assert_eq!(
+            "2025-06-01T12:00:00Z".parse(),
+            Ok(BiteAt::Timestamp(1748779200000))
+        );
+        let hash = "0xc890131fe604ae48cd385eb91be80f70ad99307bdc9e79d5969b8e8b507ad138";
+        assert_eq!(hash.parse(), Ok(BiteAt::Hash(hash.to_string())));
+
+        assert!("finalized-".parse::<BiteAt>().is_err());
+        assert!("0x1234".parse::<BiteAt>().is_err());
+        assert!("yesterday".parse::<BiteAt>().is_err());
+    }
+
+    #[test]
+    fn bite_at_should_roundtrip() {
+        for value in ["finalized", "finalized-5", "2025-06-01T12:00:00+00:00"] {
+            let bite_at: BiteAt = value.parse().unwrap();
+            assert_eq!(bite_at.to_string(), value);
+        }
+
+        #[derive(Serialize, Deserialize)]
+        struct T {
+            bite_at: Vec<BiteAt>,
+        }
+        let t: T = toml::from_str(r#"bite_at = [100, "finalized-2"]"#).unwrap();
+        assert_eq!(t.bite_at, vec![BiteAt::Number(100), BiteAt::Finalized(2)]);
+        assert_eq!(
+            toml::to_string(&t).unwrap().trim(),
+            r#"bite_at = [100, "finalized-2"]"#
+        );
+    }
+
+    /// Values of a chain with 1000 blocks, +6 each block, pruned before `#pruned`.
+    async fn value_at(number: u32, pruned: u32) -> Result<Option<u64>, anyhow::Error> {
+        Ok((number >= pruned && number > 0).then_some(number as u64 * 6))
+    }
+
+    #[tokio::test]
+    async fn last_block_with_value_le_should_work() {
+        let probed = std::sync::Mutex::new(vec![]);
+        let search = |target| {
+            last_block_with_value_le(target, 1000, |number| {
+                probed.lock().unwrap().push(number);
+                value_at(number, 900)
+            })
+        };
+
+        assert_eq!(search(6000).await.unwrap(), (1000, 6000));
+        assert_eq!(search(5995).await.unwrap(), (999, 5994));
+        assert_eq!(search(5500).await.unwrap(), (916, 5496));
+        // only recent blocks are read
+        assert!(probed.lock().unwrap().iter().all(|number| *number >= 488));
+
+        // older than the pruned state
+        let err = search(100).await.unwrap_err();
+        assert!(err.to_string().contains("archive rpc"));
+        assert_eq!(crate::error::exit_code(&err), 3);
+
+        // archive node
+        let (number, value) = last_block_with_value_le(100, 1000, |number| value_at(number, 0))
+            .await
+            .unwrap();
+        assert_eq!((number, value), (16, 96));
+    }
+}
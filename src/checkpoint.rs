@@ -0,0 +1,187 @@
+//! Persisted state of the `bite` phases, to allow resume a failed bite (`bite --resume`).
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+use crate::error::BiteError;
+
+/// File (in the `bite` dir) with the completed phases.
+pub const BITE_STATE_FILE: &str = "bite-state.json";
+
+/// Chains and blocks of the bite, a resumed bite should use the same ones.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BiteTarget {
+    pub chain: String,
+    pub paras: Vec<String>,
+    /// `bite_at` of each chain (only the ones set)
+    pub bite_at: BTreeMap<String, String>,
+}
+
+impl fmt::Display for BiteTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} with paras {:?}", self.chain, self.paras)?;
+        if !self.bite_at.is_empty() {
+            write!(f, " at {:?}", self.bite_at)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BiteState {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Not present in the states of older versions.
+    #[serde(default)]
+    target: Option<BiteTarget>,
+    /// Completed phases (e.g. `asset-hub-polkadot/sync`).
+    completed: BTreeSet<String>,
+    /// Outputs of the completed phases needed to resume (e.g. the sync db path).
+    outputs: BTreeMap<String, serde_json::Value>,
+}
+
+/// Id of the phase for the chain.
+pub fn phase(chain: &str, step: &str) -> String {
+    format!("{chain}/{step}")
+}
+
+impl BiteState {
+    /// Load the state from the `bite` dir if `resume` is set, otherwise start from scratch.
+    /// Fails if the state is for another `target`.
+    pub async fn load(
+        bite_dir: &Path,
+        resume: bool,
+        target: BiteTarget,
+    ) -> Result<Self, anyhow::Error> {
+        let path = bite_dir.join(BITE_STATE_FILE);
+        let mut state = if resume && fs::try_exists(&path).await.unwrap_or_default() {
+            let content = fs::read_to_string(&path).await?;
+            let state: Self = serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Error parsing bite state {}: {e}", path.display()))?;
+            match &state.target {
+                Some(previous) if *previous != target => {
+                    return Err(BiteError::Config(format!(
+                        "can't resume the bite of {previous} as {target}, use the same chains / bite_at or a new base path"
+                    ))
+                    .into());
+                }
+                Some(_) => {}
+                None => warn!("the bite state doesn't have the target, resuming as {target}"),
+            }
+            info!(
+                "resuming bite, completed phases: {:?}",
+                state.completed.iter().collect::<Vec<_>>()
+            );
+            Self { path, ..state }
+        } else {
+            if resume {
+                info!(
+                    "no bite state found at {}, starting from scratch",
+                    path.display()
+                );
+            }
+            Self {
+                path,
+                ..Default::default()
+            }
+        };
+        state.target = Some(target);
+        state.persist().await?;
+
+        Ok(state)
+    }
+
+    pub fn is_done(&self, phase: &str) -> bool {
+        self.completed.contains(phase)
+    }
+
+    /// Output of the phase (IFF completed).
+    pub fn output<T: DeserializeOwned>(&self, phase: &str) -> Option<T> {
+        if !self.is_done(phase) {
+            return None;
+        }
+        self.outputs
+            .get(phase)
+            .and_then(|output| serde_json::from_value(output.clone()).ok())
+    }
+
+    /// Mark the phase as completed (with the output to reuse) and persist the state.
+    pub async fn complete(
+        &mut self,
+        phase: &str,
+        output: impl Serialize,
+    ) -> Result<(), anyhow::Error> {
+        self.completed.insert(phase.to_string());
+        self.outputs
+            .insert(phase.to_string(), serde_json::to_value(output)?);
+        self.persist().await?;
+        debug!("phase {phase} completed");
+        Ok(())
+    }
+
+    async fn persist(&self) -> Result<(), anyhow::Error> {
+        // write + rename to not leave a corrupted file if we are killed
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?).await?;
+        fs::rename(&tmp_path, &self.path)
+            .await
+            .map_err(|e| anyhow!("Error writing bite state {}: {e}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn state_should_persist_and_resume() {
+        let dir =
+            std::env::temp_dir().join(format!("zombie-bite-checkpoint-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir).await;
+        fs::create_dir_all(&dir).await.unwrap();
+
+        let sync = phase("asset-hub-polkadot", "sync");
+        let target = BiteTarget {
+            chain: String::from("polkadot"),
+            paras: vec![String::from("asset-hub-polkadot")],
+            bite_at: BTreeMap::from([(String::from("polkadot"), String::from("finalized"))]),
+        };
+        let mut state = BiteState::load(&dir, false, target.clone()).await.unwrap();
+        assert!(!state.is_done(&sync));
+        state
+            .complete(&sync, ("/tmp/sync-db", "asset-hub-polkadot"))
+            .await
+            .unwrap();
+
+        let state = BiteState::load(&dir, true, target.clone()).await.unwrap();
+        assert!(state.is_done(&sync));
+        assert_eq!(
+            state.output::<(String, String)>(&sync),
+            Some((
+                String::from("/tmp/sync-db"),
+                String::from("asset-hub-polkadot")
+            ))
+        );
+
+        // other target
+        let other = BiteTarget {
+            bite_at: BTreeMap::new(),
+            ..target.clone()
+        };
+        let err = BiteState::load(&dir, true, other).await.unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 2);
+
+        // without resume we start from scratch
+        let state = BiteState::load(&dir, false, target).await.unwrap();
+        assert!(!state.is_done(&sync));
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -3,6 +3,10 @@ use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use clap::{Parser, Subcommand};
+
+use crate::bite_at::BiteAt;
+use crate::control::Request;
+use crate::manifest::get_manifest;
 use std::str::FromStr;
 
 #[derive(Parser, Debug)]
@@ -13,26 +17,49 @@ pub struct Args {
 }
 
 #[derive(Subcommand, Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Bite the running network using 'doppelganger' binaries, and generate the artifacts for spawning.
     Bite {
-        /// The network will be using for bite (will try the network + ah)
-        #[arg(short = 'r', long = "rc", value_parser = clap::builder::PossibleValuesParser::new(["polkadot", "kusama", "paseo"]), default_value="polkadot")]
-        relay: String,
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd).
+        /// The cli args and env vars take precedence over the values in the manifest.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// The network will be using for bite (will try the network + ah) [default: polkadot]
+        /// Any other value than polkadot, kusama or paseo is handled as a custom relaychain
+        /// and needs `--rc-rpc` (and optionally `--rc-spec`, `--rc-epoch-duration` and `--rc-chain-id`).
+        #[arg(short = 'r', long = "rc", verbatim_doc_comment)]
+        relay: Option<String>,
+        /// Chain spec (path or name known by the doppelganger binary) of the custom relaychain [default: the `--rc` value]
+        #[arg(long = "rc-spec", verbatim_doc_comment)]
+        rc_spec: Option<String>,
+        /// Rpc endpoints of the relaychain (comma separated, in order of preference), used with failover
+        /// for the header lookup and when we sync the parachains. Required for custom relaychains.
+        /// [default: public endpoints of the network]
+        #[arg(long = "rc-rpc", value_delimiter = ',', verbatim_doc_comment)]
+        rc_rpc: Vec<String>,
+        /// Epoch duration (in blocks) of the custom relaychain [default: 2400]
+        #[arg(long = "rc-epoch-duration", value_parser = clap::value_parser!(u64).range(1..), verbatim_doc_comment)]
+        rc_epoch_duration: Option<u64>,
+        /// Chain id (used in the db path) of the custom relaychain [default: `id` from the spec or the `--rc` value]
+        #[arg(long = "rc-chain-id", verbatim_doc_comment)]
+        rc_chain_id: Option<String>,
         /// If provided we will override the runtime as part of the process of 'bite'
         /// The resulting network will be running with this runtime.
         #[arg(long = "rc-override", verbatim_doc_comment)]
         relay_runtime: Option<String>,
-        /// If provided we will _bite_ the live network at the supplied block hieght
+        /// If provided we will _bite_ the live network at the supplied block, as height, hash,
+        /// `finalized`, `finalized-<N>` or timestamp (rfc3339 or `@<unix_secs>`).
+        /// If the parachains don't set the block we will use the one matching this relay block.
         #[arg(long = "rc-bite-at", verbatim_doc_comment)]
-        relay_bite_at: Option<u32>,
+        relay_bite_at: Option<BiteAt>,
         /// If provided we will override the runtime as part of the process of 'bite'
         /// The resulting version of AH will be running with this runtime.
         #[arg(long = "ah-override", verbatim_doc_comment)]
         ah_runtime: Option<String>,
-        /// If provided we will _bite_ the live network at the supplied block hieght
+        /// If provided we will _bite_ the live network at the supplied block (same format as `--rc-bite-at`)
         #[arg(long = "ah-bite-at", verbatim_doc_comment)]
-        ah_bite_at: Option<u32>,
+        ah_bite_at: Option<BiteAt>,
         /// Base path to use. if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
         #[arg(long, short = 'd', verbatim_doc_comment)]
         base_path: Option<String>,
@@ -42,12 +69,66 @@ pub enum Commands {
         /// Automatically spawn the 'bited' network
         #[arg(long, short = 'm', default_value_t = false, verbatim_doc_comment)]
         and_spawn: bool,
-        /// Db to use
-        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(["rocksdb", "paritydb"]), default_value="rocksdb", verbatim_doc_comment)]
-        database: String,
+        /// Db to use [default: rocksdb]
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(["rocksdb", "paritydb"]), verbatim_doc_comment)]
+        database: Option<String>,
+        /// File (json or toml) with `overrides`/`injects` (and optionally `remove`) sections
+        /// to merge over the default relaychain overrides.
+        #[arg(long = "rc-overrides", verbatim_doc_comment)]
+        rc_overrides: Option<String>,
+        /// File (json or toml) to merge over the default overrides of a parachain, in the form <para_id>=<file>.
+        /// Can be passed multiple times.
+        #[arg(long = "para-overrides", value_parser = parse_para_overrides, verbatim_doc_comment)]
+        para_overrides: Vec<(u32, String)>,
+        /// Rpc endpoints of a parachain (comma separated, in order of preference), in the form <para_id>=<url>[,<url>].
+        /// Can be passed multiple times. [default: public endpoints of the parachain]
+        #[arg(long = "para-rpc", value_parser = parse_para_rpcs, verbatim_doc_comment)]
+        para_rpcs: Vec<(u32, Vec<String>)>,
+        /// System parachains to bite (comma separated), each one will get its own core and collator.
+        /// [default: asset-hub]
+        #[arg(long, value_delimiter = ',', value_parser = clap::builder::PossibleValuesParser::new(["asset-hub", "coretime", "people", "bridge-hub", "collectives"]), verbatim_doc_comment)]
+        paras: Vec<String>,
+        /// Number of relaychain validators to use (alice, bob, charlie, dave, eve, ferdie and then validator-<n>).
+        /// [default: 2]
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..), verbatim_doc_comment)]
+        validators: Option<u32>,
+        /// Resume a failed bite (in the same base path), skipping the completed phases (e.g. already synced chains).
+        /// If `--manifest` is not provided we will use the resolved config of the bite to resume.
+        #[arg(long, default_value_t = false, verbatim_doc_comment)]
+        resume: bool,
+        /// Max time (in secs) to sync each chain, the bite fails if the sync is not completed [default: no limit]
+        #[arg(long = "sync-timeout", value_parser = clap::value_parser!(u64).range(1..), verbatim_doc_comment)]
+        sync_timeout: Option<u64>,
+        /// Max time (in secs) without sync progress (imported blocks or warp download/import) before
+        /// failing the bite [default: 1800]
+        #[arg(long = "sync-stall-timeout", value_parser = clap::value_parser!(u64).range(1..), verbatim_doc_comment)]
+        sync_stall_timeout: Option<u64>,
+        /// Skip the preflight checks (`doctor`) before the bite.
+        #[arg(long, default_value_t = false, verbatim_doc_comment)]
+        skip_doctor: bool,
+        /// What to do on SIGINT/SIGTERM with the spawned network (with `--and-spawn`), the network
+        /// is always teardown but the artifacts for the next step are only generated with `generate`.
+        #[arg(long = "on-signal", value_parser = clap::builder::PossibleValuesParser::new(["generate", "discard"]), default_value = "discard", verbatim_doc_comment)]
+        on_signal: String,
+        /// Port to serve the zombie-bite metrics (`/metrics`, prometheus format)
+        /// [default: env 'ZOMBIE_BITE_METRICS_PORT' or manifest `ports.metrics`, disabled if not set]
+        #[arg(long = "metrics-port", verbatim_doc_comment)]
+        metrics_port: Option<u16>,
+        /// Codec of the snapshots (artifacts for the next step), zstd and none are extracted by
+        /// zombie-bite before the spawn [default: manifest `snapshot.codec` or gzip]
+        #[arg(long = "snapshot-codec", value_parser = clap::builder::PossibleValuesParser::new(["gzip", "zstd", "none"]), verbatim_doc_comment)]
+        snapshot_codec: Option<String>,
+        /// Also keep the dbs uncompressed (`<name>-snap.d`), so the next step is spawned cloning
+        /// them (reflinks / hardlinks) instead of extracting the snapshots [default: manifest `snapshot.dir`]
+        #[arg(long = "snapshot-dir", default_value_t = false, verbatim_doc_comment)]
+        snapshot_dir: bool,
     },
     /// Spawn a new instance of the network from the bite step.
     Spawn {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
         /// Base path where the 'bite' artifacts lives, we should use this base_path
         /// to find those artifacts and 'spawn' the network.
         /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
@@ -59,11 +140,143 @@ pub enum Commands {
         /// The network will be using for bite (will try the network + ah)
         #[arg(short = 's', value_parser = clap::builder::PossibleValuesParser::new(["spawn", "post", "after"]), default_value="spawn")]
         step: String,
+        /// Skip the preflight checks (`doctor`) before the spawn.
+        #[arg(long, default_value_t = false, verbatim_doc_comment)]
+        skip_doctor: bool,
+        /// What to do on SIGINT/SIGTERM, the network is always teardown but the artifacts
+        /// for the next step are only generated with `generate`.
+        #[arg(long = "on-signal", value_parser = clap::builder::PossibleValuesParser::new(["generate", "discard"]), default_value = "discard", verbatim_doc_comment)]
+        on_signal: String,
+        /// Port to serve the zombie-bite metrics (`/metrics`, prometheus format)
+        /// [default: env 'ZOMBIE_BITE_METRICS_PORT' or manifest `ports.metrics`, disabled if not set]
+        #[arg(long = "metrics-port", verbatim_doc_comment)]
+        metrics_port: Option<u16>,
+        /// Codec of the snapshots (artifacts for the next step), zstd and none are extracted by
+        /// zombie-bite before the spawn [default: manifest `snapshot.codec` or gzip]
+        #[arg(long = "snapshot-codec", value_parser = clap::builder::PossibleValuesParser::new(["gzip", "zstd", "none"]), verbatim_doc_comment)]
+        snapshot_codec: Option<String>,
+        /// Also keep the dbs uncompressed (`<name>-snap.d`), so the next step is spawned cloning
+        /// them (reflinks / hardlinks) instead of extracting the snapshots [default: manifest `snapshot.dir`]
+        #[arg(long = "snapshot-dir", default_value_t = false, verbatim_doc_comment)]
+        snapshot_dir: bool,
+    },
+    /// Check the environment (binaries, disk space, ports and base path) before bite/spawn.
+    /// Also run automatically by `bite` and `spawn` (unless `--skip-doctor` is used).
+    Doctor {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd).
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// The network to bite, used to estimate the disk space needed [default: polkadot]
+        #[arg(short = 'r', long = "rc", verbatim_doc_comment)]
+        relay: Option<String>,
+        /// System parachains to bite (comma separated) [default: asset-hub]
+        #[arg(long, value_delimiter = ',', value_parser = clap::builder::PossibleValuesParser::new(["asset-hub", "coretime", "people", "bridge-hub", "collectives"]), verbatim_doc_comment)]
+        paras: Vec<String>,
+        /// Base path to use. if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+    },
+    /// Verify the snapshots of a step (size, sha256, codec, chain and database from their
+    /// manifests) before spawning from it.
+    Verify {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// Base path where the artifacts lives.
+        /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+        /// Step with the artifacts to verify (the one to spawn from)
+        #[arg(short = 's', value_parser = clap::builder::PossibleValuesParser::new(["bite", "spawn", "post"]), default_value="bite")]
+        step: String,
+    },
+    /// Remove the snapshots in the store (`<base_path>/store` by default) not referenced
+    /// by any step dir of the base path.
+    Gc {
+        /// Manifest file to use (for the `snapshot.store`), if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// Base path with the step dirs.
+        /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+        /// Only print the snapshots to remove.
+        #[arg(long, default_value_t = false, verbatim_doc_comment)]
+        dry_run: bool,
+    },
+    /// Upload the artifacts of a step (config, chain-specs, snapshots and manifests) to a remote
+    /// store (s3 bucket, http server or dir), resuming partial uploads.
+    Push {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// Remote store, `s3://<bucket>/<prefix>`, `http(s)://<host>/<prefix>` or a dir
+        #[arg(verbatim_doc_comment)]
+        remote: String,
+        /// Base path where the artifacts lives.
+        /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+        /// Step with the artifacts to push
+        #[arg(short = 's', value_parser = clap::builder::PossibleValuesParser::new(["bite", "spawn", "post"]), default_value="bite")]
+        step: String,
+    },
+    /// Download the artifacts of a step from a remote store (resuming partial downloads),
+    /// localizing the paths of the config and verifying the snapshots.
+    Pull {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// Remote store, `s3://<bucket>/<prefix>`, `http(s)://<host>/<prefix>` or a dir
+        #[arg(verbatim_doc_comment)]
+        remote: String,
+        /// Base path to download the artifacts.
+        /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+        /// Step with the artifacts to pull (the one to spawn from)
+        #[arg(short = 's', value_parser = clap::builder::PossibleValuesParser::new(["bite", "spawn", "post"]), default_value="bite")]
+        step: String,
+    },
+    /// Send a command to the running network (control socket in the base path).
+    Ctl {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// Base path of the network.
+        /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+        #[command(subcommand)]
+        request: Request,
+    },
+    /// Print the info (nodes, ports, logs, pids) of the running network (from the control socket or `network.json` in the step dir).
+    Info {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
+        /// Base path of the network.
+        /// if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
+        #[arg(long, short = 'd', verbatim_doc_comment)]
+        base_path: Option<String>,
+        /// Step of the running network
+        #[arg(short = 's', value_parser = clap::builder::PossibleValuesParser::new(["spawn", "post", "after"]), default_value="spawn")]
+        step: String,
     },
     /// [Helper] Generate artifacts to be used by the next step (only 'spawn' and 'post' allowed)
     GenerateArtifacts {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
         /// The network will be using for bite (will try the network + ah)
-        #[arg(short = 'r', long = "rc", value_parser = clap::builder::PossibleValuesParser::new(["polkadot", "kusame", "paseo"]), default_value="polkadot")]
+        #[arg(short = 'r', long = "rc", default_value = "polkadot")]
         relay: String,
         /// Base path to use. if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
         #[arg(long, short = 'd', verbatim_doc_comment)]
@@ -74,8 +287,12 @@ pub enum Commands {
     },
     /// [Helper] Clean up directory to only include the needed artifacts
     CleanUpDir {
+        /// Manifest file to use, if not provided we will use `zombie-bite.toml` (IFF exist in the cwd)
+        /// or the resolved config of the bite in the base path.
+        #[arg(long, verbatim_doc_comment)]
+        manifest: Option<String>,
         /// The network will be using for bite (will try the network + ah)
-        #[arg(short = 'r', long = "rc", value_parser = clap::builder::PossibleValuesParser::new(["polkadot", "kusame", "paseo"]), default_value="polkadot")]
+        #[arg(short = 'r', long = "rc", default_value = "polkadot")]
         relay: String,
         /// Base path to use. if not provided we will check the env 'ZOMBIE_BITE_BASE_PATH' and if not present we will use `<cwd>_timestamp`
         #[arg(long, short = 'd', verbatim_doc_comment)]
@@ -86,10 +303,34 @@ pub enum Commands {
     },
 }
 
-/// base_path can be set from env with 'ZOMBIE_BITE_BASE_PATH'
+fn parse_para_overrides(value: &str) -> Result<(u32, String), String> {
+    let (id, file) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid value '{value}', expected <para_id>=<file>"))?;
+    let id = id
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| format!("invalid para_id '{id}'"))?;
+    Ok((id, file.trim().to_string()))
+}
+
+fn parse_para_rpcs(value: &str) -> Result<(u32, Vec<String>), String> {
+    let (id, urls) = parse_para_overrides(value)
+        .map_err(|_| format!("invalid value '{value}', expected <para_id>=<url>[,<url>]"))?;
+    let urls: Vec<String> = urls
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+    if urls.is_empty() {
+        return Err(format!("no urls in '{value}'"));
+    }
+    Ok((id, urls))
+}
+
+/// base_path can be set from env with 'ZOMBIE_BITE_BASE_PATH', the manifest
 /// or using the cli argument (take precedence).
 /// And if not set we fallback to defaul `cwd_timestamp`
-
 pub fn get_base_path(cli_base_path: Option<String>) -> PathBuf {
     let global_base_path = if let Some(base_path) = cli_base_path {
         PathBuf::from_str(&base_path).expect("Base path in cli args should be valid")
@@ -97,6 +338,8 @@ pub fn get_base_path(cli_base_path: Option<String>) -> PathBuf {
         if let Ok(base_path) = env::var("ZOMBIE_BITE_BASE_PATH") {
             PathBuf::from_str(&base_path)
                 .expect("Base path in env 'ZOMBIE_BITE_BASE_PATH' should be valid")
+        } else if let Some(base_path) = &get_manifest().base_path {
+            PathBuf::from_str(base_path).expect("Base path in manifest should be valid")
         } else {
             // fallback
             let path = env::current_dir().expect("cwd should be valid");
@@ -113,5 +356,6 @@ pub fn get_base_path(cli_base_path: Option<String>) -> PathBuf {
         }
     };
 
-    global_base_path.canonicalize().unwrap()
+    // the base path could not exist yet (e.g. first bite)
+    global_base_path.canonicalize().unwrap_or(global_base_path)
 }
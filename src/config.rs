@@ -1,8 +1,8 @@
 #![allow(dead_code)]
 // TODO: don't allow dead_code
 
-use std::env;
-
+use crate::bite_at::BiteAt;
+use crate::manifest::get_manifest;
 use zombienet_configuration::{NetworkConfig, NetworkConfigBuilder};
 const BITE: &str = "bite";
 const SPAWN: &str = "spawn";
@@ -13,12 +13,29 @@ const DEBUG: &str = "debug";
 // `--state-pruning` config flag (two days +1 by default)
 pub const STATE_PRUNING: &str = "28801";
 pub fn get_state_pruning_config() -> String {
-    env::var("ZOMBIE_BITE_STATE_PRUNING").unwrap_or_else(|_| STATE_PRUNING.to_string())
+    get_manifest().state_pruning()
 }
 
 pub const AH_POLKADOT_RCP: &str = "https://asset-hub-polkadot-rpc.n.dwellir.com";
 pub const AH_KUSAMA_RCP: &str = "https://asset-hub-kusama-rpc.n.dwellir.com";
 
+// Default (public) rpc endpoints, in order of preference.
+const POLKADOT_RPCS: [&str; 3] = [
+    "wss://polkadot-rpc.n.dwellir.com",
+    "wss://rpc.ibp.network/polkadot",
+    "wss://polkadot-rpc.publicnode.com",
+];
+const KUSAMA_RPCS: [&str; 3] = [
+    "wss://kusama-rpc.n.dwellir.com",
+    "wss://rpc.ibp.network/kusama",
+    "wss://kusama-rpc.publicnode.com",
+];
+const PASEO_RPCS: [&str; 3] = [
+    "wss://paseo-rpc.dwellir.com",
+    "wss://rpc.ibp.network/paseo",
+    "wss://paseo.rpc.amforc.com",
+];
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Step {
     /// Initial step
@@ -124,105 +141,226 @@ impl Context {
 
 type MaybeWasmOverridePath = Option<String>;
 type MaybeSyncUrl = Option<String>;
-type MaybeByteAt = Option<u32>;
+type MaybeRpcEndpoints = Option<Vec<String>>;
+type MaybeByteAt = Option<BiteAt>;
 
 #[derive(Debug, PartialEq)]
 pub enum Relaychain {
     Polkadot {
         maybe_override: MaybeWasmOverridePath,
         maybe_sync_url: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
         maybe_bite_at: MaybeByteAt,
     },
     Kusama {
         maybe_override: MaybeWasmOverridePath,
         maybe_sync_url: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
         maybe_bite_at: MaybeByteAt,
     },
 
     Paseo {
         maybe_override: MaybeWasmOverridePath,
         maybe_sync_url: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
+        maybe_bite_at: MaybeByteAt,
+    },
+
+    /// Any other relaychain (e.g. westend or a private testnet)
+    Custom {
+        name: String,
+        spec: CustomRelaySpec,
+        maybe_override: MaybeWasmOverridePath,
+        maybe_sync_url: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
         maybe_bite_at: MaybeByteAt,
     },
 }
 
+/// Chain info needed to bite a custom relaychain.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CustomRelaySpec {
+    /// Chain spec path or name (known by the doppelganger binary) to use as `--chain`
+    pub chain_spec: String,
+    /// Epoch duration (in blocks)
+    pub epoch_duration: u64,
+    /// Chain id, used as dir name in the db path (e.g. `ksmcc3` for kusama)
+    pub chain_id: String,
+}
+
+impl CustomRelaySpec {
+    /// Spec with the defaults for the supplied name (chain spec and id equal to the name).
+    pub fn from_name(name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            chain_spec: name.clone(),
+            epoch_duration: DEFAULT_EPOCH_DURATION,
+            chain_id: name,
+        }
+    }
+}
+
+/// Epoch duration (in blocks) of polkadot.
+pub const DEFAULT_EPOCH_DURATION: u64 = 2400;
+
+/// Read the chain `id` from the chain spec (IFF is a path to a json file).
+pub fn chain_id_from_spec(chain_spec: &str) -> Option<String> {
+    let content = std::fs::read_to_string(chain_spec).ok()?;
+    let spec: serde_json::Value = serde_json::from_str(&content).ok()?;
+    spec["id"].as_str().map(str::to_string)
+}
+
 impl Relaychain {
+    /// Relaychain from the network name, unknown networks are handled as `Custom` with
+    /// the default spec (see [CustomRelaySpec::from_name]).
     pub fn new(network: impl AsRef<str>) -> Self {
-        match network.as_ref() {
-            "kusama" => Self::Kusama {
-                maybe_override: None,
-                maybe_sync_url: None,
-                maybe_bite_at: None,
-            },
-            "paseo" => Self::Paseo {
-                maybe_override: None,
-                maybe_sync_url: None,
-                maybe_bite_at: None,
-            },
-            _ => Self::Polkadot {
-                maybe_override: None,
-                maybe_sync_url: None,
-                maybe_bite_at: None,
-            },
-        }
+        Self::new_with_values(network, None, None, None, None)
     }
 
     pub fn new_with_values(
         network: impl AsRef<str>,
         maybe_override: MaybeWasmOverridePath,
         maybe_sync_url: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
         maybe_bite_at: MaybeByteAt,
     ) -> Self {
         match network.as_ref() {
             "kusama" => Self::Kusama {
                 maybe_override,
                 maybe_sync_url,
+                maybe_rpc_endpoints,
                 maybe_bite_at,
             },
             "paseo" => Self::Paseo {
                 maybe_override,
                 maybe_sync_url,
+                maybe_rpc_endpoints,
                 maybe_bite_at,
             },
-            _ => Self::Polkadot {
+            "polkadot" => Self::Polkadot {
                 maybe_override,
                 maybe_sync_url,
+                maybe_rpc_endpoints,
                 maybe_bite_at,
             },
+            name => Self::Custom {
+                name: name.to_string(),
+                spec: CustomRelaySpec::from_name(name),
+                maybe_override,
+                maybe_sync_url,
+                maybe_rpc_endpoints,
+                maybe_bite_at,
+            },
+        }
+    }
+
+    pub fn new_custom(
+        name: impl Into<String>,
+        spec: CustomRelaySpec,
+        maybe_override: MaybeWasmOverridePath,
+        maybe_sync_url: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
+        maybe_bite_at: MaybeByteAt,
+    ) -> Self {
+        Self::Custom {
+            name: name.into(),
+            spec,
+            maybe_override,
+            maybe_sync_url,
+            maybe_rpc_endpoints,
+            maybe_bite_at,
         }
     }
 
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Relaychain::Custom { .. })
+    }
+
     pub fn as_local_chain_string(&self) -> String {
-        String::from(match self {
-            Relaychain::Polkadot { .. } => "polkadot-local",
-            Relaychain::Kusama { .. } => "kusama-local",
-            Relaychain::Paseo { .. } => "paseo-local",
-        })
+        format!("{}-local", self.as_chain_string())
     }
 
     pub fn as_chain_string(&self) -> String {
-        String::from(match self {
-            Relaychain::Polkadot { .. } => "polkadot",
-            Relaychain::Kusama { .. } => "kusama",
-            Relaychain::Paseo { .. } => "paseo",
-        })
+        match self {
+            Relaychain::Polkadot { .. } => String::from("polkadot"),
+            Relaychain::Kusama { .. } => String::from("kusama"),
+            Relaychain::Paseo { .. } => String::from("paseo"),
+            Relaychain::Custom { name, .. } => name.clone(),
+        }
     }
 
-    // TODO: make this endpoints configurables
-    pub fn sync_endpoint(&self) -> String {
-        String::from(match self {
-            Relaychain::Polkadot { .. } => "wss://polkadot-rpc.n.dwellir.com",
-            Relaychain::Kusama { .. } => "wss://kusama-rpc.n.dwellir.com",
-            Relaychain::Paseo { .. } => "wss://paseo-rpc.dwellir.com",
-        })
+    /// Value to use as `--chain` arg in the nodes (chain spec path or name).
+    pub fn chain_arg(&self) -> String {
+        match self {
+            Relaychain::Custom { spec, .. } => spec.chain_spec.clone(),
+            _ => self.as_chain_string(),
+        }
     }
 
-    pub fn rpc_endpoint(&self) -> String {
-        String::from(match self {
-            Relaychain::Polkadot { .. } => "wss://polkadot-rpc.n.dwellir.com",
-            Relaychain::Kusama { .. } => "wss://kusama-rpc.n.dwellir.com",
-            Relaychain::Paseo { .. } => "wss://paseo-rpc.dwellir.com",
-        })
+    /// Chain id, used as dir name in the db path.
+    pub fn chain_id(&self) -> String {
+        match self {
+            Relaychain::Kusama { .. } => String::from("ksmcc3"),
+            Relaychain::Custom { spec, .. } => spec.chain_id.clone(),
+            _ => self.as_chain_string(),
+        }
+    }
+
+    /// Endpoints to use as `--relay-chain-rpc-url` when we sync the parachains,
+    /// the `sync_url` (if any) followed by the rpc endpoints.
+    pub fn sync_endpoints(&self) -> Vec<String> {
+        let mut endpoints = self.rpc_endpoints();
+        if let Some(sync_url) = self.sync_url() {
+            endpoints.retain(|endpoint| endpoint != sync_url);
+            endpoints.insert(0, sync_url.to_string());
+        }
+        endpoints
+    }
+
+    /// Rpc endpoints of the live network (in order of preference), the configured ones
+    /// or the default (public) ones.
+    pub fn rpc_endpoints(&self) -> Vec<String> {
+        let maybe_rpc_endpoints = match self {
+            Relaychain::Kusama {
+                maybe_rpc_endpoints,
+                ..
+            }
+            | Relaychain::Polkadot {
+                maybe_rpc_endpoints,
+                ..
+            }
+            | Relaychain::Paseo {
+                maybe_rpc_endpoints,
+                ..
+            }
+            | Relaychain::Custom {
+                maybe_rpc_endpoints,
+                ..
+            } => maybe_rpc_endpoints,
+        };
+        if let Some(endpoints) = maybe_rpc_endpoints {
+            return endpoints.clone();
+        }
+
+        let defaults: &[&str] = match self {
+            Relaychain::Polkadot { .. } => &POLKADOT_RPCS,
+            Relaychain::Kusama { .. } => &KUSAMA_RPCS,
+            Relaychain::Paseo { .. } => &PASEO_RPCS,
+            Relaychain::Custom { .. } => &[],
+        };
+        defaults
+            .iter()
+            .map(|endpoint| endpoint.to_string())
+            .collect()
+    }
+
+    fn sync_url(&self) -> Option<&str> {
+        match self {
+            Relaychain::Kusama { maybe_sync_url, .. }
+            | Relaychain::Polkadot { maybe_sync_url, .. }
+            | Relaychain::Paseo { maybe_sync_url, .. }
+            | Relaychain::Custom { maybe_sync_url, .. } => maybe_sync_url.as_deref(),
+        }
     }
 
     pub fn context(&self) -> Context {
@@ -233,7 +371,8 @@ impl Relaychain {
         match self {
             Relaychain::Kusama { maybe_override, .. }
             | Relaychain::Polkadot { maybe_override, .. }
-            | Relaychain::Paseo { maybe_override, .. } => maybe_override.as_deref(),
+            | Relaychain::Paseo { maybe_override, .. }
+            | Relaychain::Custom { maybe_override, .. } => maybe_override.as_deref(),
         }
     }
 
@@ -241,15 +380,17 @@ impl Relaychain {
         match self {
             Relaychain::Paseo { .. } => 600,
             Relaychain::Kusama { .. } => 600,
-            _ => 2400,
+            Relaychain::Custom { spec, .. } => spec.epoch_duration,
+            _ => DEFAULT_EPOCH_DURATION,
         }
     }
 
-    pub fn at_block(&self) -> Option<u32> {
+    pub fn at_block(&self) -> Option<&BiteAt> {
         match self {
             Relaychain::Kusama { maybe_bite_at, .. }
             | Relaychain::Polkadot { maybe_bite_at, .. }
-            | Relaychain::Paseo { maybe_bite_at, .. } => maybe_bite_at.clone(),
+            | Relaychain::Paseo { maybe_bite_at, .. }
+            | Relaychain::Custom { maybe_bite_at, .. } => maybe_bite_at.as_ref(),
         }
     }
 }
@@ -259,59 +400,117 @@ pub enum Parachain {
     AssetHub {
         maybe_override: MaybeWasmOverridePath,
         maybe_bite_at: MaybeByteAt,
-        maybe_rpc_endpoint: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
     },
     Coretime {
         maybe_override: MaybeWasmOverridePath,
         maybe_bite_at: MaybeByteAt,
-        maybe_rpc_endpoint: MaybeSyncUrl,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
     },
     People {
         maybe_override: MaybeWasmOverridePath,
         maybe_bite_at: MaybeByteAt,
-        maybe_rpc_endpoint: MaybeSyncUrl,
-    }, // Bridge
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
+    },
+    BridgeHub {
+        maybe_override: MaybeWasmOverridePath,
+        maybe_bite_at: MaybeByteAt,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
+    },
+    Collectives {
+        maybe_override: MaybeWasmOverridePath,
+        maybe_bite_at: MaybeByteAt,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
+    },
 }
 
 impl Parachain {
     pub fn new(chain: &str) -> Self {
+        Self::new_with_values(chain, None, None, None)
+    }
+
+    pub fn new_with_values(
+        chain: &str,
+        maybe_override: MaybeWasmOverridePath,
+        maybe_bite_at: MaybeByteAt,
+        maybe_rpc_endpoints: MaybeRpcEndpoints,
+    ) -> Self {
         match chain {
             "coretime" => Parachain::Coretime {
-                maybe_override: None,
-                maybe_bite_at: None,
-                maybe_rpc_endpoint: None,
+                maybe_override,
+                maybe_bite_at,
+                maybe_rpc_endpoints,
             },
             "people" => Parachain::People {
-                maybe_override: None,
-                maybe_bite_at: None,
-                maybe_rpc_endpoint: None,
+                maybe_override,
+                maybe_bite_at,
+                maybe_rpc_endpoints,
+            },
+            "bridge-hub" => Parachain::BridgeHub {
+                maybe_override,
+                maybe_bite_at,
+                maybe_rpc_endpoints,
+            },
+            "collectives" => Parachain::Collectives {
+                maybe_override,
+                maybe_bite_at,
+                maybe_rpc_endpoints,
             },
             _ => Parachain::AssetHub {
-                maybe_override: None,
-                maybe_bite_at: None,
-                maybe_rpc_endpoint: None,
+                maybe_override,
+                maybe_bite_at,
+                maybe_rpc_endpoints,
             },
         }
     }
 
-    pub fn as_local_chain_string(&self, relay_part: &str) -> String {
-        let para_part = match self {
+    pub fn as_para_part(&self) -> &'static str {
+        match self {
             Parachain::AssetHub { .. } => "asset-hub",
             Parachain::Coretime { .. } => "coretime",
             Parachain::People { .. } => "people",
-        };
+            Parachain::BridgeHub { .. } => "bridge-hub",
+            Parachain::Collectives { .. } => "collectives",
+        }
+    }
 
-        format!("{para_part}-{relay_part}-local")
+    pub fn as_local_chain_string(&self, relay_part: &str) -> String {
+        format!("{}-{relay_part}-local", self.as_para_part())
     }
 
     pub fn as_chain_string(&self, relay_part: &str) -> String {
-        let para_part = match self {
-            Parachain::AssetHub { .. } => "asset-hub",
-            Parachain::Coretime { .. } => "coretime",
-            Parachain::People { .. } => "people",
-        };
+        format!("{}-{relay_part}", self.as_para_part())
+    }
+
+    /// Default (public) rpc endpoints of the parachain for the supplied relaychain,
+    /// in order of preference.
+    pub fn default_rpc_endpoints(&self, relay_part: &str) -> Vec<String> {
+        let mut endpoints = vec![match (self, relay_part) {
+            (Parachain::AssetHub { .. }, "polkadot") => AH_POLKADOT_RCP.to_string(),
+            (Parachain::AssetHub { .. }, "kusama") => AH_KUSAMA_RCP.to_string(),
+            _ => format!(
+                "https://{}-{relay_part}-rpc.n.dwellir.com",
+                self.as_para_part()
+            ),
+        }];
+
+        if ["polkadot", "kusama", "paseo"].contains(&relay_part) {
+            // ibp use `bridgehub` instead of `bridge-hub`
+            let ibp_para_part = self.as_para_part().replace("bridge-hub", "bridgehub");
+            endpoints.push(format!(
+                "wss://sys.ibp.network/{ibp_para_part}-{relay_part}"
+            ));
+        }
 
-        format!("{para_part}-{relay_part}")
+        endpoints
+    }
+
+    /// Name of the collator node, asset-hub keeps `collator` for backward compatibility.
+    pub fn collator_name(&self) -> String {
+        match self {
+            Parachain::AssetHub { .. } => String::from("collator"),
+            _ => format!("collator-{}", self.id()),
+        }
     }
 
     pub fn context(&self) -> Context {
@@ -321,8 +520,10 @@ impl Parachain {
     pub fn id(&self) -> u32 {
         match self {
             Parachain::AssetHub { .. } => 1000,
+            Parachain::Collectives { .. } => 1001,
+            Parachain::BridgeHub { .. } => 1002,
+            Parachain::People { .. } => 1004,
             Parachain::Coretime { .. } => 1005,
-            Parachain::People { .. } => 1001,
         }
     }
 
@@ -330,29 +531,45 @@ impl Parachain {
         match self {
             Parachain::AssetHub { maybe_override, .. }
             | Parachain::Coretime { maybe_override, .. }
-            | Parachain::People { maybe_override, .. } => maybe_override.as_deref(),
+            | Parachain::People { maybe_override, .. }
+            | Parachain::BridgeHub { maybe_override, .. }
+            | Parachain::Collectives { maybe_override, .. } => maybe_override.as_deref(),
         }
     }
 
-    pub fn at_block(&self) -> Option<u32> {
+    pub fn at_block(&self) -> Option<&BiteAt> {
         match self {
             Parachain::AssetHub { maybe_bite_at, .. }
             | Parachain::Coretime { maybe_bite_at, .. }
-            | Parachain::People { maybe_bite_at, .. } => maybe_bite_at.clone(),
+            | Parachain::People { maybe_bite_at, .. }
+            | Parachain::BridgeHub { maybe_bite_at, .. }
+            | Parachain::Collectives { maybe_bite_at, .. } => maybe_bite_at.as_ref(),
         }
     }
 
-    pub fn rpc_endpoint(&self) -> Option<&str> {
+    /// Rpc endpoints of the live network (in order of preference).
+    pub fn rpc_endpoints(&self) -> &[String] {
         match self {
             Parachain::AssetHub {
-                maybe_rpc_endpoint, ..
+                maybe_rpc_endpoints,
+                ..
             }
             | Parachain::Coretime {
-                maybe_rpc_endpoint, ..
+                maybe_rpc_endpoints,
+                ..
             }
             | Parachain::People {
-                maybe_rpc_endpoint, ..
-            } => maybe_rpc_endpoint.as_deref(),
+                maybe_rpc_endpoints,
+                ..
+            }
+            | Parachain::BridgeHub {
+                maybe_rpc_endpoints,
+                ..
+            }
+            | Parachain::Collectives {
+                maybe_rpc_endpoints,
+                ..
+            } => maybe_rpc_endpoints.as_deref().unwrap_or_default(),
         }
     }
 }
@@ -382,8 +599,13 @@ pub fn generate_network_config(
 
     let chain_spec_cmd = match network {
         Relaychain::Polkadot { .. } | Relaychain::Kusama { .. } => CMD_TPL,
-        Relaychain::Paseo { .. } => DEFAULT_CHAIN_SPEC_TPL_COMMAND,
+        Relaychain::Paseo { .. } | Relaychain::Custom { .. } => DEFAULT_CHAIN_SPEC_TPL_COMMAND,
     };
+    let alice_port = get_manifest().alice_port()?;
+    let collator_ports = paras
+        .iter()
+        .map(|para| get_manifest().collator_port(para))
+        .collect::<Result<Vec<_>, _>>()?;
 
     let network_builder = NetworkConfigBuilder::new().with_relaychain(|r| {
         let relaychain_builder = r
@@ -394,10 +616,7 @@ pub fn generate_network_config(
             // .with_default_args(vec![("-l", "babe=debug,grandpa=debug,runtime=debug,parachain::=debug,sub-authority-discovery=trace").into()])
             .with_default_args(vec![("-l", "runtime=trace").into()]);
 
-        let relaychain_builder = if let Ok(port) = env::var("ZOMBIE_BITE_RC_PORT") {
-            let rpc_port = port
-                .parse()
-                .expect("env var ZOMBIE_BITE_RC_PORT must be a valid u16");
+        let relaychain_builder = if let Some(rpc_port) = alice_port {
             relaychain_builder.with_validator(|node| node.with_name(ALICE).with_rpc_port(rpc_port))
         } else {
             relaychain_builder.with_validator(|node| node.with_name(ALICE))
@@ -409,14 +628,11 @@ pub fn generate_network_config(
         // .with_node(|node| node.with_name(DAVE))
     });
 
-    let network_builder = paras.iter().fold(network_builder, |builder, para| {
+    let network_builder = paras.iter().zip(collator_ports).fold(network_builder, |builder, (para, collator_port)| {
         println!("para: {:?}", para);
-        let (chain_part, id) = match para {
-            Parachain::AssetHub { .. } => ("asset-hub", para.id()),
-            Parachain::Coretime{ .. } => ("coretime", para.id()),
-            Parachain::People { .. } => ("people", para.id()),
-        };
+        let (chain_part, id) = (para.as_para_part(), para.id());
         let chain = format!("{}-{}",chain_part, relay_chain);
+        let collator_name = para.collator_name();
 
         builder.with_parachain(|p| {
             p.with_id(id)
@@ -425,14 +641,12 @@ pub fn generate_network_config(
                 .with_chain_spec_command(chain_spec_cmd)
                 .with_collator(|c| {
                     // TODO: use single collator for now
-                    // c.with_name(&format!("col-{}",id))
-                    let col_builder = c.with_name("collator")
+                    let col_builder = c.with_name(collator_name.as_str())
                     .with_args(vec![
                         ("-l", "aura=debug,runtime=trace,cumulus-consensus=trace,consensus::common=trace,parachain::collation-generation=trace,parachain::collator-protocol=trace,parachain=debug,basic-authorship=trace").into(),
                         "--force-authoring".into()
                     ]);
-                    if let Ok(port) = env::var("ZOMBIE_BITE_AH_PORT") {
-                        let rpc_port = port.parse().expect("env var ZOMBIE_BITE_AH_PORT must be a valid u16");
+                    if let Some(rpc_port) = collator_port {
                         col_builder.with_rpc_port(rpc_port)
                     } else {
                         col_builder
@@ -472,6 +686,76 @@ mod test {
         assert_eq!(parachain.as_str(), "asset-hub-kusama-local");
     }
 
+    #[test]
+    fn custom_relay_ok() {
+        let spec_path = "/tmp/zombie-bite-custom-relay-spec.json";
+        std::fs::write(spec_path, r#"{"name": "Westend", "id": "westend2"}"#).unwrap();
+        let relay = Relaychain::new_custom(
+            "westend",
+            CustomRelaySpec {
+                chain_spec: spec_path.to_string(),
+                epoch_duration: 600,
+                chain_id: chain_id_from_spec(spec_path).unwrap(),
+            },
+            None,
+            None,
+            Some(vec![String::from("wss://westend-rpc.polkadot.io")]),
+            Some(BiteAt::Number(100)),
+        );
+
+        assert_eq!(relay.as_chain_string(), "westend");
+        assert_eq!(relay.chain_arg(), spec_path);
+        assert_eq!(relay.chain_id(), "westend2");
+        assert_eq!(relay.epoch_duration(), 600);
+        assert_eq!(relay.at_block(), Some(&BiteAt::Number(100)));
+        assert_eq!(Relaychain::new("kusama").chain_id(), "ksmcc3");
+        // unknown networks are custom
+        assert!(Relaychain::new("rococo").is_custom());
+    }
+
+    #[test]
+    fn relay_endpoints_ok() {
+        let relay = Relaychain::new("kusama");
+        assert_eq!(relay.rpc_endpoints().len(), KUSAMA_RPCS.len());
+
+        let relay = Relaychain::new_with_values(
+            "polkadot",
+            None,
+            Some(String::from("wss://b")),
+            Some(vec![String::from("wss://a"), String::from("wss://b")]),
+            None,
+        );
+        assert_eq!(relay.rpc_endpoints(), vec!["wss://a", "wss://b"]);
+        // sync url goes first
+        assert_eq!(relay.sync_endpoints(), vec!["wss://b", "wss://a"]);
+    }
+
+    #[test]
+    fn system_paras_ok() {
+        let people = Parachain::new("people");
+        assert_eq!(people.id(), 1004);
+        assert_eq!(people.collator_name(), "collator-1004");
+        assert_eq!(
+            people.default_rpc_endpoints("kusama"),
+            vec![
+                "https://people-kusama-rpc.n.dwellir.com",
+                "wss://sys.ibp.network/people-kusama"
+            ]
+        );
+        let ah = Parachain::new("asset-hub");
+        assert_eq!(ah.collator_name(), "collator");
+        assert_eq!(ah.default_rpc_endpoints("polkadot")[0], AH_POLKADOT_RCP);
+        // paseo get its own endpoints
+        assert_eq!(
+            ah.default_rpc_endpoints("paseo")[0],
+            "https://asset-hub-paseo-rpc.n.dwellir.com"
+        );
+        assert_eq!(
+            Parachain::new("bridge-hub").as_chain_string("polkadot"),
+            "bridge-hub-polkadot"
+        );
+    }
+
     #[tokio::test]
     async fn spec() {
         let config = generate_network_config(
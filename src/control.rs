@@ -0,0 +1,329 @@
+//! Local control api (unix socket in the base path) of the running network.
+//!
+//! The protocol is line delimited json, one request per line (e.g. `{"cmd":"restart","node":"alice"}`)
+//! and one response per request (`{"ok":true,"result":...}`). The `stop.txt` file is still
+//! supported as fallback.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Subcommand;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+use zombienet_sdk::{LocalFileSystem, Network};
+
+use crate::config::Step;
+use crate::error::BiteError;
+use crate::monit::PausedNodes;
+use crate::network_info::NetworkInfo;
+use crate::snapshot::{generate_snap, snap_file, SnapSource};
+use crate::store::Store;
+
+/// Socket (in the base path) to control the running network.
+pub const CONTROL_SOCKET_FILE: &str = "zombie-bite.sock";
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+    /// Info of the running network (nodes, ports, logs, pids).
+    Status,
+    /// Stop the network, generating the artifacts for the next step (unless `--no-artifacts` is used).
+    Stop {
+        #[arg(long, default_value_t = false)]
+        #[serde(default)]
+        no_artifacts: bool,
+    },
+    /// Restart the node.
+    Restart { node: String },
+    /// Pause the node (SIGSTOP).
+    Pause { node: String },
+    /// Resume a paused node (SIGCONT).
+    Resume { node: String },
+    /// Snapshot the db of all the nodes (paused while the snapshot is generated).
+    Snapshot,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub fn ok(result: Value) -> Self {
+        Self {
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn err(error: impl ToString) -> Self {
+        Self {
+            ok: false,
+            result: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// Request received by the server, with the channel to send the response.
+pub type ControlMessage = (Request, oneshot::Sender<Response>);
+
+pub fn socket_path(base_path: &Path) -> PathBuf {
+    base_path.join(CONTROL_SOCKET_FILE)
+}
+
+/// Listen in the socket and forward the requests to `tx`.
+pub async fn serve(socket: PathBuf, tx: mpsc::Sender<ControlMessage>) -> Result<(), BiteError> {
+    // remove the socket of a previous run
+    let _ = fs::remove_file(&socket).await;
+    let listener = UnixListener::bind(&socket)
+        .map_err(|e| BiteError::io("binding control socket", &socket, e))?;
+    info!("🎛️  control socket listening at {}", socket.display());
+
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|e| BiteError::io("accepting connection in", &socket, e))?;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, tx).await {
+                debug!("control connection closed with error: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    tx: mpsc::Sender<ControlMessage>,
+) -> Result<(), anyhow::Error> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                debug!("control request: {request:?}");
+                let (response_tx, response_rx) = oneshot::channel();
+                tx.send((request, response_tx)).await?;
+                response_rx
+                    .await
+                    .unwrap_or_else(|_| Response::err("network is stopping"))
+            }
+            Err(e) => Response::err(format!("invalid request: {e}")),
+        };
+        writer
+            .write_all(format!("{}\n", serde_json::to_string(&response)?).as_bytes())
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Handle the requests that don't stop the network, the paused nodes are shared with the monitor.
+pub async fn handle(
+    request: &Request,
+    network: &Network<LocalFileSystem>,
+    base_path: &Path,
+    step: Step,
+    paused: &PausedNodes,
+) -> Result<Value, anyhow::Error> {
+    match request {
+        Request::Status => Ok(serde_json::to_value(
+            NetworkInfo::from_network(network, base_path).await?,
+        )?),
+        Request::Restart { node } => {
+            network.get_node(node)?.restart(None).await?;
+            paused.resume(node);
+            warn!("{node} restarted (control api)");
+            Ok(json!({ "restarted": node }))
+        }
+        Request::Pause { node } => {
+            let target = network.get_node(node)?;
+            // before pausing, so the monitor doesn't check it in between
+            paused.pause(node);
+            if let Err(e) = target.pause().await {
+                paused.resume(node);
+                return Err(e);
+            }
+            info!("{node} paused (control api)");
+            Ok(json!({ "paused": node }))
+        }
+        Request::Resume { node } => {
+            network.get_node(node)?.resume().await?;
+            paused.resume(node);
+            info!("{node} resumed (control api)");
+            Ok(json!({ "resumed": node }))
+        }
+        Request::Snapshot => {
+            paused.set_snapshot(true);
+            let res = snapshot(network, base_path, step, paused).await;
+            paused.set_snapshot(false);
+            res
+        }
+        Request::Stop { .. } => Err(anyhow::anyhow!("stop should be handled by the caller")),
+    }
+}
+
+/// Pause all the nodes, snapshot the dbs and resume them (the ones paused with the
+/// control api stay paused).
+async fn snapshot(
+    network: &Network<LocalFileSystem>,
+    base_path: &Path,
+    step: Step,
+    paused: &PausedNodes,
+) -> Result<Value, anyhow::Error> {
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let step_dir = base_path.join(step.dir());
+    let snaps_dir = step_dir.join("snapshots").join(ts.to_string());
+    fs::create_dir_all(&snaps_dir)
+        .await
+        .map_err(|e| BiteError::io("creating", &snaps_dir, e))?;
+
+    let store = &Store::for_base_path(base_path);
+    let nodes = network.nodes();
+    // chain and best block (before the pause) of each node, for the snapshots manifests
+    let mut sources = vec![];
+    for node in &nodes {
+        let chain = network
+            .parachains()
+            .into_iter()
+            .find(|para| para.collators().iter().any(|c| c.name() == node.name()))
+            .map(|para| para.chain_id().unwrap_or(para.unique_id()).to_string())
+            .unwrap_or_else(|| network.relaychain().chain().to_string());
+        let best = node.reports("block_height{status=\"best\"}").await.ok();
+        sources.push(SnapSource {
+            block_number: best.map(|best| best as u64),
+            ..SnapSource::new(chain)
+        });
+    }
+
+    let snaps =
+        async {
+            for node in &nodes {
+                node.pause().await?;
+            }
+            let snaps: Vec<PathBuf> = nodes
+                .iter()
+                .map(|node| snaps_dir.join(snap_file(node.name())))
+                .collect();
+            try_join_all(nodes.iter().zip(&snaps).zip(sources).map(
+                |((node, snap_path), source)| {
+                    let data_path = step_dir.join(node.name()).join("data");
+                    async move {
+                        generate_snap(
+                            &data_path.to_string_lossy(),
+                            &snap_path.to_string_lossy(),
+                            source,
+                        )
+                        .await?;
+                        store.put(snap_path).await
+                    }
+                },
+            ))
+            .await?;
+            Ok::<_, anyhow::Error>(snaps)
+        }
+        .await;
+
+    // always resume the nodes (even if the snapshot failed)
+    for node in nodes.iter().filter(|node| !paused.is_paused(node.name())) {
+        if let Err(e) = node.resume().await {
+            warn!("Error resuming {}: {e}", node.name());
+        }
+    }
+    let snaps = snaps?;
+
+    Ok(json!({ "snapshots": snaps }))
+}
+
+/// Send the request to the running network (client side).
+pub async fn send(socket: &Path, request: &Request) -> Result<Response, BiteError> {
+    let stream = UnixStream::connect(socket)
+        .await
+        .map_err(|e| BiteError::io("connecting (is the network running?) to", socket, e))?;
+    let (reader, mut writer) = stream.into_split();
+    let payload = serde_json::to_string(request)
+        .map_err(|e| BiteError::Config(format!("invalid request: {e}")))?;
+    writer
+        .write_all(format!("{payload}\n").as_bytes())
+        .await
+        .map_err(|e| BiteError::io("writing to", socket, e))?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .map_err(|e| BiteError::io("reading from", socket, e))?
+        .ok_or_else(|| BiteError::Io(format!("no response from {}", socket.display())))?;
+    serde_json::from_str(&line)
+        .map_err(|e| BiteError::Io(format!("invalid response from {}: {e}", socket.display())))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests_should_roundtrip() {
+        let request: Request = serde_json::from_str(r#"{"cmd":"stop"}"#).unwrap();
+        assert_eq!(
+            request,
+            Request::Stop {
+                no_artifacts: false
+            }
+        );
+        let request: Request = serde_json::from_str(r#"{"cmd":"restart","node":"alice"}"#).unwrap();
+        assert_eq!(
+            request,
+            Request::Restart {
+                node: String::from("alice")
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&Request::Status).unwrap(),
+            r#"{"cmd":"status"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn server_should_forward_requests() {
+        let socket =
+            std::env::temp_dir().join(format!("zombie-bite-control-{}.sock", std::process::id()));
+        let (tx, mut rx) = mpsc::channel(1);
+        tokio::spawn(serve(socket.clone(), tx));
+        tokio::spawn(async move {
+            while let Some((request, response_tx)) = rx.recv().await {
+                let _ = response_tx.send(Response::ok(serde_json::to_value(&request).unwrap()));
+            }
+        });
+
+        // wait the server to bind
+        let mut response = None;
+        for _ in 0..50 {
+            if let Ok(r) = send(&socket, &Request::Snapshot).await {
+                response = Some(r);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let response = response.expect("server should reply");
+        assert!(response.ok);
+        assert_eq!(response.result, Some(json!({"cmd": "snapshot"})));
+        let _ = fs::remove_file(&socket).await;
+    }
+}
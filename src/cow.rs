@@ -0,0 +1,181 @@
+//! Copy-on-write clone of the dbs dirs (uncompressed snapshots), so the nodes can be spawned
+//! without extracting the snapshot.
+//!
+//! Each file is cloned with a reflink (btrfs / xfs), or hardlinked if the file is immutable
+//! (rocksdb sst / blob files are never modified, only removed) or copied as fallback.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use tracing::trace;
+
+/// Extensions of the db files that are never modified (safe to hardlink).
+const IMMUTABLE_EXTENSIONS: [&str; 2] = ["sst", "blob"];
+
+/// Files cloned by each method.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CloneStats {
+    pub reflinked: usize,
+    pub hardlinked: usize,
+    pub copied: usize,
+    pub bytes_copied: u64,
+}
+
+impl fmt::Display for CloneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} reflinked, {} hardlinked, {} copied ({} MiB)",
+            self.reflinked,
+            self.hardlinked,
+            self.copied,
+            self.bytes_copied / (1024 * 1024)
+        )
+    }
+}
+
+/// Clone the `from` dir in `to` (that should not exist), see the module docs.
+pub async fn clone_dir(from: &Path, to: &Path) -> io::Result<CloneStats> {
+    let (from, to) = (from.to_path_buf(), to.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        let mut stats = CloneStats::default();
+        // don't try the reflinks again if the fs doesn't support them
+        let mut reflinks = true;
+        clone_dir_sync(&from, &to, &mut reflinks, &mut stats)?;
+        Ok(stats)
+    })
+    .await?
+}
+
+fn clone_dir_sync(
+    from: &Path,
+    to: &Path,
+    reflinks: &mut bool,
+    stats: &mut CloneStats,
+) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (src, dest) = (entry.path(), to.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            clone_dir_sync(&src, &dest, reflinks, stats)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(&src)?, &dest)?;
+        } else {
+            clone_file(&src, &dest, reflinks, stats)?;
+        }
+    }
+    Ok(())
+}
+
+fn clone_file(
+    src: &Path,
+    dest: &Path,
+    reflinks: &mut bool,
+    stats: &mut CloneStats,
+) -> io::Result<()> {
+    if *reflinks {
+        match reflink(src, dest) {
+            Ok(_) => {
+                stats.reflinked += 1;
+                return Ok(());
+            }
+            Err(e) => {
+                trace!("reflinks not supported ({e}), falling back to hardlinks / copies");
+                *reflinks = false;
+                let _ = fs::remove_file(dest);
+            }
+        }
+    }
+
+    if is_immutable(src) && fs::hard_link(src, dest).is_ok() {
+        stats.hardlinked += 1;
+        return Ok(());
+    }
+
+    stats.bytes_copied += fs::copy(src, dest)?;
+    stats.copied += 1;
+    Ok(())
+}
+
+fn is_immutable(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| IMMUTABLE_EXTENSIONS.iter().any(|e| ext == *e))
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let src = File::open(src)?;
+    let dest = File::create(dest)?;
+    // SAFETY: both fds are valid (and open) during the call
+    let res = unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks are only supported in linux",
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    #[tokio::test]
+    async fn clone_should_link_immutable_files() {
+        let dir = std::env::temp_dir().join(format!("zombie-bite-cow-{}", std::process::id()));
+        let db = dir.join("snap.d").join("data").join("chains").join("db");
+        std::fs::create_dir_all(&db).unwrap();
+        std::fs::write(db.join("000042.sst"), b"immutable").unwrap();
+        std::fs::write(db.join("MANIFEST-000001"), b"mutable").unwrap();
+
+        let to = dir.join("alice").join("data");
+        let stats = clone_dir(&dir.join("snap.d").join("data"), &to)
+            .await
+            .unwrap();
+        let cloned = to.join("chains").join("db");
+        assert_eq!(
+            std::fs::read(cloned.join("000042.sst")).unwrap(),
+            b"immutable"
+        );
+        assert_eq!(
+            std::fs::read(cloned.join("MANIFEST-000001")).unwrap(),
+            b"mutable"
+        );
+        if stats.reflinked == 0 {
+            assert_eq!(stats.hardlinked, 1);
+            assert_eq!(stats.copied, 1);
+            assert_eq!(
+                std::fs::metadata(cloned.join("000042.sst"))
+                    .unwrap()
+                    .nlink(),
+                2
+            );
+        }
+
+        // writes in the clone don't change the snapshot
+        std::fs::write(cloned.join("MANIFEST-000001"), b"changed").unwrap();
+        assert_eq!(
+            std::fs::read(db.join("MANIFEST-000001")).unwrap(),
+            b"mutable"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
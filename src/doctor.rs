@@ -0,0 +1,342 @@
+//! Preflight checks (`doctor`), to fail early (and with a clear report) if the environment
+//! is not ready to bite/spawn instead of failing deep in the sync or spawn.
+
+use std::{
+    io::ErrorKind,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tokio::fs;
+use tracing::debug;
+
+use crate::config::{Parachain, Relaychain};
+use crate::manifest::get_manifest;
+use crate::snapshot::Codec;
+
+/// Command the checks are run for, each one needs different binaries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Bite,
+    Spawn,
+}
+
+/// Binaries used by zombie-bite, and the flows that require them (the others are optional).
+const BINARIES: [(&str, &[Flow]); 5] = [
+    ("doppelganger", &[Flow::Bite]),
+    ("doppelganger-parachain", &[Flow::Bite]),
+    ("polkadot", &[Flow::Spawn]),
+    ("polkadot-parachain", &[Flow::Spawn]),
+    ("chain-spec-generator", &[]),
+];
+
+const GIB: u64 = 1024 * 1024 * 1024;
+/// Expansion of the (gzip) snapshots when they are extracted to spawn the nodes.
+const SNAP_EXPANSION: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn colored(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "\x1b[92mPASS\x1b[0m",
+            CheckStatus::Warn => "\x1b[93mWARN\x1b[0m",
+            CheckStatus::Fail => "\x1b[91mFAIL\x1b[0m",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        !self.checks.iter().any(|c| c.status == CheckStatus::Fail)
+    }
+
+    pub fn print(&self) {
+        let width = self
+            .checks
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or_default()
+            .max(5);
+        println!();
+        println!("\t{:<width$}  STATUS  DETAIL", "CHECK");
+        for check in &self.checks {
+            println!(
+                "\t{:<width$}  {}    {}",
+                check.name,
+                check.status.colored(),
+                check.detail
+            );
+        }
+        println!();
+    }
+}
+
+/// Rough disk needed (in bytes) to bite the chains, synced dbs (with the default state pruning)
+/// plus the generated snapshots.
+pub fn bite_disk_estimate(relaychain: &Relaychain, paras: &[Parachain]) -> u64 {
+    let relay_gib = match relaychain {
+        Relaychain::Polkadot { .. } => 150,
+        Relaychain::Kusama { .. } => 200,
+        Relaychain::Paseo { .. } | Relaychain::Custom { .. } => 50,
+    };
+    let paras_gib: u64 = paras
+        .iter()
+        .map(|para| match (para, relaychain) {
+            (
+                Parachain::AssetHub { .. },
+                Relaychain::Polkadot { .. } | Relaychain::Kusama { .. },
+            ) => 60,
+            _ => 15,
+        })
+        .sum();
+
+    (relay_gib + paras_gib) * GIB
+}
+
+/// Disk already used (in bytes) by `path`, e.g. to discount the synced dbs when resuming a bite.
+pub fn used_disk(path: &Path) -> u64 {
+    Command::new("du")
+        .arg("-sk")
+        .arg(path)
+        .output()
+        .ok()
+        .and_then(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout.split_whitespace().next()?.parse::<u64>().ok()
+        })
+        .map(|kb| kb * 1024)
+        .unwrap_or_default()
+}
+
+/// Disk needed (in bytes) to spawn from the artifacts in `from_dir` (the snapshots are
+/// extracted for each node).
+pub async fn spawn_disk_estimate(from_dir: &Path) -> u64 {
+    let mut snaps_size = 0;
+    if let Ok(mut entries) = fs::read_dir(from_dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if Codec::ALL
+                .iter()
+                .any(|codec| name.ends_with(&format!("-snap.{}", codec.extension())))
+            {
+                snaps_size += entry.metadata().await.map(|m| m.len()).unwrap_or_default();
+            }
+        }
+    }
+
+    snaps_size * SNAP_EXPANSION
+}
+
+/// Run all the checks for the `flows`, `required_disk` is the estimate (in bytes) for the
+/// command to run.
+pub async fn doctor(base_path: &Path, required_disk: u64, flows: &[Flow]) -> Report {
+    let mut checks: Vec<Check> = BINARIES
+        .iter()
+        .map(|(bin, required_by)| {
+            check_binary(bin, required_by.iter().any(|flow| flows.contains(flow)))
+        })
+        .collect();
+
+    let existing = nearest_existing(base_path);
+    checks.push(check_writable(base_path, existing.as_deref()).await);
+    checks.push(check_disk(existing.as_deref(), required_disk));
+
+    let manifest = get_manifest();
+    let mut ports = vec![
+        (String::from("alice"), manifest.alice_port()),
+        (String::from("bob"), manifest.bob_port()),
+    ];
+    // spawn doesn't set the paras, asset-hub is the default one
+    let paras = if manifest.paras.is_empty() {
+        vec![Parachain::new("asset-hub")]
+    } else {
+        manifest
+            .paras
+            .iter()
+            .map(|para| Parachain::new(&para.name))
+            .collect()
+    };
+    for para in &paras {
+        ports.push((para.collator_name(), manifest.collator_port(para)));
+    }
+    for (name, maybe_port) in ports {
+        match maybe_port {
+            Ok(Some(port)) => checks.push(check_port(&name, port)),
+            Ok(None) => {}
+            Err(e) => checks.push(Check::new(
+                format!("port ({name})"),
+                CheckStatus::Fail,
+                e.to_string(),
+            )),
+        }
+    }
+
+    Report { checks }
+}
+
+fn check_binary(bin: &str, required: bool) -> Check {
+    let name = format!("binary {bin}");
+    match Command::new(bin).arg("--version").output() {
+        Ok(output) => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = version.lines().next().unwrap_or_default().trim();
+            if output.status.success() && !version.is_empty() {
+                Check::new(name, CheckStatus::Pass, version)
+            } else {
+                Check::new(
+                    name,
+                    CheckStatus::Warn,
+                    format!("found, but `{bin} --version` failed ({})", output.status),
+                )
+            }
+        }
+        Err(e) => {
+            let detail = if e.kind() == ErrorKind::NotFound {
+                String::from("not found in PATH")
+            } else {
+                format!("can't run: {e}")
+            };
+            if required {
+                Check::new(name, CheckStatus::Fail, detail)
+            } else {
+                Check::new(name, CheckStatus::Warn, format!("{detail} (optional)"))
+            }
+        }
+    }
+}
+
+/// The base path (or the dir where will be created) should be writable.
+async fn check_writable(base_path: &Path, existing: Option<&Path>) -> Check {
+    let name = "base path writable";
+    let Some(dir) = existing else {
+        return Check::new(name, CheckStatus::Fail, "no existing parent dir");
+    };
+    let probe = dir.join(".zombie-bite-doctor");
+    match fs::write(&probe, b"").await {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe).await;
+            Check::new(name, CheckStatus::Pass, base_path.to_string_lossy())
+        }
+        Err(e) => Check::new(
+            name,
+            CheckStatus::Fail,
+            format!("can't write in {}: {e}", dir.display()),
+        ),
+    }
+}
+
+fn check_disk(existing: Option<&Path>, required_disk: u64) -> Check {
+    let name = "free disk";
+    let Some(dir) = existing else {
+        return Check::new(name, CheckStatus::Fail, "no existing parent dir");
+    };
+    match available_disk(dir) {
+        Some(available) if available >= required_disk => Check::new(
+            name,
+            CheckStatus::Pass,
+            format!(
+                "{} GiB available (~{} GiB needed)",
+                available / GIB,
+                required_disk.div_ceil(GIB)
+            ),
+        ),
+        Some(available) => Check::new(
+            name,
+            CheckStatus::Fail,
+            format!(
+                "{} GiB available, but ~{} GiB needed",
+                available / GIB,
+                required_disk.div_ceil(GIB)
+            ),
+        ),
+        None => Check::new(name, CheckStatus::Warn, "can't get the free space (`df`)"),
+    }
+}
+
+fn check_port(name: &str, port: u16) -> Check {
+    let check_name = format!("port {port} ({name})");
+    match TcpListener::bind(("0.0.0.0", port)) {
+        Ok(_) => Check::new(check_name, CheckStatus::Pass, "free"),
+        Err(e) => Check::new(check_name, CheckStatus::Fail, format!("not available: {e}")),
+    }
+}
+
+/// The path or the nearest ancestor that exist.
+fn nearest_existing(path: &Path) -> Option<PathBuf> {
+    let path = if path.is_relative() {
+        std::env::current_dir().ok()?.join(path)
+    } else {
+        path.to_path_buf()
+    };
+    path.ancestors().find(|p| p.exists()).map(Path::to_path_buf)
+}
+
+/// Available space (in bytes) in the filesystem of `path`.
+fn available_disk(path: &Path) -> Option<u64> {
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    debug!("df: {stdout}");
+    parse_df_available(&stdout)
+}
+
+// Filesystem 1024-blocks Used Available Capacity Mounted on
+fn parse_df_available(df_output: &str) -> Option<u64> {
+    let line = df_output.lines().nth(1)?;
+    let available_kb: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb * 1024)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_df_should_work() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/nvme0n1p2   490617784 301122428 164499124      65% /\n";
+        assert_eq!(parse_df_available(output), Some(164499124 * 1024));
+        assert_eq!(parse_df_available("Filesystem"), None);
+    }
+
+    #[test]
+    fn missing_binary_should_fail_only_if_required() {
+        let check = check_binary("zombie-bite-not-a-binary", true);
+        assert_eq!(check.status, CheckStatus::Fail);
+        let check = check_binary("zombie-bite-not-a-binary", false);
+        assert_eq!(check.status, CheckStatus::Warn);
+
+        let report = Report {
+            checks: vec![check],
+        };
+        assert!(report.passed());
+    }
+}
@@ -1,12 +1,10 @@
 #![allow(dead_code)]
 // TODO: don't allow dead_code
 
-use anyhow::anyhow;
-use futures::future::try_join_all;
-use futures::FutureExt;
 use serde_json::json;
 // use serde_json::json;
-use std::fs::{read_to_string, File};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_to_string;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -16,12 +14,10 @@ use tokio::fs;
 use zombienet_sdk::NetworkConfig;
 
 use codec::Encode;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use tar::Builder;
+use futures::future::try_join_all;
 
 use tracing::debug;
-use tracing::{info, trace};
+use tracing::{info, trace, warn};
 use zombienet_configuration::NetworkConfigBuilder;
 use zombienet_orchestrator::network::Network;
 use zombienet_orchestrator::Orchestrator;
@@ -33,17 +29,28 @@ use zombienet_provider::Provider;
 use zombienet_support::fs::local::LocalFileSystem;
 
 use crate::utils::{
-    get_header_from_block, get_random_port, localize_config, para_head_key, HeadData,
+    get_block_hash, get_random_port, header_number, localize_config, move_file, para_head_key,
+    HeadData,
 };
 
+use crate::bite_at::para_header_for_relay_block;
+use crate::checkpoint::{phase, BiteState, BiteTarget};
 use crate::config::{get_state_pruning_config, Context, Parachain, Relaychain, Step};
-use crate::overrides::{generate_default_overrides_for_para, generate_default_overrides_for_rc};
+use crate::error::BiteError;
+use crate::manifest::get_manifest;
+use crate::overrides::{
+    generate_default_overrides_for_para, generate_default_overrides_for_rc, CustomOverridesPaths,
+};
+use crate::report::{move_events_log, Event, EventLog};
+use crate::snapshot::{
+    find_snap, generate_snap, prepare_spawn_config, snap_dir, snap_file, SnapManifest, SnapSource,
+};
+use crate::store::Store;
 use crate::sync::{sync_para, sync_relay_only};
 
-use std::env;
-
 const PORTS_FILE: &str = "ports.json";
-const READY_FILE: &str = "ready.json";
+/// File (in the base path) with the start blocks of the chains.
+pub const READY_FILE: &str = "ready.json";
 
 #[derive(Debug, Clone)]
 struct ChainArtifact {
@@ -52,13 +59,19 @@ struct ChainArtifact {
     spec_path: String,
     snap_path: String,
     override_wasm: Option<String>,
+    /// Parachain of the artifact (None for the relaychain)
+    para: Option<Parachain>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn doppelganger_inner(
     global_base_dir: PathBuf,
     relay_chain: Relaychain,
     paras_to: Vec<Parachain>,
     database: &str,
+    validators: &[String],
+    custom_overrides: &CustomOverridesPaths,
+    resume: bool,
 ) -> Result<(), anyhow::Error> {
     // Star the node and wait until finish (with temp dir managed by us)
     info!(
@@ -71,178 +84,313 @@ pub async fn doppelganger_inner(
     let provider = NativeProvider::new(filesystem.clone());
 
     // ensure the base path exist
-    fs::create_dir_all(&global_base_dir).await.unwrap();
+    fs::create_dir_all(&global_base_dir)
+        .await
+        .map_err(|e| BiteError::io("creating", &global_base_dir, e))?;
 
     // add `/bite` to global base
-    let fixed_base_dir = global_base_dir.canonicalize().unwrap().join("bite");
+    let fixed_base_dir = global_base_dir
+        .canonicalize()
+        .map_err(|e| BiteError::io("resolving", &global_base_dir, e))?
+        .join("bite");
 
     let base_dir_str = fixed_base_dir.to_string_lossy();
     let ns = provider
         .create_namespace_with_base_dir(fixed_base_dir.as_path())
         .await
-        .unwrap();
+        .map_err(|e| BiteError::Sync(format!("Error creating namespace at {base_dir_str}: {e}")))?;
 
     let _relaychain_rpc_random_port = get_random_port().await;
 
+    let relay_part = relay_chain.as_chain_string();
+    let mut bite_at = BTreeMap::new();
+    if let Some(at_block) = relay_chain.at_block() {
+        bite_at.insert(relay_part.clone(), at_block.to_string());
+    }
+    for para in &paras_to {
+        if let Some(at_block) = para.at_block() {
+            bite_at.insert(para.as_chain_string(&relay_part), at_block.to_string());
+        }
+    }
+    let target = BiteTarget {
+        chain: relay_part.clone(),
+        paras: paras_to
+            .iter()
+            .map(|para| para.as_chain_string(&relay_part))
+            .collect(),
+        bite_at,
+    };
+    // completed phases are skipped (if we are resuming)
+    let mut state = BiteState::load(&fixed_base_dir, resume, target).await?;
+    // snapshots are moved to the (content addressed) store
+    let store = Store::for_base_path(&global_base_dir);
+
+    // resolve the relaychain block first, since the paras use it to pick the matching block
+    let rc_header_phase = phase(&relay_part, "target-header");
+    let maybe_rc_header = match state.output::<Option<serde_json::Value>>(&rc_header_phase) {
+        Some(header) => header,
+        None => {
+            let header = if let Some(at_block) = relay_chain.at_block() {
+                Some(
+                    at_block
+                        .resolve_header(&relay_chain.rpc_endpoints())
+                        .await?,
+                )
+            } else {
+                None
+            };
+            state.complete(&rc_header_phase, &header).await?;
+            header
+        }
+    };
+
     // Parachain sync
     let mut syncs = vec![];
     for para in &paras_to {
-        let para_default_overrides_path =
-            generate_default_overrides_for_para(&base_dir_str, para, &relay_chain).await;
-        let info_path = format!("{base_dir_str}/para-{}.txt", para.id());
+        let para_chain = para.as_chain_string(&relay_part);
+        if state.is_done(&phase(&para_chain, "sync")) {
+            info!("⏭️  {para_chain} already synced, skipping");
+            continue;
+        }
 
-        let maybe_target_header_path = if let Some(at_block) = para.at_block() {
-            let para_rpc = para
-                .rpc_endpoint()
-                .expect("rpc for parachain should be set. qed");
-            let header = get_header_from_block(at_block, para_rpc).await?;
+        let header_phase = phase(&para_chain, "target-header");
+        let maybe_header = match state.output::<Option<serde_json::Value>>(&header_phase) {
+            Some(header) => header,
+            None => {
+                let header = match (para.at_block(), &maybe_rc_header) {
+                    (Some(at_block), _) => {
+                        Some(at_block.resolve_header(para.rpc_endpoints()).await?)
+                    }
+                    (None, Some(rc_header)) => Some(
+                        para_header_for_relay_block(
+                            header_number(rc_header)?,
+                            para.rpc_endpoints(),
+                        )
+                        .await?,
+                    ),
+                    _ => None,
+                };
+                state.complete(&header_phase, &header).await?;
+                header
+            }
+        };
+
+        let para_default_overrides_path = generate_default_overrides_for_para(
+            &base_dir_str,
+            para,
+            &relay_chain,
+            custom_overrides.paras.get(&para.id()).map(PathBuf::as_path),
+            maybe_header.as_ref().map(header_number).transpose()?,
+        )
+        .await?;
+        let info_path = format!("{base_dir_str}/para-{}.txt", para.id());
 
-            let target_header_path = format!("{base_dir_str}/para-header.json");
+        let maybe_target_header_path = if let Some(header) = maybe_header {
+            let target_header_path = format!("{base_dir_str}/para-{}-header.json", para.id());
             fs::write(&target_header_path, serde_json::to_string_pretty(&header)?)
                 .await
-                .expect("create target head json should works");
+                .map_err(|e| BiteError::io("writing", &target_header_path, e))?;
             Some(target_header_path)
         } else {
             None
         };
 
-        syncs.push(
+        // spawn each sync in a task, a failure in one of them should not stop the others
+        let ns = ns.clone();
+        let chain = para_chain.clone();
+        let relay_chain_arg = relay_chain.chain_arg();
+        let sync_endpoints = relay_chain.sync_endpoints();
+        let database = database.to_string();
+        let sync = tokio::spawn(async move {
             sync_para(
-                ns.clone(),
+                ns,
                 "doppelganger-parachain",
-                para.as_chain_string(&relay_chain.as_chain_string()),
-                relay_chain.as_chain_string(),
-                relay_chain.sync_endpoint(),
+                chain,
+                relay_chain_arg,
+                sync_endpoints,
                 para_default_overrides_path,
                 info_path,
                 maybe_target_header_path,
-                database,
+                &database,
             )
-            .boxed(),
-        );
+            .await
+            .map(|(_sync_node, sync_db_path, sync_chain, sync_head_path)| {
+                (sync_db_path, sync_chain, sync_head_path)
+            })
+        });
+        syncs.push((para_chain, sync));
     }
 
-    let res = try_join_all(syncs).await.unwrap();
+    // record the completed syncs (even if other fails), so we don't need to sync them again
+    let mut sync_errors = vec![];
+    for (para_chain, sync) in syncs {
+        match sync.await {
+            Ok(Ok(output)) => state.complete(&phase(&para_chain, "sync"), output).await?,
+            Ok(Err(e)) => sync_errors.push(format!("{para_chain}: {e}")),
+            Err(e) => sync_errors.push(format!("{para_chain}: {e}")),
+        }
+    }
+    if !sync_errors.is_empty() {
+        return Err(BiteError::Sync(format!(
+            "parachains sync failed ({}), use `bite --resume` to continue",
+            sync_errors.join(", ")
+        ))
+        .into());
+    }
 
     // loop over paras
     let mut para_artifacts = vec![];
     let mut para_heads_env = vec![];
     let context_para = Context::Parachain;
-    for (para_index, (_sync_node, sync_db_path, sync_chain, sync_head_path)) in
-        res.into_iter().enumerate()
-    {
+    for para in &paras_to {
+        let para_chain = para.as_chain_string(&relay_part);
+        let (sync_db_path, sync_chain, sync_head_path): (String, String, String) = state
+            .output(&phase(&para_chain, "sync"))
+            .expect("para sync should be completed. qed");
         let sync_chain_name = if sync_chain.contains('/') {
             let parts: Vec<&str> = sync_chain.split('/').collect();
             let name_parts: Vec<&str> = parts.last().unwrap().split('.').collect();
-            name_parts.get(0).unwrap().to_string()
+            name_parts.first().unwrap().to_string()
         } else {
             // is not a file
             sync_chain.clone()
         };
 
         let chain_spec_path = format!("{}/{}-spec.json", &base_dir_str, &sync_chain_name);
-        generate_chain_spec(
-            ns.clone(),
-            &chain_spec_path,
-            &context_para.doppelganger_cmd(),
-            &sync_chain,
-        )
-        .await
-        .unwrap();
-
-        // generate the data.tgz to use as snapshot
-        let snap_path = format!("{}/{}-snap.tgz", &base_dir_str, &sync_chain_name);
-        trace!("snap_path: {snap_path}");
-        generate_snap(&sync_db_path, &snap_path).await.unwrap();
+        let spec_phase = phase(&para_chain, "chain-spec");
+        if !state.is_done(&spec_phase) {
+            generate_chain_spec(
+                ns.clone(),
+                &chain_spec_path,
+                &context_para.doppelganger_cmd(),
+                &sync_chain,
+            )
+            .await?;
+            state.complete(&spec_phase, &chain_spec_path).await?;
+        }
 
-        let para_head_str = read_to_string(&sync_head_path).expect(&format!(
-            "read para_head ({sync_head_path}) file should works."
-        ));
-        let para_head_hex = if &para_head_str[..2] == "0x" {
-            &para_head_str[2..]
+        // generate the data snapshot (reuse the one of the resumed bite IFF any)
+        let snap_phase = phase(&para_chain, "snap");
+        let artifact_chain = if sync_chain.contains('/') {
+            para_chain.clone()
         } else {
-            &para_head_str
+            sync_chain.clone()
         };
+        let snap_path = match state.output::<String>(&snap_phase) {
+            Some(snap_path) => snap_path,
+            None => {
+                let snap_path = format!("{}/{}", &base_dir_str, snap_file(&sync_chain_name));
+                trace!("snap_path: {snap_path}");
+                let info_path = format!("{base_dir_str}/para-{}.txt", para.id());
+                let source =
+                    bite_snap_source(&artifact_chain, &info_path, para.rpc_endpoints()).await;
+                generate_snap(&sync_db_path, &snap_path, source).await?;
+                store.put(Path::new(&snap_path)).await?;
+                state.complete(&snap_phase, &snap_path).await?;
+                snap_path
+            }
+        };
+
+        let para_head_str = read_to_string(&sync_head_path)
+            .map_err(|e| BiteError::io("reading para head", &sync_head_path, e))?;
+        let para_head_str = para_head_str.trim();
+        let para_head_hex = para_head_str.strip_prefix("0x").unwrap_or(para_head_str);
 
         let para_head = array_bytes::bytes2hex(
             "0x",
-            HeadData(hex::decode(para_head_hex).expect("para_head should be a valid hex. qed"))
-                .encode(),
+            HeadData(hex::decode(para_head_hex).map_err(|e| {
+                BiteError::Sync(format!("invalid para head in {sync_head_path}: {e}"))
+            })?)
+            .encode(),
         );
 
-        let para = paras_to
-            .get(para_index)
-            .expect("para_index should be valid. qed");
         para_heads_env.push((
             format!("ZOMBIE_{}", &para_head_key(para.id())[2..]),
-            format!("{}", &para_head[2..]),
+            para_head[2..].to_string(),
         ));
 
         para_artifacts.push(ChainArtifact {
             cmd: context_para.doppelganger_cmd(),
-            chain: if sync_chain.contains('/') {
-                para.as_chain_string(&relay_chain.as_chain_string())
-            } else {
-                sync_chain
-            },
+            chain: artifact_chain,
             spec_path: chain_spec_path,
             snap_path,
             override_wasm: para.wasm_overrides().map(str::to_string),
+            para: Some(para.clone()),
         });
     }
 
-    let rc_default_overrides_path =
-        generate_default_overrides_for_rc(&base_dir_str, &relay_chain, &paras_to).await;
-    let rc_info_path = format!("{base_dir_str}/rc_info.txt");
     // RELAYCHAIN sync
+    let rc_sync_phase = phase(&relay_part, "sync");
+    let (sync_db_path, sync_chain_arg): (String, String) = match state.output(&rc_sync_phase) {
+        Some(output) => {
+            info!("⏭️  {relay_part} already synced, skipping");
+            output
+        }
+        None => {
+            let rc_default_overrides_path = generate_default_overrides_for_rc(
+                &base_dir_str,
+                &relay_chain,
+                &paras_to,
+                validators,
+                custom_overrides.rc.as_deref(),
+                maybe_rc_header.as_ref().map(header_number).transpose()?,
+            )
+            .await?;
+            let rc_info_path = format!("{base_dir_str}/rc_info.txt");
 
-    let maybe_target_header_path = if let Some(at_block) = relay_chain.at_block() {
-        let header = get_header_from_block(at_block, &relay_chain.rpc_endpoint()).await?;
+            let maybe_target_header_path = if let Some(header) = maybe_rc_header {
+                let target_header_path = format!("{base_dir_str}/rc-header.json");
+                fs::write(&target_header_path, serde_json::to_string_pretty(&header)?)
+                    .await
+                    .map_err(|e| BiteError::io("writing", &target_header_path, e))?;
+                Some(target_header_path)
+            } else {
+                None
+            };
 
-        let target_header_path = format!("{base_dir_str}/rc-header.json");
-        fs::write(&target_header_path, serde_json::to_string_pretty(&header)?)
-            .await
-            .expect("create target head json should works");
-        Some(target_header_path)
-    } else {
-        None
+            let (sync_node, sync_db_path, sync_chain_arg) = sync_relay_only(
+                ns.clone(),
+                "doppelganger",
+                relay_chain.chain_arg(),
+                relay_chain.epoch_duration(),
+                para_heads_env,
+                rc_default_overrides_path,
+                &rc_info_path,
+                maybe_target_header_path,
+                database,
+            )
+            .await?;
+
+            // stop relay node
+            sync_node.destroy().await.map_err(|e| {
+                BiteError::Sync(format!("Error stopping {relay_part} sync node: {e}"))
+            })?;
+            state
+                .complete(&rc_sync_phase, (&sync_db_path, &sync_chain_arg))
+                .await?;
+            (sync_db_path, sync_chain_arg)
+        }
     };
 
-    let (sync_node, sync_db_path, sync_chain) = sync_relay_only(
-        ns.clone(),
-        "doppelganger",
-        relay_chain.as_chain_string(),
-        para_heads_env,
-        rc_default_overrides_path,
-        &rc_info_path,
-        maybe_target_header_path,
-        database,
-    )
-    .await
-    .unwrap();
-
-    // stop relay node
-    sync_node.destroy().await.unwrap();
-
     // get the chain-spec (prod) and clean the bootnodes
     // relaychain
     let context_relay = Context::Relaychain;
+    let sync_chain = relay_part.clone();
     let r_chain_spec_path = format!("{}/{}-spec.json", &base_dir_str, &sync_chain);
-    generate_chain_spec(
-        ns.clone(),
-        &r_chain_spec_path,
-        &context_relay.doppelganger_cmd(),
-        &sync_chain,
-    )
-    .await
-    .unwrap();
+    let rc_spec_phase = phase(&relay_part, "chain-spec");
+    if !state.is_done(&rc_spec_phase) {
+        generate_chain_spec(
+            ns.clone(),
+            &r_chain_spec_path,
+            &context_relay.doppelganger_cmd(),
+            &sync_chain_arg,
+        )
+        .await?;
+        state.complete(&rc_spec_phase, &r_chain_spec_path).await?;
+    }
 
     // remove `parachains` db
-    let sync_chain_in_path = if sync_chain == "kusama" {
-        "ksmcc3"
-    } else {
-        sync_chain.as_str()
-    };
+    let sync_chain_in_path = relay_chain.chain_id();
 
     let parachains_path = if database == "rocksdb" {
         format!("{sync_db_path}/chains/{sync_chain_in_path}/db/full/parachains")
@@ -250,14 +398,32 @@ pub async fn doppelganger_inner(
         format!("{sync_db_path}/chains/{sync_chain_in_path}/paritydb/parachains")
     };
 
-    debug!("Deleting `parachains` db at {parachains_path}");
-    tokio::fs::remove_dir_all(parachains_path)
-        .await
-        .expect("remove parachains db should work");
+    let rc_parachains_db_phase = phase(&relay_part, "remove-parachains-db");
+    if !state.is_done(&rc_parachains_db_phase) {
+        debug!("Deleting `parachains` db at {parachains_path}");
+        tokio::fs::remove_dir_all(&parachains_path)
+            .await
+            .map_err(|e| BiteError::io("removing parachains db", &parachains_path, e))?;
+        state
+            .complete(&rc_parachains_db_phase, &parachains_path)
+            .await?;
+    }
 
-    // generate the data.tgz to use as snapshot
-    let r_snap_path = format!("{}/{}-snap.tgz", &base_dir_str, &sync_chain);
-    generate_snap(&sync_db_path, &r_snap_path).await.unwrap();
+    // generate the data snapshot (reuse the one of the resumed bite IFF any)
+    let rc_snap_phase = phase(&relay_part, "snap");
+    let r_snap_path = match state.output::<String>(&rc_snap_phase) {
+        Some(snap_path) => snap_path,
+        None => {
+            let snap_path = format!("{}/{}", &base_dir_str, snap_file(&sync_chain));
+            let info_path = format!("{base_dir_str}/rc_info.txt");
+            let source =
+                bite_snap_source(&sync_chain, &info_path, &relay_chain.rpc_endpoints()).await;
+            generate_snap(&sync_db_path, &snap_path, source).await?;
+            store.put(Path::new(&snap_path)).await?;
+            state.complete(&rc_snap_phase, &snap_path).await?;
+            snap_path
+        }
+    };
 
     let relay_artifacts = ChainArtifact {
         cmd: context_relay.doppelganger_cmd(),
@@ -265,6 +431,7 @@ pub async fn doppelganger_inner(
         spec_path: r_chain_spec_path,
         snap_path: r_snap_path,
         override_wasm: relay_chain.wasm_overrides().map(str::to_string),
+        para: None,
     };
 
     let config = generate_config(
@@ -272,58 +439,55 @@ pub async fn doppelganger_inner(
         para_artifacts,
         Some(global_base_dir.clone()),
         database,
+        validators,
     )
-    .await
-    .map_err(|e| anyhow!(e.to_string()))?;
+    .await?;
     // write config in 'bite'
     let config_toml_path = format!("{}/bite/config.toml", global_base_dir.to_string_lossy());
     let toml_config = config.dump_to_toml()?;
-    fs::write(config_toml_path, &toml_config)
+    fs::write(&config_toml_path, &toml_config)
         .await
-        .expect("create config.toml should works");
+        .map_err(|e| BiteError::io("writing", &config_toml_path, e))?;
 
     // create port and ready files
-    let rc_start_block = fs::read_to_string(format!("{base_dir_str}/rc_info.txt"))
-        .await
-        .unwrap()
-        .parse::<u64>()
-        .expect("read bite rc block should works");
+    let rc_start_block = read_start_block(&format!("{base_dir_str}/rc_info.txt")).await?;
 
-    let ah_start_block = fs::read_to_string(format!("{base_dir_str}/para-1000.txt"))
-        .await
-        .unwrap()
-        .parse::<u64>()
-        .expect("read bite ah block should works");
+    let mut paras_start_block = serde_json::Map::new();
+    for para in &paras_to {
+        let para_start_block =
+            read_start_block(&format!("{base_dir_str}/para-{}.txt", para.id())).await?;
+        paras_start_block.insert(para.id().to_string(), para_start_block.into());
+    }
 
     // ready to start
-    let ready_content = json!({
+    let mut ready_content = json!({
         "rc_start_block": rc_start_block,
-        "ah_start_block": ah_start_block,
+        "paras_start_block": paras_start_block,
     });
+    if let Some(ah_start_block) = paras_start_block.get("1000") {
+        ready_content["ah_start_block"] = ah_start_block.clone();
+    }
 
     let alice_config = config
         .relaychain()
         .nodes()
         .into_iter()
-        .find(|node| node.name() == "alice")
-        .expect("'alice' should exist");
-
-    let ah_config = config
-        .parachains()
-        .into_iter()
-        .last()
-        .expect("shoul be one parachain");
-    let collator_config = ah_config
-        .collators()
-        .into_iter()
-        .last()
-        .expect("should be one collator");
+        .next()
+        .expect("should be at least one validator");
 
     // ports
-    let ports_content = json!({
+    let mut ports_content = json!({
         "alice_port" : alice_config.rpc_port().unwrap(),
-        "collator_port": collator_config.rpc_port().unwrap(),
     });
+    for para_config in config.parachains() {
+        let collator_config = para_config
+            .collators()
+            .into_iter()
+            .last()
+            .expect("should be one collator");
+        ports_content[format!("{}_port", collator_config.name())] =
+            collator_config.rpc_port().unwrap().into();
+    }
 
     let _ = fs::write(
         format!("{}/{PORTS_FILE}", global_base_dir.to_string_lossy()),
@@ -341,6 +505,46 @@ pub async fn doppelganger_inner(
     Ok(())
 }
 
+/// Block number where the sync node stopped (written by the doppelganger binary).
+async fn read_start_block(info_path: &str) -> Result<u64, BiteError> {
+    let content = fs::read_to_string(info_path)
+        .await
+        .map_err(|e| BiteError::io("reading bite block from", info_path, e))?;
+    content
+        .trim()
+        .parse::<u64>()
+        .map_err(|e| BiteError::Sync(format!("invalid bite block in {info_path}: {e}")))
+}
+
+/// Source of the bite snapshot, with the block from the info file (written by the sync node)
+/// and its hash in the live network (best effort).
+async fn bite_snap_source(chain: &str, info_path: &str, endpoints: &[String]) -> SnapSource {
+    let mut source = SnapSource::new(chain);
+    match read_start_block(info_path).await {
+        Ok(number) => {
+            source.block_number = Some(number);
+            match get_block_hash(number as u32, endpoints).await {
+                Ok(hash) => source.block_hash = Some(hash),
+                Err(e) => warn!("Error getting the hash of block #{number} of {chain}: {e}"),
+            }
+        }
+        Err(e) => warn!("Error reading the bite block of {chain}: {e}"),
+    }
+    source
+}
+
+/// Last observed best block of each node (from the events log of the step).
+async fn last_best_blocks(step_dir: &Path) -> HashMap<String, u64> {
+    let events = EventLog::new(step_dir).load().await.unwrap_or_default();
+    events
+        .into_iter()
+        .filter_map(|record| match record.event {
+            Event::Sample { node, best, .. } => Some((node, best)),
+            _ => None,
+        })
+        .collect()
+}
+
 /// Create the needed artifats for the next step
 pub async fn generate_artifacts(
     global_base_dir: PathBuf,
@@ -348,32 +552,32 @@ pub async fn generate_artifacts(
     rc: &Relaychain,
 ) -> Result<(), anyhow::Error> {
     let global_base_dir_str = global_base_dir.to_string_lossy();
+    let from_config_path = format!("{global_base_dir_str}/{}/config.toml", step.dir_from());
 
-    // generate snapshot for alice (rc)
-    let alice_data = format!("{global_base_dir_str}/{}/alice/data", step.dir());
-
-    // // remove `parachains` db
-    // let parachains_path = format!("{alice_data}/chains/{}/db/full/parachains", rc.as_chain_string());
-    // debug!("Deleting `parachains` db at {parachains_path}");
-    // fs::remove_dir_all(parachains_path)
-    //     .await
-    //     .expect("remove parachains db should work");
-
-    let alice_rc_snap_file = format!("alice-{}-snap.tgz", rc.as_chain_string());
-    let alice_rc_snap_path = format!("{global_base_dir_str}/{}/{alice_rc_snap_file}", step.dir());
-    generate_snap(&alice_data, &alice_rc_snap_path).await?;
-
-    // generate snapshot for alice (rc)
-    let bob_data = format!("{global_base_dir_str}/{}/bob/data", step.dir());
-    let bob_rc_snap_file = format!("bob-{}-snap.tgz", rc.as_chain_string());
-    let bob_rc_snap_path = format!("{global_base_dir_str}/{}/{bob_rc_snap_file}", step.dir());
-    generate_snap(&bob_data, &bob_rc_snap_path).await?;
-
-    // generate snapshot for collator
-    let collator_data = format!("{global_base_dir_str}/{}/collator/data", step.dir());
-    let ah_snap_file = format!("asset-hub-{}-snap.tgz", rc.as_chain_string());
-    let ah_snap_path = format!("{global_base_dir_str}/{}/{ah_snap_file}", step.dir());
-    generate_snap(&collator_data, &ah_snap_path).await?;
+    let nodes = ConfigNodes::from_config(&from_config_path).await?;
+
+    // snapshot for each validator (rc), generated concurrently with the collators ones
+    let mut snaps = vec![];
+    // (data, snap, source) to generate
+    let mut to_generate: Vec<(String, String, SnapSource)> = vec![];
+    let best_blocks = last_best_blocks(&global_base_dir.join(step.dir())).await;
+    let store = &Store::for_base_path(&global_base_dir);
+    let source = |chain: &str, node: &str| SnapSource {
+        block_number: best_blocks.get(node).copied(),
+        ..SnapSource::new(chain)
+    };
+    for validator in &nodes.relay {
+        let validator_data = format!("{global_base_dir_str}/{}/{validator}/data", step.dir());
+        let validator_snap_file = snap_file(&format!("{validator}-{}", rc.as_chain_string()));
+        let validator_snap_path =
+            format!("{global_base_dir_str}/{}/{validator_snap_file}", step.dir());
+        to_generate.push((
+            validator_data,
+            validator_snap_path.clone(),
+            source(&rc.as_chain_string(), validator),
+        ));
+        snaps.push(validator_snap_path);
+    }
 
     // cp chain-spec for rc
     let rc_spec_file = format!("{}-spec.json", rc.as_chain_string());
@@ -381,24 +585,52 @@ pub async fn generate_artifacts(
     let rc_spec_to = format!("{global_base_dir_str}/{}/{rc_spec_file}", step.dir());
     fs::copy(&rc_spec_from, &rc_spec_to)
         .await
-        .expect("cp should work");
+        .map_err(|e| BiteError::io("copying", &rc_spec_from, e))?;
+    let mut specs = vec![rc_spec_to];
+
+    for (para_chain, collators) in &nodes.paras {
+        // snapshot for collator (one per para, all the collators use the same)
+        for collator in collators {
+            let collator_data = format!("{global_base_dir_str}/{}/{collator}/data", step.dir());
+            let para_snap_file = snap_file(para_chain);
+            let para_snap_path = format!("{global_base_dir_str}/{}/{para_snap_file}", step.dir());
+            if !to_generate
+                .iter()
+                .any(|(_, snap, _)| snap == &para_snap_path)
+            {
+                to_generate.push((
+                    collator_data,
+                    para_snap_path.clone(),
+                    source(para_chain, collator),
+                ));
+            }
+            snaps.push(para_snap_path);
+        }
 
-    // cp chain-spec for ah
-    let ah_spec_file = format!("asset-hub-{}-spec.json", rc.as_chain_string());
-    let ah_spec_from = format!("{global_base_dir_str}/{}/{ah_spec_file}", step.dir_from());
-    let ah_spec_to = format!("{global_base_dir_str}/{}/{ah_spec_file}", step.dir());
-    fs::copy(&ah_spec_from, &ah_spec_to)
-        .await
-        .expect("cp should work");
+        // cp chain-spec for para
+        let para_spec_file = format!("{para_chain}-spec.json");
+        let para_spec_from = format!("{global_base_dir_str}/{}/{para_spec_file}", step.dir_from());
+        let para_spec_to = format!("{global_base_dir_str}/{}/{para_spec_file}", step.dir());
+        fs::copy(&para_spec_from, &para_spec_to)
+            .await
+            .map_err(|e| BiteError::io("copying", &para_spec_from, e))?;
+        specs.push(para_spec_to);
+    }
 
-    let mut snaps = vec![alice_rc_snap_path, bob_rc_snap_path, ah_snap_path];
-    let mut specs = vec![rc_spec_to, ah_spec_to];
+    try_join_all(
+        to_generate
+            .into_iter()
+            .map(|(data, snap, source)| async move {
+                generate_snap(&data, &snap, source).await?;
+                store.put(Path::new(&snap)).await
+            }),
+    )
+    .await?;
 
     // generate custom config
-    let from_config_path = format!("{global_base_dir_str}/{}/config.toml", step.dir_from());
     let config = fs::read_to_string(&from_config_path)
         .await
-        .expect("read config file should work");
+        .map_err(|e| BiteError::io("reading", &from_config_path, e))?;
     let db_snaps_in_file: Vec<(usize, &str)> = config.match_indices("db_snapshot").collect();
     let needs_to_insert_db = db_snaps_in_file.len() != snaps.len();
     let toml_config = config
@@ -430,9 +662,9 @@ pub async fn generate_artifacts(
 
     // write config in 'dir'
     let config_toml_path = format!("{global_base_dir_str}/{}/config.toml", step.dir());
-    fs::write(config_toml_path, &toml_config)
+    fs::write(&config_toml_path, &toml_config)
         .await
-        .expect("create config.toml should works");
+        .map_err(|e| BiteError::io("writing", &config_toml_path, e))?;
 
     Ok(())
 }
@@ -450,89 +682,132 @@ pub async fn clean_up_dir_for_step(
     if let Ok(true) = fs::try_exists(&debug_path).await {
         fs::remove_dir_all(&debug_path)
             .await
-            .expect("remove debug dir should works");
+            .map_err(|e| BiteError::io("removing", &debug_path, e))?;
     }
 
     let step_path = format!("{global_base_dir_str}/{}", step.dir());
     fs::rename(&step_path, &debug_path)
         .await
-        .expect("rename dir should works");
+        .map_err(|e| BiteError::io("renaming", &step_path, e))?;
     info!("renamed dir from {step_path} to {debug_path}");
 
     // create the step dir again
     fs::create_dir_all(&step_path)
         .await
-        .expect("Create step dir should works");
+        .map_err(|e| BiteError::io("creating", &step_path, e))?;
     info!("created dir {step_path}");
 
     // copy needed files
-    let ah_spec = format!("asset-hub-{}-spec.json", rc.as_chain_string());
-    let ah_snap = format!("asset-hub-{}-snap.tgz", rc.as_chain_string());
+    let nodes = ConfigNodes::from_config(&format!("{debug_path}/config.toml")).await?;
     let rc_spec = format!("{}-spec.json", rc.as_chain_string());
-    let rc_snap = format!("{}-snap.tgz", rc.as_chain_string());
-    let alice_snap = format!("alice-{}-snap.tgz", rc.as_chain_string());
-    let bob_snap = format!("bob-{}-snap.tgz", rc.as_chain_string());
-    let mut needed_files = vec!["config.toml", &ah_spec, &ah_snap, &rc_spec];
+    let mut needed_files = vec![String::from("config.toml"), rc_spec];
+    let mut snap_names = vec![];
+    for (para_chain, _) in &nodes.paras {
+        needed_files.push(format!("{para_chain}-spec.json"));
+        snap_names.push(para_chain.clone());
+    }
 
     if step == Step::Bite {
-        needed_files.push(&rc_snap);
+        snap_names.push(rc.as_chain_string());
     } else {
-        needed_files.push(&alice_snap);
-        needed_files.push(&bob_snap);
+        for validator in &nodes.relay {
+            snap_names.push(format!("{validator}-{}", rc.as_chain_string()));
+        }
+    }
+    // snapshots can use any codec
+    for name in snap_names {
+        needed_files
+            .push(find_snap(Path::new(&debug_path), &name).unwrap_or_else(|| snap_file(&name)));
     }
 
     for file in needed_files {
         let from = format!("{debug_path}/{file}");
         let to = format!("{step_path}/{file}");
         info!("mv {from} {to}");
-        fs::rename(&from, &to)
-            .await
-            .expect(&format!("copy from {from} to {to} should works"));
+        move_file(&from, &to).await?;
+        // and the sidecar manifest of the snapshots
+        let from_manifest = SnapManifest::path(Path::new(&from));
+        if from_manifest.exists() {
+            move_file(&from_manifest, SnapManifest::path(Path::new(&to))).await?;
+        }
+        // and the uncompressed dbs (same device, renamed)
+        let from_dir = snap_dir(Path::new(&from));
+        if from_dir.is_dir() {
+            move_file(&from_dir, snap_dir(Path::new(&to))).await?;
+        }
     }
+    move_events_log(Path::new(&debug_path), Path::new(&step_path)).await?;
 
     Ok(())
 }
 
+/// Nodes defined in the network config (toml) file.
+struct ConfigNodes {
+    /// Relaychain node names
+    relay: Vec<String>,
+    /// Chain and collator names of each parachain
+    paras: Vec<(String, Vec<String>)>,
+}
+
+impl ConfigNodes {
+    async fn from_config(config_path: &str) -> Result<Self, anyhow::Error> {
+        let content = fs::read_to_string(config_path)
+            .await
+            .map_err(|e| BiteError::io("reading config", config_path, e))?;
+        let config: toml::Value = toml::from_str(&content)
+            .map_err(|e| BiteError::Config(format!("invalid config {config_path}: {e}")))?;
+
+        let names = |nodes: Option<&toml::Value>| -> Vec<String> {
+            nodes
+                .and_then(|nodes| nodes.as_array())
+                .map(|nodes| {
+                    nodes
+                        .iter()
+                        .filter_map(|node| node.get("name").and_then(|n| n.as_str()))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let relay = names(config.get("relaychain").and_then(|r| r.get("nodes")));
+        let paras = config
+            .get("parachains")
+            .and_then(|paras| paras.as_array())
+            .map(|paras| {
+                paras
+                    .iter()
+                    .map(|para| {
+                        let chain = para
+                            .get("chain")
+                            .and_then(|c| c.as_str())
+                            .unwrap_or_default()
+                            .to_string();
+                        (chain, names(para.get("collators")))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self { relay, paras })
+    }
+}
+
 async fn generate_config(
     relaychain: ChainArtifact,
     paras: Vec<ChainArtifact>,
     global_base_dir: Option<PathBuf>,
     database: &str,
-) -> Result<NetworkConfig, String> {
-    let leaked_rust_log = env::var("RUST_LOG_RC").unwrap_or_else(|_| {
-        String::from(
-            "babe=debug,grandpa=info,runtime=debug,consensus::common=debug,parachain=debug,parachain::gossip-support=info",
-        )
-    });
-
-    let para_leaked_rust_log = env::var("RUST_LOG_COL").unwrap_or_else(|_| {
-        String::from(
-            "aura=debug,runtime=debug,cumulus-consensus=debug,consensus::common=debug,parachain::collation-generation=debug,parachain::collator-protocol=debug,parachain=debug,xcm=debug",
-        )
-    });
-
-    let (chain_spec_path, db_path) = if let Ok(ci_path) = env::var("ZOMBIE_BITE_CI_PATH") {
-        let chain_spec_path = PathBuf::from(relaychain.spec_path.as_str());
-        let chain_spec_filename = chain_spec_path
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
-
-        let db_path = PathBuf::from(relaychain.snap_path.as_str());
-        let db_path_filename = db_path.file_name().unwrap().to_string_lossy().to_string();
-
-        let new_chain_spec_path = PathBuf::from(&format!("{ci_path}/{}", chain_spec_filename));
-        let new_db_path = PathBuf::from(&format!("{ci_path}/{}", db_path_filename));
-
-        tokio::fs::rename(chain_spec_path, &new_chain_spec_path)
-            .await
-            .unwrap();
-        tokio::fs::rename(db_path, &new_db_path).await.unwrap();
+    validators: &[String],
+) -> Result<NetworkConfig, anyhow::Error> {
+    let manifest = get_manifest();
+    let leaked_rust_log = manifest.rc_log();
+    let para_leaked_rust_log = manifest.col_log();
 
+    let (chain_spec_path, db_path) = if let Some(ci_path) = manifest.ci_path() {
         (
-            PathBuf::from(format!("./{}", chain_spec_filename)),
-            PathBuf::from(format!("./{}", db_path_filename)),
+            move_to_ci_path(&relaychain.spec_path, &ci_path).await?,
+            move_to_ci_path(&relaychain.snap_path, &ci_path).await?,
         )
     } else {
         (
@@ -541,25 +816,31 @@ async fn generate_config(
         )
     };
 
-    // backward compatibility
-    let rpc_alice_port: u16 = if let Ok(port) = env::var("ZOMBIE_BITE_RC_PORT") {
-        port.parse()
-            .expect("env var ZOMBIE_BITE_RC_PORT must be a valid u16")
-    } else if let Ok(port) = env::var("ZOMBIE_BITE_ALICE_PORT") {
-        port.parse()
-            .expect("env var ZOMBIE_BITE_ALICE_PORT must be a valid u16")
-    } else {
-        get_random_port().await
+    let rpc_alice_port: u16 = match manifest.alice_port()? {
+        Some(port) => port,
+        None => get_random_port().await,
     };
 
-    let rpc_bob_port: u16 = if let Ok(port) = env::var("ZOMBIE_BITE_BOB_PORT") {
-        port.parse()
-            .expect("env var ZOMBIE_BITE_RC_PORT must be a valid u16")
-    } else {
-        get_random_port().await
+    let rpc_bob_port: u16 = match manifest.bob_port()? {
+        Some(port) => port,
+        None => get_random_port().await,
     };
 
-    // config a new network with alice/bob
+    // (name, rpc_port) for each validator, alice/bob ports can be set from env / manifest
+    let mut validators_ports = vec![];
+    for (i, name) in validators.iter().enumerate() {
+        let port = match i {
+            0 => rpc_alice_port,
+            1 => rpc_bob_port,
+            _ => get_random_port().await,
+        };
+        validators_ports.push((name.clone(), port));
+    }
+    let (first_validator, rest_validators) = validators_ports
+        .split_first()
+        .ok_or_else(|| BiteError::Config(String::from("at least one validator is needed")))?;
+
+    // config a new network with the validators
     let mut config = NetworkConfigBuilder::new().with_relaychain(|r| {
         let mut default_args = vec![
             ("-l", leaked_rust_log.as_str()).into(),
@@ -570,10 +851,8 @@ async fn generate_config(
             ("--database", database).into(),
         ];
 
-        if let Ok(extra_args) = env::var("ZOMBIE_BITE_RC_EXTRA_ARGS") {
-            for extra in extra_args.split(',') {
-                default_args.push(extra.trim().into());
-            }
+        for extra in manifest.rc_extra_args() {
+            default_args.push(extra.as_str().into());
         }
 
         let relay_builder = r
@@ -590,9 +869,15 @@ async fn generate_config(
         //     relay_builder
         // };
 
-        relay_builder
-            .with_validator(|node| node.with_name("alice").with_rpc_port(rpc_alice_port))
-            .with_validator(|node| node.with_name("bob").with_rpc_port(rpc_bob_port))
+        let relay_builder = relay_builder.with_validator(|node| {
+            node.with_name(first_validator.0.as_str())
+                .with_rpc_port(first_validator.1)
+        });
+        rest_validators
+            .iter()
+            .fold(relay_builder, |builder, (name, port)| {
+                builder.with_validator(|node| node.with_name(name.as_str()).with_rpc_port(*port))
+            })
     });
     if !paras.is_empty() {
         // TODO: enable for multiple paras
@@ -607,29 +892,10 @@ async fn generate_config(
             // .with_default_db_snapshot(PathBuf::from(para.snap_path.as_str()))
             // .with_collator(|c| c.with_name("col-1000"));
 
-            let (chain_spec_path, db_path) = if let Ok(ci_path) = env::var("ZOMBIE_BITE_CI_PATH") {
-                let chain_spec_path = PathBuf::from(para.spec_path.as_str());
-                let chain_spec_filename = chain_spec_path
-                    .file_name()
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string();
-
-                let db_path = PathBuf::from(para.snap_path.as_str());
-                let db_path_filename = db_path.file_name().unwrap().to_string_lossy().to_string();
-
-                let new_chain_spec_path =
-                    PathBuf::from(&format!("{ci_path}/{}", chain_spec_filename));
-                let new_db_path = PathBuf::from(&format!("{ci_path}/{}", db_path_filename));
-
-                tokio::fs::rename(chain_spec_path, &new_chain_spec_path)
-                    .await
-                    .unwrap();
-                tokio::fs::rename(db_path, &new_db_path).await.unwrap();
-
+            let (chain_spec_path, db_path) = if let Some(ci_path) = manifest.ci_path() {
                 (
-                    PathBuf::from(format!("./{}", chain_spec_filename)),
-                    PathBuf::from(format!("./{}", db_path_filename)),
+                    move_to_ci_path(&para.spec_path, &ci_path).await?,
+                    move_to_ci_path(&para.snap_path, &ci_path).await?,
                 )
             } else {
                 (
@@ -638,11 +904,13 @@ async fn generate_config(
                 )
             };
 
-            let para_rpc_port: u16 = if let Ok(port) = env::var("ZOMBIE_BITE_AH_PORT") {
-                port.parse()
-                    .expect("env var ZOMBIE_BITE_AH_PORT must be a valid u16")
-            } else {
-                get_random_port().await
+            let parachain = para
+                .para
+                .clone()
+                .expect("parachain artifact should have a para. qed");
+            let para_rpc_port: u16 = match manifest.collator_port(&parachain)? {
+                Some(port) => port,
+                None => get_random_port().await,
             };
 
             let mut para_default_args = vec![
@@ -660,22 +928,20 @@ async fn generate_config(
                 ("--database", database).into(),
             ];
 
-            if let Ok(extra_args) = env::var("ZOMBIE_BITE_AH_EXTRA_ARGS") {
-                for extra in extra_args.split(',') {
-                    para_default_args.push(extra.trim().into());
-                }
+            for extra in manifest.col_extra_args() {
+                para_default_args.push(extra.as_str().into());
             }
 
             config = config.with_parachain(|p| {
                 let para_builder = p
-                    .with_id(1000)
+                    .with_id(parachain.id())
                     .with_chain(para.chain.as_str())
                     .with_default_command(para.cmd.as_str())
                     .with_chain_spec_path(chain_spec_path)
                     .with_default_db_snapshot(db_path);
 
                 para_builder.with_collator(|c| {
-                    c.with_name("collator")
+                    c.with_name(parachain.collator_name().as_str())
                         .with_rpc_port(para_rpc_port)
                         .with_args(para_default_args)
                 })
@@ -684,18 +950,45 @@ async fn generate_config(
     }
 
     let config = if let Some(global_base_dir) = &global_base_dir {
-        let fixed_base_dir = global_base_dir.canonicalize().unwrap().join("spawn");
+        let fixed_base_dir = global_base_dir
+            .canonicalize()
+            .map_err(|e| BiteError::io("resolving", global_base_dir, e))?
+            .join("spawn");
         config.with_global_settings(|global_settings| {
-            global_settings.with_base_dir(&fixed_base_dir.to_string_lossy().to_string())
+            global_settings.with_base_dir(fixed_base_dir.to_string_lossy().to_string())
         })
     } else {
         config
     };
 
-    let network_config = config.build().unwrap();
+    let network_config = config.build().map_err(|errs| {
+        let errs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+        BiteError::Config(format!("invalid network config: {}", errs.join(", ")))
+    })?;
     Ok(network_config)
 }
 
+/// Move the artifact to the `ci_path`, returning the (relative) path to use in the config.
+async fn move_to_ci_path(artifact_path: &str, ci_path: &str) -> Result<PathBuf, BiteError> {
+    let filename = Path::new(artifact_path)
+        .file_name()
+        .ok_or_else(|| BiteError::Config(format!("invalid artifact path {artifact_path}")))?
+        .to_string_lossy()
+        .to_string();
+    move_file(artifact_path, &format!("{ci_path}/{filename}")).await?;
+    // keep the sidecar manifest (snapshots) with the artifact
+    let manifest = SnapManifest::path(Path::new(artifact_path));
+    if manifest.exists() {
+        move_file(
+            &manifest,
+            SnapManifest::path(&Path::new(ci_path).join(&filename)),
+        )
+        .await?;
+    }
+
+    Ok(PathBuf::from(format!("./{filename}")))
+}
+
 /// Spawn a new instance of the chain from a base_path and step.
 pub async fn spawn(
     step: Step,
@@ -729,36 +1022,28 @@ pub async fn spawn(
 
     // ensure base_dir is correct in settings
     let base_dir = format!("{}/{}", base_path.to_string_lossy(), step.dir());
+    // snapshots not supported by zombienet are extracted by us
+    let config_file = prepare_spawn_config(&config_file, &base_dir).await?;
     let global_settings = zombienet_configuration::GlobalSettingsBuilder::new()
         .with_base_dir(&base_dir)
         .with_tear_down_on_failure(false)
         .build()
-        .expect("global settings should work");
+        .map_err(|errs| {
+            let errs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+            BiteError::Config(format!("invalid global settings: {}", errs.join(", ")))
+        })?;
 
     let network_config = zombienet_configuration::NetworkConfig::load_from_toml_with_settings(
         &config_file,
         &global_settings,
     )
-    .unwrap();
+    .map_err(|e| BiteError::Config(format!("Error loading config {config_file}: {e}")))?;
 
-    orchestrator
+    let network = orchestrator
         .spawn(network_config)
         .await
-        .map_err(|e| anyhow!(e.to_string()))
-}
-
-async fn generate_snap(data_path: &str, snap_path: &str) -> Result<(), anyhow::Error> {
-    info!("\n📝 Generating snapshot file {snap_path} with data_path {data_path}...");
-
-    let compressed_file = File::create(snap_path).unwrap();
-    let mut encoder = GzEncoder::new(compressed_file, Compression::fast());
-
-    let mut archive = Builder::new(&mut encoder);
-    archive.append_dir_all("data", data_path).unwrap();
-    archive.finish().unwrap();
-
-    info!("✅ generated with path {snap_path}");
-    Ok(())
+        .map_err(|e| BiteError::Spawn(e.to_string()))?;
+    Ok(network)
 }
 
 async fn generate_chain_spec(
@@ -766,7 +1051,7 @@ async fn generate_chain_spec(
     chain_spec_path: &str,
     cmd: &str,
     chain: &str,
-) -> Result<(), String> {
+) -> Result<(), anyhow::Error> {
     info!("\n📝 Generating chain-spec file {chain_spec_path} using cmd {cmd} with chain {chain} without bootnodes...");
 
     let temp_node = ns
@@ -775,21 +1060,35 @@ async fn generate_chain_spec(
                 .args(vec!["-c", "while :; do sleep 60; done"]),
         )
         .await
-        .unwrap();
+        .map_err(|e| BiteError::Spec(format!("Error spawning temp node: {e}")))?;
 
-    let cmd_stdout = temp_node
+    let cmd_output = temp_node
         .run_command(RunCommandOptions::new(cmd).args(vec!["build-spec", "--chain", chain]))
-        .await
-        .unwrap()
-        .unwrap();
-
-    temp_node.destroy().await.unwrap();
+        .await;
+    // always try to stop the temp node
+    let _ = temp_node.destroy().await;
+
+    let cmd_stdout = match cmd_output {
+        Ok(Ok(stdout)) => stdout,
+        Ok(Err((status, stderr))) => {
+            return Err(BiteError::Spec(format!(
+                "`{cmd} build-spec --chain {chain}` failed ({status}): {stderr}"
+            ))
+            .into())
+        }
+        Err(e) => {
+            return Err(BiteError::Spec(format!("Error running `{cmd} build-spec`: {e}")).into())
+        }
+    };
 
-    let mut chain_spec_json: serde_json::Value = serde_json::from_str(&cmd_stdout).unwrap();
+    let mut chain_spec_json: serde_json::Value = serde_json::from_str(&cmd_stdout)
+        .map_err(|e| BiteError::Spec(format!("invalid chain-spec from `{cmd}`: {e}")))?;
     chain_spec_json["bootNodes"] = serde_json::Value::Array(vec![]);
-    let contents = serde_json::to_string_pretty(&chain_spec_json).unwrap();
+    let contents = serde_json::to_string_pretty(&chain_spec_json)?;
 
-    tokio::fs::write(&chain_spec_path, contents).await.unwrap();
+    tokio::fs::write(&chain_spec_path, contents)
+        .await
+        .map_err(|e| BiteError::io("writing", chain_spec_path, e))?;
     info!("✅ generated with path {chain_spec_path}");
 
     Ok(())
@@ -852,7 +1151,12 @@ mod test {
     #[tokio::test(flavor = "multi_thread")]
     async fn test_snap() {
         let snap_path = "/tmp/zombie-bite_1726677980197/snap.tgz";
-        let demo = generate_snap("/tmp/zombie-bite_1726677980197", snap_path).await;
+        let demo = generate_snap(
+            "/tmp/zombie-bite_1726677980197",
+            snap_path,
+            SnapSource::new("polkadot"),
+        )
+        .await;
         // .unwrap();
         println!("{:?}", demo);
         // let _n = spawn(provider, chain_spec_path, snap_path).await.unwrap();
@@ -883,7 +1187,9 @@ mod test {
             .await
             .unwrap();
         println!("{:?}", n);
-        loop {}
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        }
     }
 
     #[tokio::test]
@@ -899,6 +1205,7 @@ mod test {
             spec_path: "/home/ubuntu/something.json".into(),
             snap_path: "/home/ubuntu/something.tgz".into(),
             override_wasm: None,
+            para: None,
         };
         let ah = ChainArtifact {
             cmd: "doppelganger-parachain".into(),
@@ -906,14 +1213,80 @@ mod test {
             spec_path: "/home/ubuntu/something-ah.json".into(),
             snap_path: "/home/ubuntu/something-ah.tgz".into(),
             override_wasm: None,
+            para: Some(Parachain::new("asset-hub")),
         };
 
-        let network_config = generate_config(relay, vec![ah], None, "rocksdb")
-            .await
-            .unwrap();
+        let network_config = generate_config(
+            relay,
+            vec![ah],
+            None,
+            "rocksdb",
+            &crate::keys::validator_names(3),
+        )
+        .await
+        .unwrap();
 
         let toml = network_config.dump_to_toml().unwrap();
         println!("{toml}");
         assert!(toml.contains("--db-cache=24000"));
+        assert_eq!(network_config.relaychain().nodes().len(), 3);
+        assert!(toml.contains(r#"name = "charlie""#));
+    }
+
+    #[tokio::test]
+    async fn test_config_nodes_with_multiple_paras() {
+        let relay = ChainArtifact {
+            cmd: "doppelganger".into(),
+            chain: "polkadot".into(),
+            spec_path: "/home/ubuntu/something.json".into(),
+            snap_path: "/home/ubuntu/something.tgz".into(),
+            override_wasm: None,
+            para: None,
+        };
+        let paras = ["asset-hub", "coretime"]
+            .into_iter()
+            .map(|name| {
+                let para = Parachain::new(name);
+                let chain = para.as_chain_string("polkadot");
+                ChainArtifact {
+                    cmd: "doppelganger-parachain".into(),
+                    chain: chain.clone(),
+                    spec_path: format!("/home/ubuntu/{chain}-spec.json"),
+                    snap_path: format!("/home/ubuntu/{chain}-snap.tgz"),
+                    override_wasm: None,
+                    para: Some(para),
+                }
+            })
+            .collect();
+
+        let network_config = generate_config(
+            relay,
+            paras,
+            None,
+            "rocksdb",
+            &crate::keys::validator_names(2),
+        )
+        .await
+        .unwrap();
+
+        let config_path = "/tmp/zombie-bite-config-nodes.toml";
+        fs::write(config_path, network_config.dump_to_toml().unwrap())
+            .await
+            .unwrap();
+        let nodes = ConfigNodes::from_config(config_path).await.unwrap();
+        assert_eq!(nodes.relay, vec!["alice", "bob"]);
+        assert_eq!(
+            nodes.paras,
+            vec![
+                (
+                    String::from("asset-hub-polkadot"),
+                    vec![String::from("collator")]
+                ),
+                (
+                    String::from("coretime-polkadot"),
+                    vec![String::from("collator-1005")]
+                ),
+            ]
+        );
     }
 }
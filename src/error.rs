@@ -0,0 +1,122 @@
+//! Errors of the bite/spawn process, each category maps to a distinct exit code
+//! so wrappers (e.g. ci) can decide if they should retry.
+
+use std::{fmt, io, path::Path};
+
+#[derive(Debug)]
+pub enum BiteError {
+    /// Invalid args, manifest or network config
+    Config(String),
+    /// Rpc calls to the live network (e.g. resolving the block to bite)
+    Rpc(String),
+    /// Syncing the live network with the doppelganger nodes
+    Sync(String),
+    /// Chain-spec generation (or download)
+    Spec(String),
+    /// Snapshot generation
+    Snapshot(String),
+    /// Spawning the bited network
+    Spawn(String),
+    /// Files / dirs handling
+    Io(String),
+    /// Failed preflight checks (`doctor`)
+    Preflight(String),
+    /// The monitor gave up (e.g. restart budget exhausted)
+    Monitor(String),
+    /// Transfers with the remote artifact store (`push` / `pull`)
+    Remote(String),
+}
+
+impl BiteError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BiteError::Config(_) => 2,
+            BiteError::Rpc(_) => 3,
+            BiteError::Sync(_) => 4,
+            BiteError::Spec(_) => 5,
+            BiteError::Snapshot(_) => 6,
+            BiteError::Spawn(_) => 7,
+            BiteError::Io(_) => 8,
+            BiteError::Preflight(_) => 9,
+            BiteError::Monitor(_) => 10,
+            BiteError::Remote(_) => 11,
+        }
+    }
+
+    /// Actionable hint to show to the user.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            BiteError::Config(_) => "check the cli args / manifest (`zombie-bite bite --help`)",
+            BiteError::Rpc(_) => "the rpc endpoints are not available, retry later or set other ones with `--rc-rpc` / `--para-rpc`",
+            BiteError::Sync(_) => "ensure the `doppelganger` / `doppelganger-parachain` binaries are in your PATH and check the sync node logs, then retry with `bite --resume`",
+            BiteError::Spec(_) => "ensure the `doppelganger` binaries are in your PATH and the chain is supported, then retry with `bite --resume`",
+            BiteError::Snapshot(_) => "check the available disk space, then retry with `bite --resume`",
+            BiteError::Spawn(_) => "ensure the `polkadot` / `polkadot-parachain` binaries are in your PATH and check the nodes logs",
+            BiteError::Io(_) => "check the paths exist and you have permissions (and disk space) to write them",
+            BiteError::Preflight(_) => "fix the failed checks (`zombie-bite doctor`) or skip them with `--skip-doctor`",
+            BiteError::Monitor(_) => "check the nodes logs, the restart budget can be tuned in the `[monitor]` section of the manifest",
+            BiteError::Remote(_) => "check the remote url and credentials (`AWS_*` env vars for s3), then retry (the transfers are resumed)",
+        }
+    }
+
+    /// Io error with the path involved.
+    pub fn io(action: &str, path: impl AsRef<Path>, err: io::Error) -> Self {
+        BiteError::Io(format!("Error {action} {}: {err}", path.as_ref().display()))
+    }
+}
+
+impl fmt::Display for BiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (category, msg) = match self {
+            BiteError::Config(msg) => ("config", msg),
+            BiteError::Rpc(msg) => ("rpc", msg),
+            BiteError::Sync(msg) => ("sync", msg),
+            BiteError::Spec(msg) => ("chain-spec", msg),
+            BiteError::Snapshot(msg) => ("snapshot", msg),
+            BiteError::Spawn(msg) => ("spawn", msg),
+            BiteError::Io(msg) => ("io", msg),
+            BiteError::Preflight(msg) => ("doctor", msg),
+            BiteError::Monitor(msg) => ("monitor", msg),
+            BiteError::Remote(msg) => ("remote", msg),
+        };
+        write!(f, "[{category}] {msg}")
+    }
+}
+
+impl std::error::Error for BiteError {}
+
+/// Exit code for the error (1 if is not a [BiteError]).
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<BiteError>()
+        .map(BiteError::exit_code)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn exit_codes_should_be_distinct() {
+        let errors = [
+            BiteError::Config(String::new()),
+            BiteError::Rpc(String::new()),
+            BiteError::Sync(String::new()),
+            BiteError::Spec(String::new()),
+            BiteError::Snapshot(String::new()),
+            BiteError::Spawn(String::new()),
+            BiteError::Io(String::new()),
+            BiteError::Preflight(String::new()),
+            BiteError::Monitor(String::new()),
+            BiteError::Remote(String::new()),
+        ];
+        let codes: HashSet<i32> = errors.iter().map(BiteError::exit_code).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&1));
+
+        let err: anyhow::Error = BiteError::Sync(String::from("node crashed")).into();
+        assert_eq!(exit_code(&err), 4);
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), 1);
+    }
+}
@@ -0,0 +1,121 @@
+use codec::{Compact, Encode};
+use sp_core::{ecdsa, ed25519, sr25519, Pair};
+
+/// Well-known dev seeds used (in order) to name the relaychain validators.
+const WELL_KNOWN_NAMES: [&str; 6] = ["alice", "bob", "charlie", "dave", "eve", "ferdie"];
+
+/// Names of the relaychain validators, using the well-known dev names first
+/// and `validator-<index>` for the rest.
+pub fn validator_names(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| {
+            WELL_KNOWN_NAMES
+                .get(i)
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("validator-{i}"))
+        })
+        .collect()
+}
+
+/// Seed used by zombienet to generate the keys of a node (e.g. `alice` -> `//Alice`).
+pub fn seed_from_name(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => format!("//{}{}", first.to_uppercase(), chars.as_str()),
+        None => String::from("//"),
+    }
+}
+
+/// Keys of a node, derived the same way zombienet does it when spawning the node.
+#[derive(Debug, Clone)]
+pub struct NodeKeys {
+    /// sr25519 `<seed>//stash`
+    pub stash: [u8; 32],
+    /// sr25519 `<seed>` (babe, para_validator, para_assignment, authority_discovery, aura)
+    pub sr: [u8; 32],
+    /// ed25519 `<seed>` (grandpa, aura in asset-hub-polkadot)
+    pub ed: [u8; 32],
+    /// ecdsa `<seed>` (beefy)
+    pub ec: [u8; 33],
+}
+
+impl NodeKeys {
+    pub fn from_name(name: &str) -> Self {
+        let seed = seed_from_name(name);
+        let pair_for = |seed: &str| sr25519::Pair::from_string(seed, None);
+        Self {
+            stash: pair_for(&format!("{seed}//stash"))
+                .expect("dev seed should be valid. qed")
+                .public()
+                .0,
+            sr: pair_for(&seed)
+                .expect("dev seed should be valid. qed")
+                .public()
+                .0,
+            ed: ed25519::Pair::from_string(&seed, None)
+                .expect("dev seed should be valid. qed")
+                .public()
+                .0,
+            ec: ecdsa::Pair::from_string(&seed, None)
+                .expect("dev seed should be valid. qed")
+                .public()
+                .0,
+        }
+    }
+
+    /// Relaychain session keys (grandpa, babe, para_validator, para_assignment,
+    /// authority_discovery, beefy) encoded.
+    pub fn rc_session_keys(&self) -> Vec<u8> {
+        [
+            &self.ed[..],
+            &self.sr[..],
+            &self.sr[..],
+            &self.sr[..],
+            &self.sr[..],
+            &self.ec[..],
+        ]
+        .concat()
+    }
+}
+
+/// Encode a list of items as a SCALE `Vec` of the raw (already encoded) items.
+pub fn encode_vec<T: AsRef<[u8]>>(items: &[T]) -> Vec<u8> {
+    let mut encoded = Compact(items.len() as u32).encode();
+    for item in items {
+        encoded.extend_from_slice(item.as_ref());
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validator_names_should_work() {
+        assert_eq!(validator_names(2), vec!["alice", "bob"]);
+        assert_eq!(validator_names(7)[6], "validator-6");
+        assert_eq!(seed_from_name("validator-6"), "//Validator-6");
+    }
+
+    #[test]
+    fn node_keys_should_work() {
+        let alice = NodeKeys::from_name("alice");
+        assert_eq!(
+            hex::encode(alice.stash),
+            "be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25f"
+        );
+        assert_eq!(
+            hex::encode(alice.sr),
+            "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+        );
+        assert_eq!(
+            hex::encode(alice.ed),
+            "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee"
+        );
+        assert_eq!(
+            hex::encode(alice.ec),
+            "020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1"
+        );
+    }
+}
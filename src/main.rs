@@ -1,4 +1,5 @@
 use std::{
+    env,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -7,30 +8,57 @@ use clap::Parser;
 use futures::StreamExt;
 use tracing::{debug, info, level_filters::LevelFilter, trace, warn};
 use tracing_subscriber::EnvFilter;
-use zombienet_sdk::{LocalFileSystem, Network};
+use zombienet_sdk::{LocalFileSystem, Network, NetworkNode};
 
+mod bite_at;
+mod checkpoint;
 mod cli;
 mod config;
+mod control;
+mod cow;
+mod doctor;
 mod doppelganger;
+mod error;
+mod keys;
+mod manifest;
+mod metrics;
 mod monit;
+mod network_info;
+mod override_spec;
 mod overrides;
+mod progress;
+mod remote;
+mod report;
+mod snapshot;
+mod store;
 mod sync;
 mod utils;
 
 use cli::{get_base_path, Args, Commands};
 use config::{Parachain, Relaychain};
+use control::{ControlMessage, Request, Response};
 use doppelganger::doppelganger_inner;
-use monit::monit_progress;
-use tokio::fs;
+use error::BiteError;
+use manifest::{get_manifest, set_manifest, Manifest, ParaManifest, RESOLVED_MANIFEST_FILE};
+use monit::{monit_progress, PausedNodes};
+use network_info::{NetworkInfo, NETWORK_INFO_FILE};
+use overrides::CustomOverridesPaths;
+use report::{Event, EventLog};
+use store::Store;
+use tokio::{
+    fs,
+    signal::unix::{signal, SignalKind},
+    sync::mpsc,
+};
 
-use crate::config::{Step, AH_KUSAMA_RCP, AH_POLKADOT_RCP};
+use crate::config::Step;
 
 /// Signal for spawn to 'stop' and generate the artifacts
 const STOP_FILE: &str = "stop.txt";
 
-/// Helpers fns
+// Helpers fns
 
-async fn resolve_if_dir_exist(base_path: &Path, step: Step) {
+async fn resolve_if_dir_exist(base_path: &Path, step: Step) -> Result<(), BiteError> {
     let base_path_str = base_path.to_string_lossy();
     let path_to_use = format!("{base_path_str}/{}", step.dir());
     let mut path_with_suffix = format!("{base_path_str}/{}", step.dir());
@@ -48,57 +76,225 @@ async fn resolve_if_dir_exist(base_path: &Path, step: Step) {
         warn!("'{}' dir exist, moving to {path_with_suffix}", step.dir());
         fs::rename(&path_to_use, &path_with_suffix)
             .await
-            .expect("mv should work");
+            .map_err(|e| BiteError::io("moving", &path_to_use, e))?;
+    }
+
+    Ok(())
+}
+
+/// Run the preflight checks and fail if any of them fails.
+async fn preflight(
+    base_path: &Path,
+    required_disk: u64,
+    flows: &[doctor::Flow],
+) -> Result<(), BiteError> {
+    info!("🩺 running preflight checks");
+    let report = doctor::doctor(base_path, required_disk, flows).await;
+    report.print();
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(BiteError::Preflight(String::from(
+            "preflight checks failed (see the report above)",
+        )))
     }
 }
 
-async fn ensure_startup_producing_blocks(network: &Network<LocalFileSystem>) {
-    // IFF we have a collator, wait until the collator reply the metrics
-    if let Ok(collator) = network.get_node("collator") {
-        let _ = collator
+async fn write_network_info(
+    network: &Network<LocalFileSystem>,
+    base_path: &Path,
+    step: Step,
+) -> Result<(), BiteError> {
+    NetworkInfo::from_network(network, base_path)
+        .await?
+        .write(&base_path.join(step.dir()))
+        .await
+}
+
+fn collators(network: &Network<LocalFileSystem>) -> Vec<&NetworkNode> {
+    network
+        .parachains()
+        .into_iter()
+        .flat_map(|para| para.collators())
+        .collect()
+}
+
+async fn ensure_startup_producing_blocks(
+    network: &Network<LocalFileSystem>,
+) -> Result<(), BiteError> {
+    // IFF we have collators, wait until the collators reply the metrics
+    for collator in collators(network) {
+        collator
             .wait_metric_with_timeout("node_roles", |x| x > 1.0, 300_u64)
             .await
-            .unwrap();
+            .map_err(|e| {
+                BiteError::Spawn(format!("collator {} not ready: {e}", collator.name()))
+            })?;
     }
 
     // ensure block production
     let client = network
-        .get_node("alice")
-        .unwrap()
+        .relaychain()
+        .nodes()
+        .first()
+        .ok_or_else(|| BiteError::Spawn(String::from("should be at least one validator")))?
         .wait_client::<zombienet_sdk::subxt::PolkadotConfig>()
         .await
-        .unwrap();
-    let mut blocks = client.blocks().subscribe_finalized().await.unwrap().take(3);
+        .map_err(|e| BiteError::Spawn(format!("Error connecting to the validator: {e}")))?;
+    let mut blocks = client
+        .blocks()
+        .subscribe_finalized()
+        .await
+        .map_err(|e| BiteError::Spawn(format!("Error subscribing to finalized blocks: {e}")))?
+        .take(3);
 
     while let Some(block) = blocks.next().await {
-        info!("Block #{}", block.unwrap().header().number);
+        let block =
+            block.map_err(|e| BiteError::Spawn(format!("Error waiting finalized blocks: {e}")))?;
+        info!("Block #{}", block.header().number);
     }
 
     info!("🚀🚀🚀 network is up and running...");
+    Ok(())
+}
+
+/// Wait for SIGINT (ctrl-c) or SIGTERM, returning the signal name.
+async fn wait_for_signal() -> &'static str {
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(e) => {
+            warn!("Error installing SIGTERM handler: {e}");
+            let _ = tokio::signal::ctrl_c().await;
+            return "SIGINT";
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = sigterm.recv() => "SIGTERM",
+    }
+}
+
+/// Serve the zombie-bite metrics in the background (IFF the port is set).
+fn serve_metrics(maybe_port: Option<u16>, step: Step) {
+    metrics::set_step(step);
+    if let Some(port) = maybe_port {
+        tokio::spawn(async move {
+            let served = match metrics::bind(port).await {
+                Ok(listener) => metrics::serve(listener).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = served {
+                warn!("metrics not available: {e}");
+            }
+        });
+    }
 }
 
+/// How the network was stopped.
+struct Stop {
+    generate_artifacts: bool,
+    /// Error to exit with after the teardown (e.g. the monitor gave up)
+    error: Option<BiteError>,
+}
+
+/// Wait until the network is signaled to stop (by the control api, the stop file,
+/// SIGINT/SIGTERM or the monitor).
 async fn post_spawn_loop(
     stop_file: &str,
     network: &Network<LocalFileSystem>,
     with_monitor: bool,
-) -> Result<(), anyhow::Error> {
-    if with_monitor {
-        let alice = network.get_node("alice")?;
-        let bob = network.get_node("bob")?;
-        let collator = if let Ok(collator) = network.get_node("collator") {
-            Some(collator)
+    base_path: &Path,
+    step: Step,
+    generate_on_signal: bool,
+    events: &EventLog,
+) -> Result<Stop, anyhow::Error> {
+    // shared by the monitor and the control api
+    let paused = PausedNodes::default();
+    let watch_stop_file = async {
+        if with_monitor {
+            monit_progress(
+                network,
+                &get_manifest().monitor,
+                Some(stop_file),
+                events,
+                &paused,
+            )
+            .await?;
         } else {
-            None
-        };
+            loop {
+                events.sample(network).await;
+                if let Ok(true) = fs::try_exists(&stop_file).await {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+        }
+        Ok::<_, BiteError>(())
+    };
 
-        monit_progress(alice, bob, collator, Some(stop_file)).await;
-    } else {
-        while let Ok(false) = fs::try_exists(&stop_file).await {
-            tokio::time::sleep(Duration::from_secs(60)).await;
+    let (tx, mut rx) = mpsc::channel::<ControlMessage>(16);
+    let socket = control::socket_path(base_path);
+    let server = tokio::spawn(control::serve(socket.clone(), tx));
+    let handle_requests = async {
+        while let Some((request, response_tx)) = rx.recv().await {
+            if let Request::Stop { no_artifacts } = request {
+                info!("stop requested (control api)");
+                let _ = response_tx.send(Response::ok(
+                    serde_json::json!({ "stopping": true, "artifacts": !no_artifacts }),
+                ));
+                return Some(!no_artifacts);
+            }
+            let response = match control::handle(&request, network, base_path, step, &paused).await
+            {
+                Ok(result) => Response::ok(result),
+                Err(e) => Response::err(e),
+            };
+            let _ = response_tx.send(response);
         }
-    }
 
-    Ok(())
+        // the server failed (e.g. can't bind the socket), keep using the stop file
+        warn!("control api not available, use the stop file to stop the network");
+        None
+    };
+
+    let (stop, reason) = tokio::select! {
+        monitor = watch_stop_file => {
+            let reason = match (&monitor, fs::try_exists(&stop_file).await) {
+                (Err(e), _) => format!("monitor ({e})"),
+                (Ok(_), Ok(true)) => String::from("stop file"),
+                (Ok(_), _) => String::from("monitor"),
+            };
+            let stop = Stop {
+                generate_artifacts: monitor.is_ok(),
+                error: monitor.err(),
+            };
+            (stop, reason)
+        },
+        Some(generate_artifacts) = handle_requests => {
+            let stop = Stop {
+                generate_artifacts,
+                error: None,
+            };
+            (stop, String::from("control api"))
+        },
+        signal = wait_for_signal() => {
+            warn!(
+                "{signal} received, tearing down the network ({} artifacts)",
+                if generate_on_signal { "generating" } else { "discarding" }
+            );
+            let stop = Stop {
+                generate_artifacts: generate_on_signal,
+                error: None,
+            };
+            (stop, signal.to_string())
+        }
+    };
+    events.append(Event::Stop { reason }).await;
+
+    server.abort();
+    let _ = fs::remove_file(&socket).await;
+    Ok(stop)
 }
 
 async fn tear_down_and_generate(
@@ -106,40 +302,71 @@ async fn tear_down_and_generate(
     step: Step,
     network: Network<LocalFileSystem>,
     base_path: PathBuf,
+    stop: Stop,
+    events: &EventLog,
 ) -> Result<(), anyhow::Error> {
     let rc = Relaychain::new(network.relaychain().chain());
+    // last sample, for the report
+    events.sample(&network).await;
+    // the info is not longer valid
+    if let Some(base_dir) = network.base_dir() {
+        let _ = fs::remove_file(Path::new(base_dir).join(NETWORK_INFO_FILE)).await;
+    }
     let _ = network.destroy().await;
-    let teardown_signal = fs::try_exists(&stop_file).await;
 
-    if let Ok(true) = teardown_signal {
+    if stop.generate_artifacts {
         // create the artifacts
-        doppelganger::generate_artifacts(base_path.clone(), step, &rc)
-            .await
-            .expect("generate should works");
-        doppelganger::clean_up_dir_for_step(base_path, step, &rc)
-            .await
-            .expect("clean-up should works");
+        doppelganger::generate_artifacts(base_path.clone(), step, &rc).await?;
+        doppelganger::clean_up_dir_for_step(base_path, step, &rc).await?;
+    }
+
+    if let Err(e) = events
+        .write_report(&step.dir(), stop.generate_artifacts)
+        .await
+    {
+        warn!("Error writing the report: {e}");
     }
 
     // signal that the teardown is completed
     _ = fs::remove_file(stop_file).await;
 
-    Ok(())
+    match stop.error {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
 }
+
 #[tokio::main(flavor = "multi_thread")]
-async fn main() -> Result<(), anyhow::Error> {
+async fn main() {
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::builder()
                 .with_default_directive(LevelFilter::INFO.into())
                 .from_env_lossy(),
         )
+        .with_writer(progress::LogWriter)
         .init();
 
-    let args = Args::parse();
+    if let Err(e) = run(Args::parse()).await {
+        println!();
+        println!("\t\x1b[91m{e:#}\x1b[0m");
+        if let Some(bite_error) = e.downcast_ref::<BiteError>() {
+            println!("\tHelp: {}", bite_error.hint());
+        }
+
+        std::process::exit(error::exit_code(&e));
+    }
+}
+
+async fn run(args: Args) -> Result<(), anyhow::Error> {
     match args.cmd {
         Commands::Bite {
+            manifest,
             relay,
+            rc_spec,
+            rc_rpc,
+            rc_epoch_duration,
+            rc_chain_id,
             relay_runtime,
             relay_bite_at,
             ah_runtime,
@@ -148,100 +375,463 @@ async fn main() -> Result<(), anyhow::Error> {
             rc_sync_url,
             and_spawn,
             database,
+            rc_overrides,
+            para_overrides,
+            para_rpcs,
+            paras,
+            validators,
+            resume,
+            sync_timeout,
+            sync_stall_timeout,
+            skip_doctor,
+            on_signal,
+            metrics_port,
+            snapshot_codec,
+            snapshot_dir,
         } => {
-            let relaychain =
-                Relaychain::new_with_values(&relay, relay_runtime, rc_sync_url, relay_bite_at);
-            debug!("{:?}", relaychain);
-            let base_path = get_base_path(base_path);
-            let ah_rpc = if relaychain.as_chain_string() == "polkadot" {
-                AH_POLKADOT_RCP.to_string()
-            } else {
-                AH_KUSAMA_RCP.to_string()
+            let explicit_base_path = base_path
+                .clone()
+                .or_else(|| env::var("ZOMBIE_BITE_BASE_PATH").ok());
+            let manifest_path = match manifest {
+                Some(path) => Some(PathBuf::from(path)),
+                // reuse the config of the bite to resume
+                None if resume => explicit_base_path
+                    .as_ref()
+                    .map(|base_path| Path::new(base_path).join(RESOLVED_MANIFEST_FILE))
+                    .filter(|path| path.exists()),
+                None => None,
             };
+            let mut manifest = Manifest::load(manifest_path.as_deref()).await?;
+
+            // cli flags take precedence over the manifest
+            manifest.relay.chain = relay.or(manifest.relay.chain);
+            manifest.relay.runtime_override = relay_runtime.or(manifest.relay.runtime_override);
+            manifest.relay.bite_at = relay_bite_at.or(manifest.relay.bite_at);
+            manifest.relay.sync_url = rc_sync_url.or(manifest.relay.sync_url);
+            manifest.relay.overrides = rc_overrides.or(manifest.relay.overrides);
+            manifest.relay.spec = rc_spec.or(manifest.relay.spec);
+            if !rc_rpc.is_empty() {
+                manifest.relay.rpcs = Some(rc_rpc);
+            }
+            manifest.relay.epoch_duration = rc_epoch_duration.or(manifest.relay.epoch_duration);
+            manifest.relay.chain_id = rc_chain_id.or(manifest.relay.chain_id);
+            manifest.database = database.or(manifest.database);
+            manifest.validators = validators.or(manifest.validators);
+            manifest.sync.timeout = sync_timeout.or(manifest.sync.timeout);
+            manifest.sync.stall_timeout = sync_stall_timeout.or(manifest.sync.stall_timeout);
+            if let Some(codec) = snapshot_codec {
+                manifest.snapshot.codec = Some(codec.parse()?);
+            }
+            if snapshot_dir {
+                manifest.snapshot.dir = Some(true);
+            }
+            if !paras.is_empty() {
+                manifest.paras = paras
+                    .into_iter()
+                    .map(|name| {
+                        manifest.para(&name).cloned().unwrap_or(ParaManifest {
+                            name,
+                            ..Default::default()
+                        })
+                    })
+                    .collect();
+            }
+            if manifest.paras.is_empty() {
+                manifest.paras.push(ParaManifest {
+                    name: String::from("asset-hub"),
+                    ..Default::default()
+                });
+            }
+            for para in manifest.paras.iter_mut() {
+                if para.name == "asset-hub" {
+                    para.runtime_override = ah_runtime.clone().or(para.runtime_override.take());
+                    para.bite_at = ah_bite_at.clone().or(para.bite_at.take());
+                }
+                let para_id = Parachain::new(&para.name).id();
+                if let Some((_, file)) = para_overrides.iter().find(|(id, _)| *id == para_id) {
+                    para.overrides = Some(file.clone());
+                }
+                if let Some((_, rpcs)) = para_rpcs.iter().find(|(id, _)| *id == para_id) {
+                    para.rpcs = Some(rpcs.clone());
+                }
+            }
+            manifest.validate()?;
+            if resume && explicit_base_path.is_none() && manifest.base_path.is_none() {
+                return Err(BiteError::Config(String::from(
+                    "`--resume` needs the base path of the bite (`-d`, env 'ZOMBIE_BITE_BASE_PATH' or manifest)",
+                ))
+                .into());
+            }
+            set_manifest(manifest.clone());
+            serve_metrics(metrics_port.or(manifest.metrics_port()?), Step::Bite);
 
-            let ah = Parachain::AssetHub {
-                maybe_override: ah_runtime,
-                maybe_bite_at: ah_bite_at,
-                maybe_rpc_endpoint: Some(ah_rpc),
+            let relaychain = manifest.relaychain();
+            debug!("{:?}", relaychain);
+            let base_path = get_base_path(base_path);
+            let paras = manifest.parachains(&relaychain.as_chain_string());
+            if !skip_doctor {
+                let mut required_disk = doctor::bite_disk_estimate(&relaychain, &paras);
+                if resume {
+                    // discount what we already have
+                    required_disk = required_disk
+                        .saturating_sub(doctor::used_disk(&base_path.join(Step::Bite.dir())));
+                }
+                preflight(&base_path, required_disk, &[doctor::Flow::Bite]).await?;
+            }
+            manifest.dump_resolved(&base_path).await?;
+
+            let custom_overrides = CustomOverridesPaths {
+                rc: manifest.relay.overrides.as_ref().map(PathBuf::from),
+                paras: manifest
+                    .paras
+                    .iter()
+                    .filter_map(|para| {
+                        para.overrides
+                            .as_ref()
+                            .map(|file| (Parachain::new(&para.name).id(), PathBuf::from(file)))
+                    })
+                    .collect(),
             };
-            let _ = doppelganger_inner(base_path.clone(), relaychain, vec![ah], &database)
-                .await
-                .expect("bite should work");
+            let database = manifest
+                .database
+                .clone()
+                .unwrap_or_else(|| String::from("rocksdb"));
+            let validators = manifest.validators.unwrap_or(2);
+            doppelganger_inner(
+                base_path.clone(),
+                relaychain,
+                paras,
+                &database,
+                &keys::validator_names(validators as usize),
+                &custom_overrides,
+                resume,
+            )
+            .await?;
             if and_spawn {
                 let step = Step::Spawn;
                 // STOP file
                 let stop_file = format!("{}/{STOP_FILE}", base_path.to_string_lossy());
 
-                resolve_if_dir_exist(&base_path, step).await;
-                let network = doppelganger::spawn(step, base_path.as_path(), None, None)
-                    .await
-                    .expect("spawn should works");
-
-                ensure_startup_producing_blocks(&network).await;
-
-                post_spawn_loop(&stop_file, &network, true).await?;
-
-                tear_down_and_generate(&stop_file, step, network, base_path).await?;
+                resolve_if_dir_exist(&base_path, step).await?;
+                metrics::set_step(step);
+                let events = EventLog::new(base_path.join(step.dir()));
+                let network = doppelganger::spawn(step, base_path.as_path(), None, None).await?;
+                write_network_info(&network, &base_path, step).await?;
+
+                ensure_startup_producing_blocks(&network).await?;
+
+                let stop = post_spawn_loop(
+                    &stop_file,
+                    &network,
+                    true,
+                    &base_path,
+                    step,
+                    on_signal == "generate",
+                    &events,
+                )
+                .await?;
+
+                tear_down_and_generate(&stop_file, step, network, base_path, stop, &events).await?;
             }
         }
         Commands::Spawn {
+            manifest,
             base_path,
             with_monitor,
             step,
+            skip_doctor,
+            on_signal,
+            metrics_port,
+            snapshot_codec,
+            snapshot_dir,
         } => {
+            let mut manifest = Manifest::load_for_base_path(
+                manifest.as_deref().map(Path::new),
+                base_path.as_deref(),
+            )
+            .await?;
+            if let Some(codec) = snapshot_codec {
+                manifest.snapshot.codec = Some(codec.parse()?);
+            }
+            if snapshot_dir {
+                manifest.snapshot.dir = Some(true);
+            }
+            set_manifest(manifest);
             let step: Step = step.into();
+            serve_metrics(metrics_port.or(get_manifest().metrics_port()?), step);
             let base_path = get_base_path(base_path);
             let base_path_str = base_path.to_string_lossy();
 
             if !fs::try_exists(format!("{base_path_str}/{}", step.dir_from()))
                 .await
-                .expect("try_exist should work")
+                .unwrap_or_default()
             {
-                println!("");
-                println!("\t\x1b[91mThe 'bite' dir doesn't exist, please run the bite subcommand first.\x1b[0m");
-                println!("\tHelp: zombie-bite bite --help");
+                let dir = step.dir_from();
+                return Err(BiteError::Config(format!(
+                    "The '{dir}' dir doesn't exist in {base_path_str}, please run the {dir} subcommand first"
+                ))
+                .into());
+            }
 
-                std::process::exit(1);
+            if !skip_doctor {
+                let from_dir = base_path.join(step.dir_from());
+                preflight(
+                    &base_path,
+                    doctor::spawn_disk_estimate(&from_dir).await,
+                    &[doctor::Flow::Spawn],
+                )
+                .await?;
             }
 
-            resolve_if_dir_exist(&base_path, step).await;
+            resolve_if_dir_exist(&base_path, step).await?;
 
-            let network = doppelganger::spawn(step, base_path.as_path(), None, None)
-                .await
-                .expect("spawn should works");
+            let events = EventLog::new(base_path.join(step.dir()));
+            let network = doppelganger::spawn(step, base_path.as_path(), None, None).await?;
+            write_network_info(&network, &base_path, step).await?;
 
-            ensure_startup_producing_blocks(&network).await;
+            ensure_startup_producing_blocks(&network).await?;
 
             // STOP file
             let stop_file = format!("{base_path_str}/{STOP_FILE}");
 
-            post_spawn_loop(&stop_file, &network, with_monitor).await?;
+            let stop = post_spawn_loop(
+                &stop_file,
+                &network,
+                with_monitor,
+                &base_path,
+                step,
+                on_signal == "generate",
+                &events,
+            )
+            .await?;
+
+            tear_down_and_generate(&stop_file, step, network, base_path.clone(), stop, &events)
+                .await?;
+        }
+        Commands::Doctor {
+            manifest,
+            relay,
+            paras,
+            base_path,
+        } => {
+            let mut manifest = Manifest::load_for_base_path(
+                manifest.as_deref().map(Path::new),
+                base_path.as_deref(),
+            )
+            .await?;
+            manifest.relay.chain = relay.or(manifest.relay.chain);
+            if !paras.is_empty() {
+                manifest.paras = paras
+                    .into_iter()
+                    .map(|name| {
+                        manifest.para(&name).cloned().unwrap_or(ParaManifest {
+                            name,
+                            ..Default::default()
+                        })
+                    })
+                    .collect();
+            }
+            if manifest.paras.is_empty() {
+                manifest.paras.push(ParaManifest {
+                    name: String::from("asset-hub"),
+                    ..Default::default()
+                });
+            }
+            set_manifest(manifest.clone());
 
-            tear_down_and_generate(&stop_file, step, network, base_path).await?;
+            let relaychain = manifest.relaychain();
+            let paras = manifest.parachains(&relaychain.as_chain_string());
+            let base_path = get_base_path(base_path);
+            // check the environment for the whole run (bite and spawn)
+            preflight(
+                &base_path,
+                doctor::bite_disk_estimate(&relaychain, &paras),
+                &[doctor::Flow::Bite, doctor::Flow::Spawn],
+            )
+            .await?;
+            info!("✅ all checks passed");
+        }
+        Commands::Verify {
+            manifest,
+            base_path,
+            step,
+        } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
+            let step: Step = step.into();
+            let step_dir = get_base_path(base_path).join(step.dir());
+            info!("🔎 verifying the snapshots in {}", step_dir.display());
+            let report = snapshot::verify(&step_dir).await?;
+            report.print();
+            if !report.passed() {
+                return Err(BiteError::Snapshot(String::from(
+                    "snapshots verification failed (see the report above)",
+                ))
+                .into());
+            }
+            info!("✅ all snapshots verified");
+        }
+        Commands::Gc {
+            manifest,
+            base_path,
+            dry_run,
+        } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
+            let base_path = get_base_path(base_path);
+            let store = Store::for_base_path(&base_path);
+            let report = store.gc(&base_path, dry_run).await?;
+            for path in &report.removed {
+                info!(
+                    "🗑️  {}{}",
+                    if dry_run { "(dry run) " } else { "" },
+                    path.display()
+                );
+            }
+            info!(
+                "✅ {} snapshots removed ({} MiB), {} kept in {}",
+                report.removed.len(),
+                report.freed / (1024 * 1024),
+                report.kept,
+                store.dir().display()
+            );
+        }
+        Commands::Push {
+            manifest,
+            remote,
+            base_path,
+            step,
+        } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
+            let step: Step = step.into();
+            let base_path = get_base_path(base_path);
+            let backend = remote::backend(&remote)?;
+            let index = remote::push(&base_path, step, backend.as_ref()).await?;
+            info!(
+                "✅ {} artifacts of the {} step pushed to {remote}",
+                index.files.len(),
+                index.step
+            );
+        }
+        Commands::Pull {
+            manifest,
+            remote,
+            base_path,
+            step,
+        } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
+            let step: Step = step.into();
+            let base_path = get_base_path(base_path);
+            let backend = remote::backend(&remote)?;
+            let index = remote::pull(&base_path, step, backend.as_ref()).await?;
+            let report = snapshot::verify(&base_path.join(step.dir())).await?;
+            report.print();
+            if !report.passed() {
+                return Err(BiteError::Snapshot(String::from(
+                    "pulled snapshots verification failed (see the report above)",
+                ))
+                .into());
+            }
+            info!(
+                "✅ {} artifacts of the {} step pulled to {}",
+                index.files.len(),
+                index.step,
+                base_path.display()
+            );
+        }
+        Commands::Ctl {
+            manifest,
+            base_path,
+            request,
+        } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
+            let base_path = get_base_path(base_path);
+            let response = control::send(&control::socket_path(&base_path), &request).await?;
+            println!("{}", serde_json::to_string_pretty(&response)?);
+            if !response.ok {
+                return Err(BiteError::Spawn(response.error.unwrap_or_default()).into());
+            }
+        }
+        Commands::Info {
+            manifest,
+            base_path,
+            step,
+        } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
+            let step: Step = step.into();
+            let base_path = get_base_path(base_path);
+            let info = NetworkInfo::current(&base_path, &base_path.join(step.dir())).await?;
+            println!("{}", serde_json::to_string_pretty(&info)?);
         }
         Commands::GenerateArtifacts {
+            manifest,
             relay,
             base_path,
             step,
         } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
             let rc = Relaychain::new(&relay);
             let step: Step = step.into();
             let base_path = get_base_path(base_path);
-            doppelganger::generate_artifacts(base_path, step, &rc)
-                .await
-                .expect("generate artifacts should work")
+            doppelganger::generate_artifacts(base_path, step, &rc).await?;
         }
         Commands::CleanUpDir {
+            manifest,
             relay,
             base_path,
             step,
         } => {
+            set_manifest(
+                Manifest::load_for_base_path(
+                    manifest.as_deref().map(Path::new),
+                    base_path.as_deref(),
+                )
+                .await?,
+            );
             let rc = Relaychain::new(&relay);
             let step: Step = step.into();
             let base_path = get_base_path(base_path);
-            doppelganger::clean_up_dir_for_step(base_path, step, &rc)
-                .await
-                .expect("clean-up should works");
+            doppelganger::clean_up_dir_for_step(base_path, step, &rc).await?;
         }
     };
     Ok(())
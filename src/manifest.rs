@@ -0,0 +1,834 @@
+//! Declarative bite manifest (`zombie-bite.toml`).
+//!
+//! Precedence (higher first): cli flags > env vars > manifest > defaults.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::info;
+
+use crate::bite_at::BiteAt;
+use crate::config::{
+    chain_id_from_spec, CustomRelaySpec, Parachain, Relaychain, DEFAULT_EPOCH_DURATION,
+    STATE_PRUNING,
+};
+use crate::error::BiteError;
+use crate::monit::{BudgetAction, HealthCheckConfig};
+use crate::snapshot::Codec;
+
+/// Manifest used if `--manifest` is not provided (IFF exist in the cwd).
+pub const DEFAULT_MANIFEST_FILE: &str = "zombie-bite.toml";
+/// File (in the base path) with the resolved config used in the bite.
+pub const RESOLVED_MANIFEST_FILE: &str = "zombie-bite.resolved.toml";
+
+const DEFAULT_RC_LOG: &str = "babe=debug,grandpa=info,runtime=debug,consensus::common=debug,parachain=debug,parachain::gossip-support=info";
+const DEFAULT_COL_LOG: &str = "aura=debug,runtime=debug,cumulus-consensus=debug,consensus::common=debug,parachain::collation-generation=debug,parachain::collator-protocol=debug,parachain=debug,xcm=debug";
+
+const DEFAULT_SYNC_STALL_TIMEOUT: u64 = 1800;
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+const DEFAULT_RESTART_WINDOW: u64 = 3600;
+const DEFAULT_BACKOFF: u64 = 60;
+const DEFAULT_MAX_BACKOFF: u64 = 900;
+
+const KNOWN_RELAYCHAINS: [&str; 3] = ["polkadot", "kusama", "paseo"];
+
+static MANIFEST: OnceLock<Manifest> = OnceLock::new();
+
+/// e.g:
+///
+/// ```toml
+/// base_path = "/tmp/ahm-migration"
+/// database = "paritydb"
+/// validators = 3
+///
+/// [relay]
+/// chain = "kusama"
+/// runtime_override = "./kusama_runtime.compact.compressed.wasm"
+/// bite_at = 29798496 # or hash, "finalized", "finalized-<N>" or timestamp
+/// log = "babe=debug,runtime=debug"
+/// extra_args = ["--db-cache=4096"]
+///
+/// [[paras]]
+/// name = "asset-hub"
+/// runtime_override = "./asset_hub_kusama_runtime.compact.compressed.wasm"
+///
+/// [[paras]]
+/// name = "coretime"
+/// port = 9946 # rpc port of the collator
+///
+/// [ports]
+/// alice = 9944
+///
+/// [snapshot]
+/// codec = "zstd" # gzip (default), zstd or none
+/// dir = true # keep the uncompressed db, to spawn without extracting the snapshot
+///
+/// [monitor]
+/// max_restarts = 3 # per node in `restart_window` (secs)
+/// on_budget_exhausted = "teardown" # or "abort"
+///
+/// # monitor health checks (default to `best-progress` with `restart`)
+/// [[monitor.checks]]
+/// kind = "finalized-lag" # best-progress, para-inclusion, peers or log-watch
+/// nodes = ["alice"]
+/// interval = 60
+/// threshold = 20
+/// action = "alert" # restart, alert or stop
+/// ```
+///
+/// Custom relaychains (not polkadot/kusama/paseo) need the `rpcs` and can set the `spec`,
+/// `epoch_duration` and `chain_id`:
+///
+/// ```toml
+/// [relay]
+/// chain = "westend"
+/// spec = "./westend.json"
+/// rpcs = ["wss://westend-rpc.polkadot.io", "wss://westend-rpc.dwellir.com"]
+/// epoch_duration = 600
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    pub base_path: Option<String>,
+    pub database: Option<String>,
+    /// Value of the `--state-pruning` flag.
+    pub state_pruning: Option<String>,
+    /// Number of relaychain validators.
+    pub validators: Option<u32>,
+    /// Sudo key to set in sudo pallet (IFF present) and in RC manager.
+    pub sudo: Option<String>,
+    /// Path to move the chain-spec/snapshot of the bite (used in ci).
+    pub ci_path: Option<String>,
+    /// Dump the overridden keys in the doppelganger nodes.
+    pub dump: Option<bool>,
+    pub relay: RelayManifest,
+    pub paras: Vec<ParaManifest>,
+    pub collators: CollatorsManifest,
+    pub ports: PortsManifest,
+    pub sync: SyncManifest,
+    pub monitor: MonitorManifest,
+    pub snapshot: SnapshotManifest,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelayManifest {
+    pub chain: Option<String>,
+    pub runtime_override: Option<String>,
+    pub bite_at: Option<BiteAt>,
+    pub sync_url: Option<String>,
+    /// Custom overrides file (see `--rc-overrides`).
+    pub overrides: Option<String>,
+    pub log: Option<String>,
+    pub extra_args: Option<Vec<String>>,
+    /// Rpc endpoints (in order of preference), required for custom relaychains.
+    pub rpcs: Option<Vec<String>>,
+    /// Chain spec (path or name) of a custom relaychain.
+    pub spec: Option<String>,
+    /// Epoch duration (in blocks) of a custom relaychain.
+    pub epoch_duration: Option<u64>,
+    /// Chain id of a custom relaychain (default to the `id` in the spec, or the chain name).
+    pub chain_id: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParaManifest {
+    pub name: String,
+    pub runtime_override: Option<String>,
+    pub bite_at: Option<BiteAt>,
+    /// Custom overrides file (see `--para-overrides`).
+    pub overrides: Option<String>,
+    /// Rpc endpoints (in order of preference).
+    pub rpcs: Option<Vec<String>>,
+    /// Rpc port of the collator [default: random, `ports.asset_hub` for asset-hub].
+    pub port: Option<u16>,
+}
+
+/// Settings applied to all the collators.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollatorsManifest {
+    pub log: Option<String>,
+    pub extra_args: Option<Vec<String>>,
+}
+
+/// Limits of the chains sync (in secs).
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncManifest {
+    /// Max time to sync each chain [default: no limit].
+    pub timeout: Option<u64>,
+    /// Max time without progress (imported blocks or warp download/import).
+    pub stall_timeout: Option<u64>,
+}
+
+impl SyncManifest {
+    pub fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    pub fn stall_timeout(&self) -> u64 {
+        self.stall_timeout.unwrap_or(DEFAULT_SYNC_STALL_TIMEOUT)
+    }
+}
+
+/// Snapshots of the nodes dbs (artifacts of each step).
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotManifest {
+    /// Codec of the snapshots (gzip, zstd or none) [default: gzip].
+    pub codec: Option<Codec>,
+    /// Threads used to compress each snapshot [default: available cpus].
+    pub threads: Option<usize>,
+    /// Content addressed store of the snapshots, can be shared by several base paths
+    /// [default: `<base_path>/store`].
+    pub store: Option<String>,
+    /// Also keep the db uncompressed next to the snapshot (`<name>-snap.d`), so the spawn can
+    /// clone it (reflinks / hardlinks) instead of extracting the snapshot [default: false].
+    pub dir: Option<bool>,
+}
+
+impl SnapshotManifest {
+    pub fn codec(&self) -> Codec {
+        self.codec.unwrap_or_default()
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
+
+    /// Store dir from the env (`ZOMBIE_BITE_STORE`) or the manifest.
+    pub fn store(&self) -> Option<String> {
+        env::var("ZOMBIE_BITE_STORE")
+            .ok()
+            .or_else(|| self.store.clone())
+    }
+
+    pub fn dir(&self) -> bool {
+        self.dir.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PortsManifest {
+    pub alice: Option<u16>,
+    pub bob: Option<u16>,
+    /// Rpc port of the asset-hub collator.
+    pub asset_hub: Option<u16>,
+    /// Port to serve the zombie-bite metrics (`/metrics`).
+    pub metrics: Option<u16>,
+}
+
+/// Monitor (`--with-monitor`) settings and health checks, see [HealthCheckConfig].
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitorManifest {
+    /// Default interval (secs) of the checks without one.
+    pub interval: Option<u64>,
+    /// Secs to wait after the spawn before the first checks.
+    pub grace_period: Option<u64>,
+    /// Max restarts of a node in `restart_window` (secs).
+    pub max_restarts: Option<u32>,
+    pub restart_window: Option<u64>,
+    /// Secs to wait before checking a restarted node, doubled on each consecutive restart
+    /// (up to `max_backoff`).
+    pub backoff: Option<u64>,
+    pub max_backoff: Option<u64>,
+    /// What to do when a node runs out of restarts (`abort` or `teardown`).
+    pub on_budget_exhausted: Option<BudgetAction>,
+    pub checks: Vec<HealthCheckConfig>,
+}
+
+impl MonitorManifest {
+    pub fn grace_period(&self) -> u64 {
+        self.grace_period.unwrap_or_default()
+    }
+
+    pub fn max_restarts(&self) -> u32 {
+        self.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS)
+    }
+
+    pub fn restart_window(&self) -> u64 {
+        self.restart_window.unwrap_or(DEFAULT_RESTART_WINDOW)
+    }
+
+    pub fn backoff(&self) -> u64 {
+        self.backoff.unwrap_or(DEFAULT_BACKOFF)
+    }
+
+    pub fn max_backoff(&self) -> u64 {
+        self.max_backoff.unwrap_or(DEFAULT_MAX_BACKOFF)
+    }
+
+    pub fn on_budget_exhausted(&self) -> BudgetAction {
+        self.on_budget_exhausted.unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<(), BiteError> {
+        if self.interval == Some(0) || self.restart_window == Some(0) {
+            return Err(BiteError::Config(String::from(
+                "monitor interval / restart_window should be greater than 0",
+            )));
+        }
+        if self.backoff() > self.max_backoff() {
+            return Err(BiteError::Config(format!(
+                "monitor backoff ({}) can't be greater than max_backoff ({})",
+                self.backoff(),
+                self.max_backoff()
+            )));
+        }
+        for check in &self.checks {
+            check.validate()?;
+        }
+
+        Ok(())
+    }
+
+    fn resolved(&self) -> Self {
+        Self {
+            interval: self.interval,
+            grace_period: Some(self.grace_period()),
+            max_restarts: Some(self.max_restarts()),
+            restart_window: Some(self.restart_window()),
+            backoff: Some(self.backoff()),
+            max_backoff: Some(self.max_backoff()),
+            on_budget_exhausted: Some(self.on_budget_exhausted()),
+            checks: self.checks.clone(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Load the manifest from the supplied path, or from `zombie-bite.toml` in the cwd (IFF exist).
+    /// Returns the default (empty) manifest otherwise.
+    pub async fn load(maybe_path: Option<&Path>) -> Result<Self, anyhow::Error> {
+        check_env_ports()?;
+        let path = match maybe_path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default_path = PathBuf::from(DEFAULT_MANIFEST_FILE);
+                if !fs::try_exists(&default_path).await.unwrap_or_default() {
+                    return Ok(Self::default());
+                }
+                default_path
+            }
+        };
+
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|e| BiteError::io("reading manifest", &path, e))?;
+        let manifest = toml::from_str(&content).map_err(|e| {
+            BiteError::Config(format!("Error parsing manifest {}: {e}", path.display()))
+        })?;
+        info!("using manifest {}", path.display());
+        Ok(manifest)
+    }
+
+    /// Manifest of the commands run over an existing base path: `maybe_path` (`--manifest`),
+    /// `zombie-bite.toml` (IFF exist in the cwd) or the resolved manifest of the bite in the
+    /// base path (cli arg or env 'ZOMBIE_BITE_BASE_PATH').
+    pub async fn load_for_base_path(
+        maybe_path: Option<&Path>,
+        cli_base_path: Option<&str>,
+    ) -> Result<Self, anyhow::Error> {
+        if maybe_path.is_some()
+            || fs::try_exists(DEFAULT_MANIFEST_FILE)
+                .await
+                .unwrap_or_default()
+        {
+            return Self::load(maybe_path).await;
+        }
+        let resolved = cli_base_path
+            .map(String::from)
+            .or_else(|| env::var("ZOMBIE_BITE_BASE_PATH").ok())
+            .map(|base_path| Path::new(&base_path).join(RESOLVED_MANIFEST_FILE))
+            .filter(|path| path.exists());
+        Self::load(resolved.as_deref()).await
+    }
+
+    /// Ensure the values (not validated by clap) are valid.
+    pub fn validate(&self) -> Result<(), BiteError> {
+        if let Some(chain) = &self.relay.chain {
+            if chain.is_empty() {
+                return Err(BiteError::Config(String::from(
+                    "invalid (empty) relay chain",
+                )));
+            }
+            if !KNOWN_RELAYCHAINS.contains(&chain.as_str()) && self.relay.rpcs.is_none() {
+                return Err(BiteError::Config(format!(
+                    "custom relay chain '{chain}' needs an rpc endpoint (`--rc-rpc` or `relay.rpcs`)"
+                )));
+            }
+        }
+        let all_rpcs = std::iter::once(&self.relay.rpcs).chain(self.paras.iter().map(|p| &p.rpcs));
+        if all_rpcs.flatten().any(|rpcs| rpcs.is_empty()) {
+            return Err(BiteError::Config(String::from("rpcs list can't be empty")));
+        }
+        if self.relay.epoch_duration == Some(0) {
+            return Err(BiteError::Config(String::from(
+                "epoch duration should be greater than 0",
+            )));
+        }
+        for para in &self.paras {
+            if ![
+                "asset-hub",
+                "coretime",
+                "people",
+                "bridge-hub",
+                "collectives",
+            ]
+            .contains(&para.name.as_str())
+            {
+                return Err(BiteError::Config(format!(
+                    "invalid para '{}' in manifest",
+                    para.name
+                )));
+            }
+        }
+        if let Some(database) = &self.database {
+            if !["rocksdb", "paritydb"].contains(&database.as_str()) {
+                return Err(BiteError::Config(format!(
+                    "invalid database '{database}' in manifest"
+                )));
+            }
+        }
+        if self.validators == Some(0) {
+            return Err(BiteError::Config(String::from(
+                "at least one validator is needed",
+            )));
+        }
+        if self.sync.timeout == Some(0) || self.sync.stall_timeout == Some(0) {
+            return Err(BiteError::Config(String::from(
+                "sync timeout / stall_timeout should be greater than 0",
+            )));
+        }
+        if self.snapshot.threads == Some(0) {
+            return Err(BiteError::Config(String::from(
+                "snapshot threads should be greater than 0",
+            )));
+        }
+        self.monitor.validate()?;
+
+        Ok(())
+    }
+
+    /// Relaychain to bite (default to polkadot).
+    pub fn relaychain(&self) -> Relaychain {
+        let relay = &self.relay;
+        let chain = relay.chain.as_deref().unwrap_or("polkadot");
+        if KNOWN_RELAYCHAINS.contains(&chain) {
+            return Relaychain::new_with_values(
+                chain,
+                relay.runtime_override.clone(),
+                relay.sync_url.clone(),
+                relay.rpcs.clone(),
+                relay.bite_at.clone(),
+            );
+        }
+
+        let mut spec = CustomRelaySpec::from_name(chain);
+        if let Some(chain_spec) = &relay.spec {
+            spec.chain_spec = chain_spec.clone();
+        }
+        spec.epoch_duration = relay.epoch_duration.unwrap_or(DEFAULT_EPOCH_DURATION);
+        spec.chain_id = relay
+            .chain_id
+            .clone()
+            .or_else(|| chain_id_from_spec(&spec.chain_spec))
+            .unwrap_or(spec.chain_id);
+
+        Relaychain::new_custom(
+            chain,
+            spec,
+            relay.runtime_override.clone(),
+            relay.sync_url.clone(),
+            relay.rpcs.clone(),
+            relay.bite_at.clone(),
+        )
+    }
+
+    /// Parachains to bite, with the configured (or default) rpc endpoints.
+    pub fn parachains(&self, relay_part: &str) -> Vec<Parachain> {
+        self.paras
+            .iter()
+            .map(|para| {
+                let rpcs = para.rpcs.clone().unwrap_or_else(|| {
+                    Parachain::new(&para.name).default_rpc_endpoints(relay_part)
+                });
+                Parachain::new_with_values(
+                    &para.name,
+                    para.runtime_override.clone(),
+                    para.bite_at.clone(),
+                    Some(rpcs),
+                )
+            })
+            .collect()
+    }
+
+    pub fn para(&self, name: &str) -> Option<&ParaManifest> {
+        self.paras.iter().find(|para| para.name == name)
+    }
+
+    pub fn state_pruning(&self) -> String {
+        env::var("ZOMBIE_BITE_STATE_PRUNING")
+            .ok()
+            .or_else(|| self.state_pruning.clone())
+            .unwrap_or_else(|| STATE_PRUNING.to_string())
+    }
+
+    pub fn rc_log(&self) -> String {
+        env::var("RUST_LOG_RC")
+            .ok()
+            .or_else(|| self.relay.log.clone())
+            .unwrap_or_else(|| DEFAULT_RC_LOG.to_string())
+    }
+
+    pub fn col_log(&self) -> String {
+        env::var("RUST_LOG_COL")
+            .ok()
+            .or_else(|| self.collators.log.clone())
+            .unwrap_or_else(|| DEFAULT_COL_LOG.to_string())
+    }
+
+    pub fn rc_extra_args(&self) -> Vec<String> {
+        extra_args_from_env("ZOMBIE_BITE_RC_EXTRA_ARGS")
+            .or_else(|| self.relay.extra_args.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn col_extra_args(&self) -> Vec<String> {
+        extra_args_from_env("ZOMBIE_BITE_AH_EXTRA_ARGS")
+            .or_else(|| self.collators.extra_args.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn alice_port(&self) -> Result<Option<u16>, BiteError> {
+        // backward compatibility
+        Ok(port_from_env("ZOMBIE_BITE_RC_PORT")?
+            .or(port_from_env("ZOMBIE_BITE_ALICE_PORT")?)
+            .or(self.ports.alice))
+    }
+
+    pub fn bob_port(&self) -> Result<Option<u16>, BiteError> {
+        Ok(port_from_env("ZOMBIE_BITE_BOB_PORT")?.or(self.ports.bob))
+    }
+
+    pub fn asset_hub_port(&self) -> Result<Option<u16>, BiteError> {
+        Ok(port_from_env("ZOMBIE_BITE_AH_PORT")?.or(self.ports.asset_hub))
+    }
+
+    /// Rpc port of the para collator (if set).
+    pub fn collator_port(&self, para: &Parachain) -> Result<Option<u16>, BiteError> {
+        let port = self
+            .para(para.as_para_part())
+            .and_then(|para_manifest| para_manifest.port);
+        match para {
+            Parachain::AssetHub { .. } => Ok(self.asset_hub_port()?.or(port)),
+            _ => Ok(port),
+        }
+    }
+
+    pub fn metrics_port(&self) -> Result<Option<u16>, BiteError> {
+        Ok(port_from_env("ZOMBIE_BITE_METRICS_PORT")?.or(self.ports.metrics))
+    }
+
+    pub fn sudo(&self) -> Option<String> {
+        env::var("ZOMBIE_SUDO").ok().or_else(|| self.sudo.clone())
+    }
+
+    pub fn ci_path(&self) -> Option<String> {
+        env::var("ZOMBIE_BITE_CI_PATH")
+            .ok()
+            .or_else(|| self.ci_path.clone())
+    }
+
+    pub fn dump(&self) -> bool {
+        env::var("ZOMBIE_DUMP").is_ok() || self.dump.unwrap_or_default()
+    }
+
+    /// The manifest with the env vars applied, to dump in the base path.
+    pub fn resolved(&self) -> Result<Self, BiteError> {
+        let mut resolved = self.clone();
+        resolved.state_pruning = Some(self.state_pruning());
+        resolved.sudo = self.sudo();
+        resolved.ci_path = self.ci_path();
+        resolved.dump = Some(self.dump());
+        resolved.relay.log = Some(self.rc_log());
+        resolved.relay.extra_args = Some(self.rc_extra_args());
+        resolved.collators.log = Some(self.col_log());
+        resolved.collators.extra_args = Some(self.col_extra_args());
+        resolved.ports = PortsManifest {
+            alice: self.alice_port()?,
+            bob: self.bob_port()?,
+            asset_hub: self.asset_hub_port()?,
+            metrics: self.metrics_port()?,
+        };
+        resolved.sync.stall_timeout = Some(self.sync.stall_timeout());
+        resolved.monitor = self.monitor.resolved();
+        resolved.snapshot.codec = Some(self.snapshot.codec());
+        resolved.snapshot.store = self.snapshot.store();
+        resolved.snapshot.dir = Some(self.snapshot.dir());
+        Ok(resolved)
+    }
+
+    /// Write the resolved manifest in the base path (created if needed).
+    pub async fn dump_resolved(&self, base_path: &Path) -> Result<PathBuf, anyhow::Error> {
+        fs::create_dir_all(base_path)
+            .await
+            .map_err(|e| BiteError::io("creating", base_path, e))?;
+        let path = base_path.join(RESOLVED_MANIFEST_FILE);
+        fs::write(&path, toml::to_string_pretty(&self.resolved()?)?)
+            .await
+            .map_err(|e| anyhow!("Error writing resolved manifest {}: {e}", path.display()))?;
+        info!("resolved config written to {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Env vars with the ports (checked in [Manifest::load]).
+const PORT_ENV_VARS: [&str; 5] = [
+    "ZOMBIE_BITE_RC_PORT",
+    "ZOMBIE_BITE_ALICE_PORT",
+    "ZOMBIE_BITE_BOB_PORT",
+    "ZOMBIE_BITE_AH_PORT",
+    "ZOMBIE_BITE_METRICS_PORT",
+];
+
+fn parse_port(var: &str, port: &str) -> Result<u16, BiteError> {
+    port.trim()
+        .parse()
+        .map_err(|_| BiteError::Config(format!("env var {var} must be a valid port, got '{port}'")))
+}
+
+fn check_env_ports() -> Result<(), BiteError> {
+    for var in PORT_ENV_VARS {
+        if let Ok(port) = env::var(var) {
+            parse_port(var, &port)?;
+        }
+    }
+    Ok(())
+}
+
+fn port_from_env(var: &str) -> Result<Option<u16>, BiteError> {
+    env::var(var)
+        .ok()
+        .map(|port| parse_port(var, &port))
+        .transpose()
+}
+
+fn extra_args_from_env(var: &str) -> Option<Vec<String>> {
+    env::var(var).ok().map(|extra_args| {
+        extra_args
+            .split(',')
+            .map(|extra| extra.trim().to_string())
+            .collect()
+    })
+}
+
+/// Set the manifest to use globally, should be called (once) before any access.
+pub fn set_manifest(manifest: Manifest) {
+    if MANIFEST.set(manifest).is_err() {
+        tracing::warn!("manifest already set, ignoring");
+    }
+}
+
+/// Global manifest (default if not set).
+pub fn get_manifest() -> &'static Manifest {
+    MANIFEST.get_or_init(Manifest::default)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manifest_should_parse() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+base_path = "/tmp/ahm-migration"
+validators = 3
+state_pruning = "1000"
+
+[relay]
+chain = "kusama"
+bite_at = 29798496
+
+[[paras]]
+name = "asset-hub"
+runtime_override = "./ah.wasm"
+
+[[paras]]
+name = "coretime"
+port = 9946
+
+[ports]
+bob = 9955
+
+[snapshot]
+codec = "zstd"
+dir = true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.validators, Some(3));
+        assert_eq!(manifest.relay.chain.as_deref(), Some("kusama"));
+        assert_eq!(manifest.paras.len(), 2);
+        assert_eq!(
+            manifest
+                .para("asset-hub")
+                .unwrap()
+                .runtime_override
+                .as_deref(),
+            Some("./ah.wasm")
+        );
+        assert_eq!(
+            manifest.collator_port(&Parachain::new("coretime")).unwrap(),
+            Some(9946)
+        );
+        assert_eq!(
+            manifest.collator_port(&Parachain::new("people")).unwrap(),
+            None
+        );
+
+        let resolved = manifest.resolved().unwrap();
+        assert_eq!(resolved.state_pruning.as_deref(), Some("1000"));
+        assert_eq!(resolved.ports.bob, Some(9955));
+        assert_eq!(resolved.snapshot.codec, Some(Codec::Zstd));
+        assert_eq!(resolved.snapshot.dir, Some(true));
+        assert!(resolved.relay.log.is_some());
+        // resolved manifest can be used as manifest
+        let dumped = toml::to_string_pretty(&resolved).unwrap();
+        assert_eq!(toml::from_str::<Manifest>(&dumped).unwrap(), resolved);
+    }
+
+    #[test]
+    fn invalid_values_should_fail() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+[[paras]]
+name = "asset-hubs"
+"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_err());
+
+        let manifest: Manifest = toml::from_str(
+            r#"
+[[monitor.checks]]
+kind = "log-watch"
+pattern = "(panicked"
+"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_err());
+
+        let manifest: Manifest = toml::from_str("[sync]\nstall_timeout = 0").unwrap();
+        assert!(manifest.validate().is_err());
+
+        assert!(toml::from_str::<Manifest>("[snapshot]\ncodec = \"xz\"").is_err());
+        let manifest: Manifest = toml::from_str("[snapshot]\nthreads = 0").unwrap();
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn custom_relay_should_work() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+[relay]
+chain = "westend"
+spec = "westend2"
+rpcs = ["wss://westend-rpc.polkadot.io"]
+epoch_duration = 600
+"#,
+        )
+        .unwrap();
+        manifest.validate().unwrap();
+
+        let relay = manifest.relaychain();
+        assert!(relay.is_custom());
+        assert_eq!(relay.chain_arg(), "westend2");
+        // spec is not a file, so we fallback to the chain name
+        assert_eq!(relay.chain_id(), "westend");
+        assert_eq!(relay.epoch_duration(), 600);
+        assert_eq!(relay.rpc_endpoints(), vec!["wss://westend-rpc.polkadot.io"]);
+
+        // rpc is required for custom relaychains
+        let manifest: Manifest = toml::from_str("[relay]\nchain = \"westend\"").unwrap();
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn para_rpcs_should_work() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+[[paras]]
+name = "asset-hub"
+rpcs = ["wss://a", "wss://b"]
+
+[[paras]]
+name = "coretime"
+"#,
+        )
+        .unwrap();
+        manifest.validate().unwrap();
+
+        let paras = manifest.parachains("paseo");
+        assert_eq!(paras[0].rpc_endpoints(), ["wss://a", "wss://b"]);
+        assert_eq!(
+            paras[1].rpc_endpoints(),
+            Parachain::new("coretime").default_rpc_endpoints("paseo")
+        );
+    }
+
+    #[test]
+    fn unknown_fields_should_fail() {
+        assert!(toml::from_str::<Manifest>("relay_chain = \"kusama\"").is_err());
+    }
+
+    #[test]
+    fn invalid_env_ports_should_fail() {
+        assert_eq!(parse_port("ZOMBIE_BITE_BOB_PORT", "9955").unwrap(), 9955);
+        for port in ["", "99999", "bob"] {
+            assert!(matches!(
+                parse_port("ZOMBIE_BITE_BOB_PORT", port),
+                Err(BiteError::Config(_))
+            ));
+        }
+        std::env::set_var("ZOMBIE_BITE_TEST_INVALID_PORT", "bob");
+        assert!(matches!(
+            port_from_env("ZOMBIE_BITE_TEST_INVALID_PORT"),
+            Err(BiteError::Config(_))
+        ));
+        assert_eq!(port_from_env("ZOMBIE_BITE_TEST_UNSET_PORT").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn resolved_should_be_dumped_in_new_base_path() {
+        let base_path = std::env::temp_dir()
+            .join(format!("zombie-bite-resolved-{}", std::process::id()))
+            .join("fresh");
+        assert!(!base_path.exists());
+        let path = Manifest::default().dump_resolved(&base_path).await.unwrap();
+        let dumped: Manifest = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(dumped, Manifest::default().resolved().unwrap());
+        // used by the commands run over the base path
+        let loaded = Manifest::load_for_base_path(None, base_path.to_str())
+            .await
+            .unwrap();
+        assert_eq!(loaded, dumped);
+        std::fs::remove_dir_all(base_path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+//! Prometheus metrics (`/metrics`) of zombie-bite itself (step, sync progress, snapshots,
+//! restarts and heights of the nodes), to track long bites/spawns from grafana.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, OnceLock},
+    time::Instant,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info};
+
+use crate::config::Step;
+use crate::error::BiteError;
+
+pub const STEP: &str = "zombie_bite_step";
+pub const SYNC_BEST_BLOCK: &str = "zombie_bite_sync_best_block";
+pub const SYNC_TARGET_BLOCK: &str = "zombie_bite_sync_target_block";
+pub const SYNCING: &str = "zombie_bite_syncing";
+pub const SNAPSHOT_BYTES: &str = "zombie_bite_snapshot_bytes_total";
+pub const MONITOR_RESTARTS: &str = "zombie_bite_monitor_restarts_total";
+pub const BEST_BLOCK: &str = "zombie_bite_best_block";
+pub const FINALIZED_BLOCK: &str = "zombie_bite_finalized_block";
+pub const SECONDS_SINCE_LAST_BLOCK: &str = "zombie_bite_seconds_since_last_block";
+
+/// (name, type, help)
+const METRICS: [(&str, &str, &str); 9] = [
+    (STEP, "gauge", "Current step (1 for the running one)"),
+    (
+        SYNC_BEST_BLOCK,
+        "gauge",
+        "Best block of the sync node, by chain",
+    ),
+    (
+        SYNC_TARGET_BLOCK,
+        "gauge",
+        "Sync target of the sync node, by chain",
+    ),
+    (
+        SYNCING,
+        "gauge",
+        "If the chain is syncing (1) or synced (0)",
+    ),
+    (SNAPSHOT_BYTES, "counter", "Bytes written in snapshots"),
+    (
+        MONITOR_RESTARTS,
+        "counter",
+        "Nodes restarted by the monitor",
+    ),
+    (BEST_BLOCK, "gauge", "Last observed best block, by node"),
+    (
+        FINALIZED_BLOCK,
+        "gauge",
+        "Last observed finalized block, by node",
+    ),
+    (
+        SECONDS_SINCE_LAST_BLOCK,
+        "gauge",
+        "Seconds since the best block of the node changed",
+    ),
+];
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// (name, labels) -> value
+    values: BTreeMap<(String, String), f64>,
+    /// When the best block of each node changed for the last time
+    last_block: HashMap<String, (f64, Instant)>,
+}
+
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    let registry = REGISTRY.get_or_init(Default::default);
+    // a poisoned registry is still valid (only values)
+    let mut guard = registry.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+fn labels_str(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+pub fn set(name: &str, labels: &[(&str, &str)], value: f64) {
+    with_registry(|r| {
+        r.values
+            .insert((name.to_string(), labels_str(labels)), value);
+    });
+}
+
+pub fn inc(name: &str, labels: &[(&str, &str)], by: f64) {
+    with_registry(|r| {
+        *r.values
+            .entry((name.to_string(), labels_str(labels)))
+            .or_default() += by;
+    });
+}
+
+pub fn set_step(step: Step) {
+    for s in [Step::Bite, Step::Spawn, Step::Post, Step::After] {
+        let dir = s.dir();
+        set(
+            STEP,
+            &[("step", dir.as_str())],
+            if s == step { 1_f64 } else { 0_f64 },
+        );
+    }
+}
+
+/// Best / finalized blocks observed for the node.
+pub fn observe_heights(node: &str, best: f64, finalized: f64) {
+    set(BEST_BLOCK, &[("node", node)], best);
+    set(FINALIZED_BLOCK, &[("node", node)], finalized);
+    with_registry(|r| match r.last_block.get(node) {
+        Some((last_best, _)) if *last_best >= best => {}
+        _ => {
+            r.last_block
+                .insert(node.to_string(), (best, Instant::now()));
+        }
+    });
+}
+
+/// Metrics in the prometheus text format.
+pub fn render() -> String {
+    with_registry(|r| {
+        let mut values = r.values.clone();
+        for (node, (_, at)) in &r.last_block {
+            values.insert(
+                (
+                    SECONDS_SINCE_LAST_BLOCK.to_string(),
+                    labels_str(&[("node", node)]),
+                ),
+                at.elapsed().as_secs() as f64,
+            );
+        }
+
+        let mut out = String::new();
+        for (name, kind, help) in METRICS {
+            let mut samples = values
+                .range((name.to_string(), String::new())..)
+                .take_while(|((n, _), _)| n == name)
+                .peekable();
+            if samples.peek().is_none() {
+                continue;
+            }
+            out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n"));
+            for ((_, labels), value) in samples {
+                out.push_str(&format!("{name}{labels} {value}\n"));
+            }
+        }
+        out
+    })
+}
+
+/// Bind the metrics port, only in the loopback interface (use port 0 for a random one).
+pub async fn bind(port: u16) -> Result<TcpListener, BiteError> {
+    TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| BiteError::Config(format!("Error binding metrics port {port}: {e}")))
+}
+
+/// Serve the metrics (`/metrics`) in the supplied listener.
+pub async fn serve(listener: TcpListener) -> Result<(), BiteError> {
+    let addr = listener
+        .local_addr()
+        .map_err(|e| BiteError::Config(format!("Error reading the metrics address: {e}")))?;
+    info!("📈 metrics available at http://{addr}/metrics");
+
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream).await {
+                debug!("metrics connection closed with error: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream) -> Result<(), anyhow::Error> {
+    // we only need the request line (e.g. `GET /metrics HTTP/1.1`)
+    let mut buf = [0_u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", render())
+    } else {
+        ("404 Not Found", String::from("not found\n"))
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn metrics_should_render() {
+        inc(MONITOR_RESTARTS, &[("node", "test-alice")], 1_f64);
+        inc(MONITOR_RESTARTS, &[("node", "test-alice")], 1_f64);
+        observe_heights("test-bob", 100_f64, 98_f64);
+        // lower best don't reset the last block time
+        observe_heights("test-bob", 99_f64, 98_f64);
+
+        let out = render();
+        assert!(out.contains("# TYPE zombie_bite_monitor_restarts_total counter\n"));
+        assert!(out.contains("zombie_bite_monitor_restarts_total{node=\"test-alice\"} 2\n"));
+        assert!(out.contains("zombie_bite_finalized_block{node=\"test-bob\"} 98\n"));
+        assert!(out.contains("zombie_bite_seconds_since_last_block{node=\"test-bob\"} 0\n"));
+    }
+
+    #[tokio::test]
+    async fn server_should_serve_metrics() {
+        set(SYNCING, &[("chain", "test-polkadot")], 1_f64);
+        let listener = bind(0).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        let mut body = None;
+        for _ in 0..50 {
+            if let Ok(response) = reqwest::get(format!("http://127.0.0.1:{port}/metrics")).await {
+                body = Some(response.text().await.unwrap());
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(body
+            .expect("server should reply")
+            .contains("zombie_bite_syncing{chain=\"test-polkadot\"} 1\n"));
+    }
+}
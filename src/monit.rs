@@ -1,112 +1,822 @@
-use std::time::Duration;
+//! Monitor of the running network, using pluggable health checks (each one with its own
+//! interval, threshold, nodes and remediation action).
 
-use tokio::fs;
-use tracing::{debug, trace, warn};
-use zombienet_sdk::NetworkNode;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-const CHECK_TIMEOUT_SECS: u64 = 600; // 10 mins
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, time::Instant};
+use tracing::{debug, error, info, trace, warn};
+use zombienet_sdk::{LocalFileSystem, Network, NetworkNode};
 
-async fn restart(node: &NetworkNode, checkpoint: impl Into<f64>) {
-    if (node.restart(None).await).is_ok() {
-        warn!(
-            "{} was restarted at block {}",
-            node.name(),
-            checkpoint.into()
-        );
-    } else {
-        warn!("Error restarting {}", node.name());
+use crate::error::BiteError;
+use crate::manifest::MonitorManifest;
+use crate::metrics;
+use crate::network_info::node_log_path;
+use crate::report::{Event, EventLog};
+use crate::utils::{para_head_key, rpc_call, LogTail};
+
+const DEFAULT_LOG_PATTERN: &str = "panicked|Failed to import";
+/// How often we check the stop file and sample the block heights
+const STOP_FILE_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckKind {
+    /// Best block should progress at least `threshold` blocks between checks
+    BestProgress,
+    /// Best - finalized should be <= `threshold` blocks
+    FinalizedLag,
+    /// The para head (in the relaychain) should change at least every `threshold` checks
+    ParaInclusion,
+    /// Peers count should be >= `threshold`
+    Peers,
+    /// Lines matching `pattern` in the logs should be < `threshold` between checks
+    LogWatch,
+}
+
+impl CheckKind {
+    fn default_interval(&self) -> u64 {
+        match self {
+            CheckKind::BestProgress => 900,
+            CheckKind::LogWatch => 30,
+            _ => 60,
+        }
+    }
+
+    fn default_threshold(&self) -> u64 {
+        match self {
+            CheckKind::FinalizedLag => 20,
+            CheckKind::ParaInclusion => 5,
+            _ => 1,
+        }
+    }
+}
+
+/// Remediation when a check fails.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Restart,
+    /// Only log the failure
+    Alert,
+    /// Stop the network and generate the artifacts for the next step
+    Stop,
+}
+
+/// What to do when a node runs out of restarts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Exit with error (without generating the artifacts)
+    #[default]
+    Abort,
+    /// Stop the network and generate the artifacts
+    Teardown,
+}
+
+/// Health check config (`[[monitor.checks]]` in the manifest).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthCheckConfig {
+    pub kind: CheckKind,
+    /// Nodes to check [default: all (first validator for `para-inclusion`)]
+    pub nodes: Option<Vec<String>>,
+    /// Seconds between checks
+    pub interval: Option<u64>,
+    pub threshold: Option<u64>,
+    #[serde(default)]
+    pub action: Action,
+    /// Regex to watch (`log-watch` only)
+    pub pattern: Option<String>,
+    /// Para to watch (`para-inclusion` only) [default: 1000]
+    pub para_id: Option<u32>,
+}
+
+impl HealthCheckConfig {
+    /// Default check (if none is configured), restart the nodes that don't produce blocks.
+    pub fn best_progress() -> Self {
+        Self {
+            kind: CheckKind::BestProgress,
+            nodes: None,
+            interval: None,
+            threshold: None,
+            action: Action::Restart,
+            pattern: None,
+            para_id: None,
+        }
     }
+
+    pub fn validate(&self) -> Result<(), BiteError> {
+        if self.interval == Some(0) {
+            return Err(BiteError::Config(format!(
+                "{:?} check interval should be greater than 0",
+                self.kind
+            )));
+        }
+        if let Some(pattern) = &self.pattern {
+            Regex::new(pattern).map_err(|e| {
+                BiteError::Config(format!("invalid log-watch pattern '{pattern}': {e}"))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Interval of the check, `default_interval` is the one of the `[monitor]` section.
+    fn interval(&self, default_interval: Option<u64>) -> Duration {
+        Duration::from_secs(
+            self.interval
+                .or(default_interval)
+                .unwrap_or_else(|| self.kind.default_interval()),
+        )
+    }
+
+    fn threshold(&self) -> u64 {
+        self.threshold
+            .unwrap_or_else(|| self.kind.default_threshold())
+    }
+}
+
+/// Reason of the failed check, and the nodes to remediate.
+#[derive(Debug)]
+pub struct Unhealthy {
+    pub reason: String,
+    /// Nodes to remediate (empty means the checked node)
+    pub targets: Vec<String>,
+}
+
+impl From<anyhow::Error> for Unhealthy {
+    fn from(e: anyhow::Error) -> Self {
+        Self {
+            reason: e.to_string(),
+            targets: vec![],
+        }
+    }
+}
+
+fn unhealthy(reason: impl Into<String>) -> Unhealthy {
+    Unhealthy {
+        reason: reason.into(),
+        targets: vec![],
+    }
+}
+
+#[async_trait]
+pub trait HealthCheck: Send {
+    fn name(&self) -> &'static str;
+
+    async fn check(&mut self, node: &NetworkNode) -> Result<(), Unhealthy>;
 }
 
-async fn progress(node: &NetworkNode, checkpoint: impl Into<f64>) -> Result<f64, anyhow::Error> {
-    let metric = node.reports("block_height{status=\"best\"}").await?;
-    let checkpoint = checkpoint.into();
-    if metric > checkpoint {
-        debug!(
-            "{} is making progress, checkpoint {} - current {}",
-            node.name(),
-            checkpoint,
-            metric
+struct BestProgress {
+    checkpoint: f64,
+    threshold: u64,
+}
+
+#[async_trait]
+impl HealthCheck for BestProgress {
+    fn name(&self) -> &'static str {
+        "best-progress"
+    }
+
+    async fn check(&mut self, node: &NetworkNode) -> Result<(), Unhealthy> {
+        let best = node.reports("block_height{status=\"best\"}").await?;
+        if best >= self.checkpoint + self.threshold as f64 {
+            debug!(
+                "{} is making progress, checkpoint {} - current {best}",
+                node.name(),
+                self.checkpoint
+            );
+            self.checkpoint = best;
+            Ok(())
+        } else {
+            Err(unhealthy(format!(
+                "node don't progress, current {best} - checkpoint {}",
+                self.checkpoint
+            )))
+        }
+    }
+}
+
+struct FinalizedLag {
+    threshold: u64,
+}
+
+#[async_trait]
+impl HealthCheck for FinalizedLag {
+    fn name(&self) -> &'static str {
+        "finalized-lag"
+    }
+
+    async fn check(&mut self, node: &NetworkNode) -> Result<(), Unhealthy> {
+        let best = node.reports("block_height{status=\"best\"}").await?;
+        let finalized = node.reports("block_height{status=\"finalized\"}").await?;
+        let lag = best - finalized;
+        if lag > self.threshold as f64 {
+            return Err(unhealthy(format!(
+                "finalized #{finalized} is {lag} blocks behind best #{best}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct ParaInclusion {
+    para_id: u32,
+    threshold: u64,
+    last_head: Option<String>,
+    stalled_checks: u64,
+    /// Collators of the para (remediation targets)
+    collators: Vec<String>,
+}
+
+#[async_trait]
+impl HealthCheck for ParaInclusion {
+    fn name(&self) -> &'static str {
+        "para-inclusion"
+    }
+
+    async fn check(&mut self, node: &NetworkNode) -> Result<(), Unhealthy> {
+        let head = rpc_call(
+            node.ws_uri(),
+            "state_getStorage",
+            serde_json::json!([para_head_key(self.para_id)]),
+        )
+        .await?
+        .and_then(|head| head.as_str().map(str::to_string));
+
+        if head.is_some() && head != self.last_head {
+            self.last_head = head;
+            self.stalled_checks = 0;
+            return Ok(());
+        }
+
+        self.stalled_checks += 1;
+        trace!(
+            "para {} head not changed in {} checks",
+            self.para_id,
+            self.stalled_checks
         );
+        if self.stalled_checks >= self.threshold {
+            self.stalled_checks = 0;
+            return Err(Unhealthy {
+                reason: format!(
+                    "para {} head not included in the last {} checks",
+                    self.para_id, self.threshold
+                ),
+                targets: self.collators.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Peers {
+    threshold: u64,
+}
+
+#[async_trait]
+impl HealthCheck for Peers {
+    fn name(&self) -> &'static str {
+        "peers"
+    }
+
+    async fn check(&mut self, node: &NetworkNode) -> Result<(), Unhealthy> {
+        let peers = node.reports("substrate_sub_libp2p_peers_count").await?;
+        if peers < self.threshold as f64 {
+            return Err(unhealthy(format!(
+                "{peers} peers (expected at least {})",
+                self.threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct LogWatch {
+    pattern: Regex,
+    threshold: u64,
+    log: LogTail,
+}
+
+#[async_trait]
+impl HealthCheck for LogWatch {
+    fn name(&self) -> &'static str {
+        "log-watch"
+    }
 
-        Ok(metric)
+    async fn check(&mut self, _node: &NetworkNode) -> Result<(), Unhealthy> {
+        self.check_new_lines().await
+    }
+}
+
+impl LogWatch {
+    /// Only the lines written since the last check count for the threshold.
+    async fn check_new_lines(&mut self) -> Result<(), Unhealthy> {
+        let content = self.log.read_new().await?;
+        let matches: Vec<&str> = content
+            .lines()
+            .filter(|line| self.pattern.is_match(line))
+            .collect();
+        if matches.len() as u64 >= self.threshold {
+            return Err(unhealthy(format!(
+                "{} lines matching '{}' in the logs (last: {})",
+                matches.len(),
+                self.pattern,
+                matches.last().unwrap_or(&"").trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check of a node, with the schedule and remediation action.
+struct Scheduled {
+    node: String,
+    check: Box<dyn HealthCheck>,
+    interval: Duration,
+    action: Action,
+    next: Instant,
+}
+
+fn build_checks(
+    network: &Network<LocalFileSystem>,
+    configs: &[HealthCheckConfig],
+    settings: &MonitorManifest,
+) -> Result<Vec<Scheduled>, BiteError> {
+    let validators: Vec<String> = network
+        .relaychain()
+        .nodes()
+        .iter()
+        .map(|node| node.name().to_string())
+        .collect();
+    let all_nodes: Vec<String> = network
+        .nodes()
+        .iter()
+        .map(|node| node.name().to_string())
+        .collect();
+    let base_dir = Path::new(network.base_dir().unwrap_or_default());
+
+    let mut scheduled = vec![];
+    for config in configs {
+        config.validate()?;
+        let nodes = match (&config.nodes, config.kind) {
+            (Some(nodes), _) => nodes.clone(),
+            (None, CheckKind::ParaInclusion) => validators.iter().take(1).cloned().collect(),
+            (None, _) => all_nodes.clone(),
+        };
+        for name in nodes {
+            let node = network.get_node(&name).map_err(|_| {
+                BiteError::Config(format!("unknown node '{name}' in monitor checks"))
+            })?;
+            let threshold = config.threshold();
+            let check: Box<dyn HealthCheck> = match config.kind {
+                CheckKind::BestProgress => Box::new(BestProgress {
+                    checkpoint: 0_f64,
+                    threshold,
+                }),
+                CheckKind::FinalizedLag => Box::new(FinalizedLag { threshold }),
+                CheckKind::ParaInclusion => {
+                    let para_id = config.para_id.unwrap_or(1000);
+                    let collators = network
+                        .parachain(para_id)
+                        .map(|para| {
+                            para.collators()
+                                .iter()
+                                .map(|c| c.name().to_string())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    Box::new(ParaInclusion {
+                        para_id,
+                        threshold,
+                        last_head: None,
+                        stalled_checks: 0,
+                        collators,
+                    })
+                }
+                CheckKind::Peers => Box::new(Peers { threshold }),
+                CheckKind::LogWatch => {
+                    let pattern = config.pattern.as_deref().unwrap_or(DEFAULT_LOG_PATTERN);
+                    Box::new(LogWatch {
+                        pattern: Regex::new(pattern).map_err(|e| {
+                            BiteError::Config(format!("invalid log-watch pattern: {e}"))
+                        })?,
+                        threshold,
+                        log: LogTail::new(node_log_path(node, base_dir)),
+                    })
+                }
+            };
+            scheduled.push(Scheduled {
+                node: name,
+                check,
+                interval: config.interval(settings.interval),
+                action: config.action,
+                // first run after the grace period, to set the initial checkpoints
+                next: Instant::now() + Duration::from_secs(settings.grace_period()),
+            });
+        }
+    }
+
+    Ok(scheduled)
+}
+
+/// Restarts of a node, to apply the budget and backoff.
+#[derive(Debug, Default)]
+struct NodeRestarts {
+    /// Restarts in the current window
+    at: Vec<Instant>,
+    /// Consecutive restarts (without a passing check in between)
+    consecutive: u32,
+}
+
+#[derive(Debug)]
+struct RestartBudget {
+    max_restarts: u32,
+    window: Duration,
+    backoff: Duration,
+    max_backoff: Duration,
+    restarts: HashMap<String, NodeRestarts>,
+}
+
+impl RestartBudget {
+    fn new(settings: &MonitorManifest) -> Self {
+        Self {
+            max_restarts: settings.max_restarts(),
+            window: Duration::from_secs(settings.restart_window()),
+            backoff: Duration::from_secs(settings.backoff()),
+            max_backoff: Duration::from_secs(settings.max_backoff()),
+            restarts: HashMap::new(),
+        }
+    }
+
+    /// Register the restart of the node, returns the backoff to wait before checking it again
+    /// or the reason if the node is out of restarts.
+    fn register(&mut self, node: &str, now: Instant) -> Result<Duration, String> {
+        let restarts = self.restarts.entry(node.to_string()).or_default();
+        restarts
+            .at
+            .retain(|at| now.duration_since(*at) < self.window);
+        if restarts.at.len() as u32 >= self.max_restarts {
+            return Err(format!(
+                "{node} was restarted {} times in the last {}s",
+                restarts.at.len(),
+                self.window.as_secs()
+            ));
+        }
+        restarts.at.push(now);
+        restarts.consecutive += 1;
+
+        let factor = 2_u32.saturating_pow(restarts.consecutive - 1);
+        Ok(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+
+    fn healthy(&mut self, node: &str) {
+        if let Some(restarts) = self.restarts.get_mut(node) {
+            restarts.consecutive = 0;
+        }
+    }
+}
+
+/// Nodes paused with the control api, shared with the monitor so it doesn't check (and
+/// restart) them. The checks of all the nodes are suspended while a snapshot is generated.
+#[derive(Debug, Clone, Default)]
+pub struct PausedNodes(Arc<Mutex<PausedState>>);
+
+#[derive(Debug, Default)]
+struct PausedState {
+    nodes: HashSet<String>,
+    snapshot: bool,
+}
+
+impl PausedNodes {
+    pub fn pause(&self, node: &str) {
+        self.lock().nodes.insert(node.to_string());
+    }
+
+    pub fn resume(&self, node: &str) {
+        self.lock().nodes.remove(node);
+    }
+
+    /// Suspend (or re-enable) the checks of all the nodes.
+    pub fn set_snapshot(&self, in_progress: bool) {
+        self.lock().snapshot = in_progress;
+    }
+
+    /// Paused with the control api (ignoring the snapshots).
+    pub fn is_paused(&self, node: &str) -> bool {
+        self.lock().nodes.contains(node)
+    }
+
+    /// The node should not be checked.
+    pub fn is_skipped(&self, node: &str) -> bool {
+        let state = self.lock();
+        state.snapshot || state.nodes.contains(node)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PausedState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+async fn restart(node: &NetworkNode, reason: &str, events: &EventLog) {
+    let node_name = node.name().to_string();
+    if let Err(e) = node.restart(None).await {
+        warn!("Error restarting {node_name}: {e}");
+        events
+            .append(Event::Failure {
+                node: Some(node_name),
+                reason: format!("error restarting: {e}"),
+            })
+            .await;
     } else {
-        Err(anyhow::anyhow!(
-            "node don't progress, current {metric} - checkpoint {checkpoint}"
-        ))
+        warn!("{node_name} was restarted ({reason})");
+        metrics::inc(metrics::MONITOR_RESTARTS, &[("node", &node_name)], 1_f64);
+        events
+            .append(Event::Restart {
+                node: node_name,
+                reason: reason.to_string(),
+            })
+            .await;
     }
 }
 
+/// Monit the network with the configured health checks (or the default `best-progress` one)
+/// until the stop file is created, a check with `stop` action fails or a node runs out of
+/// restarts (returning an error if `on_budget_exhausted` is `abort`).
+/// The collators are restarted along with the first validator (alice), since they use it as rpc node.
+/// The `paused` nodes are not checked nor restarted.
 pub async fn monit_progress(
-    alice: &NetworkNode,
-    bob: &NetworkNode,
-    collator: Option<&NetworkNode>,
+    network: &Network<LocalFileSystem>,
+    settings: &MonitorManifest,
     stop_file: Option<&str>,
-) {
-    // monitoring block production every 15 mins
-    let mut alice_block = progress(alice, 0).await.expect("first check should works");
-    let mut bob_block = progress(bob, 0).await.expect("first check should works");
-
-    let mut collator_block = if let Some(collator) = collator {
-        progress(collator, 0)
-        .await
-        .expect("first check should works")
+    events: &EventLog,
+    paused: &PausedNodes,
+) -> Result<(), BiteError> {
+    let default_checks = [HealthCheckConfig::best_progress()];
+    let configs = if settings.checks.is_empty() {
+        &default_checks[..]
     } else {
-        // no collator deployed.
-        -1_f64
+        &settings.checks[..]
     };
+    let mut scheduled = build_checks(network, configs, settings)?;
+    let mut budget = RestartBudget::new(settings);
+    let first_validator = network
+        .relaychain()
+        .nodes()
+        .first()
+        .map(|node| node.name().to_string());
+    let collators: Vec<&NetworkNode> = network
+        .parachains()
+        .into_iter()
+        .flat_map(|para| para.collators())
+        .collect();
 
-    let mut check_progress = async || {
-        // check the progress
-        // alice
-        let mut alice_was_restarted = false;
-        if let Ok(block) = progress(alice, alice_block).await {
-            alice_block = block;
-        } else {
-            // restart alice / collator
-            restart(alice, alice_block).await;
-            if let Some(collator) = collator {
-                restart(collator, collator_block).await;
+    if settings.grace_period() > 0 {
+        info!(
+            "monitor will start checking in {}s (grace period)",
+            settings.grace_period()
+        );
+    }
+
+    let mut next_stop_file_check = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_stop_file_check {
+            events.sample(network).await;
+            if let Some(stop_file) = stop_file {
+                if let Ok(true) = fs::try_exists(stop_file).await {
+                    return Ok(());
+                }
             }
-            alice_was_restarted = true;
+            next_stop_file_check = now + Duration::from_secs(STOP_FILE_INTERVAL_SECS);
         }
 
-        // bob
-        if let Ok(block) = progress(bob, bob_block).await {
-            bob_block = block;
-        } else {
-            // restart alice / collator
-            restart(bob, bob_block).await;
-        }
+        // restarted nodes, with the backoff to wait before checking them again
+        let mut restarted: Vec<(String, Duration)> = vec![];
+        for entry in scheduled.iter_mut().filter(|entry| entry.next <= now) {
+            entry.next = now + entry.interval;
+            if paused.is_skipped(&entry.node) {
+                trace!(
+                    "{} is paused, skipping {} check",
+                    entry.node,
+                    entry.check.name()
+                );
+                continue;
+            }
+            let Ok(node) = network.get_node(&entry.node) else {
+                continue;
+            };
+            let check_name = entry.check.name();
+            let result = entry.check.check(node).await;
+            events
+                .append(Event::Check {
+                    node: entry.node.clone(),
+                    check: check_name.to_string(),
+                    healthy: result.is_ok(),
+                    reason: result.as_ref().err().map(|u| u.reason.clone()),
+                })
+                .await;
+            let Err(unhealthy) = result else {
+                if entry.action == Action::Restart {
+                    budget.healthy(&entry.node);
+                }
+                continue;
+            };
 
-        if !alice_was_restarted {
-            if let Some(collator) = collator {
-                if let Ok(block) = progress(collator, collator_block).await {
-                    collator_block = block;
-                } else {
-                    // restart alice / collator
-                    restart(collator, collator_block).await;
+            let mut targets = if unhealthy.targets.is_empty() {
+                vec![entry.node.clone()]
+            } else {
+                unhealthy.targets
+            };
+            let reason = format!("{check_name}: {}", unhealthy.reason);
+            match entry.action {
+                Action::Alert => warn!("🚨 {} unhealthy, {reason}", entry.node),
+                Action::Stop => {
+                    error!(
+                        "🚨 {} unhealthy, {reason}. Stopping the network",
+                        entry.node
+                    );
+                    events
+                        .append(Event::Failure {
+                            node: Some(entry.node.clone()),
+                            reason,
+                        })
+                        .await;
+                    return Ok(());
+                }
+                Action::Restart => {
+                    if targets.iter().any(|t| Some(t) == first_validator.as_ref()) {
+                        // restart collators
+                        targets.extend(collators.iter().map(|c| c.name().to_string()));
+                    }
+                    for target in targets {
+                        if restarted.iter().any(|(node, _)| *node == target)
+                            || paused.is_skipped(&target)
+                        {
+                            continue;
+                        }
+                        let Ok(target_node) = network.get_node(&target) else {
+                            continue;
+                        };
+                        match budget.register(&target, now) {
+                            Ok(backoff) => {
+                                restart(target_node, &reason, events).await;
+                                restarted.push((target, backoff));
+                            }
+                            Err(exhausted) => {
+                                let reason = format!("restart budget exhausted, {exhausted}");
+                                events
+                                    .append(Event::Failure {
+                                        node: Some(target),
+                                        reason: reason.clone(),
+                                    })
+                                    .await;
+                                if settings.on_budget_exhausted() == BudgetAction::Abort {
+                                    return Err(BiteError::Monitor(reason));
+                                }
+                                error!("🚨 {reason}. Stopping the network");
+                                return Ok(());
+                            }
+                        }
+                    }
                 }
             }
         }
-    };
 
-    if let Some(stop_file) = stop_file {
-        let mut counter = 0;
-        while let Ok(false) = fs::try_exists(&stop_file).await {
-            trace!("monit counter: {counter}");
-            tokio::time::sleep(Duration::from_secs(60)).await;
-            if counter >= 15 {
-                check_progress().await;
-                counter = 0;
-            } else {
-                counter += 1;
+        // give time to the restarted nodes before checking them again
+        for (node, backoff) in restarted {
+            debug!("{node} restarted, next checks in {}s", backoff.as_secs());
+            for entry in scheduled.iter_mut().filter(|entry| entry.node == node) {
+                entry.next = entry.next.max(now + backoff);
             }
         }
-    } else {
-        loop {
-            tokio::time::sleep(Duration::from_secs(CHECK_TIMEOUT_SECS)).await;
-            check_progress().await
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checks_config_should_parse() {
+        #[derive(Deserialize)]
+        struct T {
+            checks: Vec<HealthCheckConfig>,
         }
+        let t: T = toml::from_str(
+            r#"
+            [[checks]]
+            kind = "finalized-lag"
+            nodes = ["alice"]
+            threshold = 10
+            action = "alert"
+
+            [[checks]]
+            kind = "log-watch"
+            pattern = "panicked"
+            action = "stop"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(t.checks[0].kind, CheckKind::FinalizedLag);
+        assert_eq!(t.checks[0].threshold(), 10);
+        assert_eq!(t.checks[0].interval(None), Duration::from_secs(60));
+        assert_eq!(t.checks[0].interval(Some(10)), Duration::from_secs(10));
+        assert_eq!(t.checks[1].action, Action::Stop);
+        assert_eq!(t.checks[1].threshold(), 1);
+        assert!(t.checks.iter().all(|c| c.validate().is_ok()));
+
+        let mut invalid = HealthCheckConfig::best_progress();
+        invalid.pattern = Some(String::from("(unclosed"));
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn restart_budget_should_backoff_and_run_out() {
+        let settings = MonitorManifest {
+            max_restarts: Some(3),
+            restart_window: Some(3600),
+            backoff: Some(60),
+            max_backoff: Some(100),
+            ..Default::default()
+        };
+        let mut budget = RestartBudget::new(&settings);
+        let now = Instant::now();
+        assert_eq!(budget.register("alice", now), Ok(Duration::from_secs(60)));
+        assert_eq!(budget.register("alice", now), Ok(Duration::from_secs(100)));
+        // a passing check reset the backoff
+        budget.healthy("alice");
+        assert_eq!(budget.register("alice", now), Ok(Duration::from_secs(60)));
+        assert!(budget.register("alice", now).is_err());
+        // the budget is per node
+        assert!(budget.register("bob", now).is_ok());
+        // and per window
+        assert!(budget
+            .register("alice", now + Duration::from_secs(3600))
+            .is_ok());
+    }
+
+    #[test]
+    fn paused_nodes_should_be_skipped() {
+        let paused = PausedNodes::default();
+        paused.pause("alice");
+        let shared = paused.clone();
+        assert!(shared.is_skipped("alice"));
+        assert!(!shared.is_skipped("bob"));
+
+        paused.set_snapshot(true);
+        assert!(shared.is_skipped("bob"));
+        paused.set_snapshot(false);
+        paused.resume("alice");
+        assert!(!shared.is_skipped("alice"));
+    }
+
+    #[tokio::test]
+    async fn log_watch_should_only_read_new_lines() {
+        let log_path =
+            std::env::temp_dir().join(format!("zombie-bite-log-watch-{}.log", std::process::id()));
+        fs::write(&log_path, "thread 'main' panicked\nok\n")
+            .await
+            .unwrap();
+        let mut watch = LogWatch {
+            pattern: Regex::new(DEFAULT_LOG_PATTERN).unwrap(),
+            threshold: 2,
+            log: LogTail::new(&log_path),
+        };
+        // below the threshold
+        assert!(watch.check_new_lines().await.is_ok());
+
+        let append = |lines: &'static str| {
+            let log_path = log_path.clone();
+            async move {
+                let mut file = fs::OpenOptions::new()
+                    .append(true)
+                    .open(&log_path)
+                    .await
+                    .unwrap();
+                tokio::io::AsyncWriteExt::write_all(&mut file, lines.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        };
+        // the match already checked doesn't count again
+        append("Imported #10\nthread 'tokio' panicked\n").await;
+        assert!(watch.check_new_lines().await.is_ok());
+
+        append("thread 'main' panicked\nthread 'main' panicked\n").await;
+        assert!(watch.check_new_lines().await.is_err());
+        // nothing new
+        assert!(watch.check_new_lines().await.is_ok());
+        fs::remove_file(&log_path).await.unwrap();
     }
 }
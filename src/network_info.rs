@@ -0,0 +1,233 @@
+//! Machine readable info (`network.json`) of a running network, to allow scripts/tools to find
+//! the nodes without parsing the logs.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+use tracing::{debug, info};
+use zombienet_sdk::{LocalFileSystem, Network, NetworkNode};
+
+use crate::control::{self, Request, Response};
+use crate::doppelganger::READY_FILE;
+use crate::error::BiteError;
+
+/// File (in the step dir) with the info of the running network.
+pub const NETWORK_INFO_FILE: &str = "network.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    Validator,
+    Collator,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeInfo {
+    pub name: String,
+    pub role: NodeRole,
+    pub chain: String,
+    /// Parachain id (None for the relaychain nodes)
+    pub para_id: Option<u32>,
+    pub rpc_port: Option<u16>,
+    pub ws_uri: String,
+    pub prometheus_port: Option<u16>,
+    pub prometheus_uri: String,
+    pub p2p_port: Option<u16>,
+    pub multiaddr: String,
+    pub log_path: PathBuf,
+    pub pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkInfo {
+    pub base_dir: String,
+    pub relaychain: String,
+    pub nodes: Vec<NodeInfo>,
+    /// Start blocks of the chains (from `ready.json`)
+    pub start_blocks: Option<Value>,
+}
+
+impl NetworkInfo {
+    pub async fn from_network(
+        network: &Network<LocalFileSystem>,
+        base_path: &Path,
+    ) -> Result<Self, BiteError> {
+        let base_dir = network
+            .base_dir()
+            .map(PathBuf::from)
+            .ok_or_else(|| BiteError::Spawn(String::from("network without base dir")))?;
+        let relaychain = network.relaychain().chain().to_string();
+
+        let mut nodes: Vec<NodeInfo> = network
+            .relaychain()
+            .nodes()
+            .into_iter()
+            .map(|node| node_info(node, NodeRole::Validator, &relaychain, None, &base_dir))
+            .collect();
+        for para in network.parachains() {
+            let chain = para.chain_id().unwrap_or(para.unique_id()).to_string();
+            for collator in para.collators() {
+                nodes.push(node_info(
+                    collator,
+                    NodeRole::Collator,
+                    &chain,
+                    Some(para.para_id()),
+                    &base_dir,
+                ));
+            }
+        }
+
+        let start_blocks = fs::read_to_string(base_path.join(READY_FILE))
+            .await
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+
+        Ok(Self {
+            base_dir: base_dir.to_string_lossy().to_string(),
+            relaychain,
+            nodes,
+            start_blocks,
+        })
+    }
+
+    /// Write the info to `network.json` in the step dir.
+    pub async fn write(&self, step_dir: &Path) -> Result<(), BiteError> {
+        let path = step_dir.join(NETWORK_INFO_FILE);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| BiteError::Spawn(format!("Error serializing network info: {e}")))?;
+        fs::write(&path, content)
+            .await
+            .map_err(|e| BiteError::io("writing", &path, e))?;
+        info!("📝 network info written to {}", path.display());
+        Ok(())
+    }
+
+    pub async fn load(step_dir: &Path) -> Result<Self, BiteError> {
+        let path = step_dir.join(NETWORK_INFO_FILE);
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|e| BiteError::io("reading network info", &path, e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| BiteError::Config(format!("invalid network info {}: {e}", path.display())))
+    }
+
+    /// Info of the network running in the step dir, asked to the control socket (so the pids
+    /// are the ones after the restarts), or the one written at spawn if the socket is not available.
+    pub async fn current(base_path: &Path, step_dir: &Path) -> Result<Self, BiteError> {
+        if let Ok(Response {
+            ok: true,
+            result: Some(result),
+            ..
+        }) = control::send(&control::socket_path(base_path), &Request::Status).await
+        {
+            match serde_json::from_value::<Self>(result) {
+                // the socket is shared by all the steps, only use it for the running one
+                Ok(info) if Path::new(&info.base_dir).file_name() == step_dir.file_name() => {
+                    return Ok(info)
+                }
+                Ok(_) => {}
+                Err(e) => debug!("invalid network info from the control socket: {e}"),
+            }
+        }
+        Self::load(step_dir).await
+    }
+}
+
+fn node_info(
+    node: &NetworkNode,
+    role: NodeRole,
+    chain: &str,
+    para_id: Option<u32>,
+    base_dir: &Path,
+) -> NodeInfo {
+    // some fields are only exposed through the serialized node
+    let serialized = serde_json::to_value(node).unwrap_or_default();
+    let prometheus_uri = serialized["prometheus_uri"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let node_dir = base_dir.join(node.name());
+
+    NodeInfo {
+        name: node.name().to_string(),
+        role,
+        chain: chain.to_string(),
+        para_id,
+        rpc_port: port_from_uri(node.ws_uri()),
+        ws_uri: node.ws_uri().to_string(),
+        prometheus_port: port_from_uri(&prometheus_uri),
+        prometheus_uri,
+        p2p_port: port_from_multiaddr(node.multiaddr()),
+        multiaddr: node.multiaddr().to_string(),
+        log_path: node_log_path(node, base_dir),
+        pid: find_pid(&node_dir.join("data")),
+    }
+}
+
+/// Log file of the node (`<base_dir>/<name>/<name>.log` for native nodes).
+pub fn node_log_path(node: &NetworkNode, base_dir: &Path) -> PathBuf {
+    let serialized = serde_json::to_value(node).unwrap_or_default();
+    serialized["spec"]["node_log_path"]
+        .as_str()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            base_dir
+                .join(node.name())
+                .join(format!("{}.log", node.name()))
+        })
+}
+
+fn port_from_uri(uri: &str) -> Option<u16> {
+    reqwest::Url::parse(uri).ok()?.port()
+}
+
+// e.g. /ip4/127.0.0.1/tcp/56246/ws/p2p/12D3KooW...
+fn port_from_multiaddr(multiaddr: &str) -> Option<u16> {
+    let mut parts = multiaddr.split('/');
+    parts.find(|part| *part == "tcp")?;
+    parts.next()?.parse().ok()
+}
+
+/// Pid of the node process, looking for the `--base-path` in the cmdline of the running
+/// processes (only supported in linux).
+fn find_pid(node_data_dir: &Path) -> Option<u32> {
+    let needle = node_data_dir.to_string_lossy().to_string();
+    let entries = std::fs::read_dir("/proc").ok()?;
+    for entry in entries.flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        let Ok(cmdline) = std::fs::read(entry.path().join("cmdline")) else {
+            continue;
+        };
+        let mut args = cmdline
+            .split(|b| *b == 0)
+            .map(|arg| String::from_utf8_lossy(arg).to_string());
+        if args.any(|arg| arg == needle) {
+            debug!("pid {pid} found for {needle}");
+            return Some(pid);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ports_should_be_parsed() {
+        assert_eq!(port_from_uri("ws://127.0.0.1:60568"), Some(60568));
+        assert_eq!(port_from_uri("http://127.0.0.1:56245/metrics"), Some(56245));
+        assert_eq!(
+            port_from_multiaddr(
+                "/ip4/127.0.0.1/tcp/56246/ws/p2p/12D3KooWQCkBm1BYtkHpocxCwMgR8yjitEeHGx8spzcDLGt2gkBm"
+            ),
+            Some(56246)
+        );
+        assert_eq!(port_from_multiaddr("/ip4/127.0.0.1/udp/1"), None);
+    }
+}
@@ -0,0 +1,444 @@
+//! Human-readable override specs (pallet / item / keys / value), resolved to the raw hex
+//! key/value pairs used in the `overrides` and `injects` maps.
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::Value;
+use sp_core::{sr25519, Pair};
+use substorager::StorageHasher;
+use zombienet_sdk::subxt::{
+    ext::{
+        scale_encode::EncodeAsType,
+        scale_value::{self, Value as ScaleValue},
+    },
+    metadata::types::{StorageEntryType, StorageHasher as MetadataHasher},
+    Metadata,
+};
+
+/// A single storage entry to override/inject, e.g:
+///
+/// ```toml
+/// [[specs]]
+/// pallet = "Sudo"
+/// item = "Key"
+/// value = "//Alice"
+///
+/// [[specs]]
+/// pallet = "Paras"
+/// item = "MostRecentContext"
+/// key = { para_id = 1000 }
+/// value = 1
+/// inject = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct OverrideSpec {
+    pub pallet: String,
+    pub item: String,
+    /// Key for single key maps (shortcut of `keys = [..]`).
+    pub key: Option<Value>,
+    /// Keys for maps (one per hasher).
+    #[serde(default)]
+    pub keys: Vec<Value>,
+    /// Value to SCALE encode using the type from the runtime metadata.
+    pub value: Option<Value>,
+    /// Already encoded (hex) value, used as is.
+    pub raw_value: Option<String>,
+    /// Add the key to the `injects` map instead of the `overrides` one.
+    #[serde(default)]
+    pub inject: bool,
+}
+
+impl OverrideSpec {
+    fn keys(&self) -> Vec<&Value> {
+        self.key.iter().chain(self.keys.iter()).collect()
+    }
+
+    /// Returns true if the spec can be resolved without the runtime metadata
+    /// (storage value with an already encoded value).
+    pub fn needs_metadata(&self) -> bool {
+        !(self.keys().is_empty() && self.raw_value.is_some())
+    }
+
+    /// Resolve the spec into the (key, value) pair, both hex encoded without the `0x` prefix.
+    pub fn resolve(
+        &self,
+        maybe_metadata: Option<&Metadata>,
+    ) -> Result<(String, String), anyhow::Error> {
+        let keys = self.keys();
+        if !self.needs_metadata() {
+            let key = substorager::storage_value_key(&self.pallet, &self.item);
+            return Ok((
+                array_bytes::bytes2hex("", key),
+                normalize_hex(self.raw_value.as_deref().unwrap_or_default()),
+            ));
+        }
+
+        let metadata = maybe_metadata
+            .ok_or_else(|| anyhow!("runtime metadata is needed to resolve {}", self.id()))?;
+        let pallet = metadata
+            .pallet_by_name(&self.pallet)
+            .ok_or_else(|| anyhow!("pallet {} not found in metadata", self.pallet))?;
+        let storage = pallet
+            .storage()
+            .ok_or_else(|| anyhow!("pallet {} doesn't have storage", self.pallet))?;
+        let entry = storage
+            .entry_by_name(&self.item)
+            .ok_or_else(|| anyhow!("storage item {} not found", self.id()))?;
+
+        let types = metadata.types();
+        let hashed_keys = match entry.entry_type() {
+            StorageEntryType::Plain(_) => {
+                if !keys.is_empty() {
+                    return Err(anyhow!(
+                        "{} is a storage value, keys not allowed",
+                        self.id()
+                    ));
+                }
+                vec![]
+            }
+            StorageEntryType::Map {
+                hashers, key_ty, ..
+            } => {
+                if keys.len() != hashers.len() {
+                    return Err(anyhow!(
+                        "{} expects {} key(s), got {}",
+                        self.id(),
+                        hashers.len(),
+                        keys.len()
+                    ));
+                }
+
+                let key_types: Vec<u32> = if hashers.len() == 1 {
+                    vec![*key_ty]
+                } else {
+                    match &types
+                        .resolve(*key_ty)
+                        .ok_or_else(|| anyhow!("key type {key_ty} not found"))?
+                        .type_def
+                    {
+                        scale_info::TypeDef::Tuple(tuple) => {
+                            tuple.fields.iter().map(|f| f.id).collect()
+                        }
+                        _ => return Err(anyhow!("key type of {} should be a tuple", self.id())),
+                    }
+                };
+
+                keys.iter()
+                    .zip(key_types)
+                    .zip(hashers)
+                    .map(|((key, key_ty), hasher)| {
+                        let encoded =
+                            to_scale_value(key)?
+                                .encode_as_type(key_ty, types)
+                                .map_err(|e| {
+                                    anyhow!("error encoding key {key} of {}: {e}", self.id())
+                                })?;
+                        Ok((to_storage_hasher(hasher), encoded))
+                    })
+                    .collect::<Result<Vec<_>, anyhow::Error>>()?
+            }
+        };
+
+        let key = substorager::storage_n_map_key(storage.prefix(), &self.item, hashed_keys);
+
+        let value = match (&self.raw_value, &self.value) {
+            (Some(raw), _) => normalize_hex(raw),
+            (None, Some(value)) => {
+                let encoded = to_scale_value(value)?
+                    .encode_as_type(entry.entry_type().value_ty(), types)
+                    .map_err(|e| anyhow!("error encoding value {value} of {}: {e}", self.id()))?;
+                hex::encode(encoded)
+            }
+            (None, None) => return Err(anyhow!("{} needs a value or raw_value", self.id())),
+        };
+
+        Ok((array_bytes::bytes2hex("", key), value))
+    }
+
+    fn id(&self) -> String {
+        format!("{}::{}", self.pallet, self.item)
+    }
+}
+
+fn normalize_hex(value: &str) -> String {
+    value.trim_start_matches("0x").to_string()
+}
+
+fn to_storage_hasher(hasher: &MetadataHasher) -> StorageHasher {
+    match hasher {
+        MetadataHasher::Blake2_128 => StorageHasher::Blake2_128,
+        MetadataHasher::Blake2_256 => StorageHasher::Blake2_256,
+        MetadataHasher::Blake2_128Concat => StorageHasher::Blake2_128Concat,
+        MetadataHasher::Twox128 => StorageHasher::Twox128,
+        MetadataHasher::Twox256 => StorageHasher::Twox256,
+        MetadataHasher::Twox64Concat => StorageHasher::Twox64Concat,
+        MetadataHasher::Identity => StorageHasher::Identity,
+    }
+}
+
+/// Convert a json/toml value into a [`ScaleValue`].
+///
+/// Strings are handled as:
+/// - `//Seed`: the sr25519 public key (account) of the dev seed.
+/// - `0x..`: bytes.
+/// - any other string is parsed using the `scale-value` syntax (e.g. `ForceNone()`, `(1, 2)`)
+///   and fallback to a plain string.
+pub fn to_scale_value(value: &Value) -> Result<ScaleValue, anyhow::Error> {
+    let scale_value = match value {
+        Value::Null => ScaleValue::unnamed_composite(vec![]),
+        Value::Bool(b) => ScaleValue::bool(*b),
+        Value::Number(n) => {
+            if let Some(n) = n.as_u64() {
+                ScaleValue::u128(n as u128)
+            } else if let Some(n) = n.as_i64() {
+                ScaleValue::i128(n as i128)
+            } else {
+                return Err(anyhow!("invalid number {n}, only integers are supported"));
+            }
+        }
+        Value::String(s) if s.starts_with("//") => {
+            let pair = sr25519::Pair::from_string(s, None)
+                .map_err(|e| anyhow!("invalid seed {s}: {e:?}"))?;
+            ScaleValue::from_bytes(pair.public())
+        }
+        Value::String(s) if s.starts_with("0x") => ScaleValue::from_bytes(
+            hex::decode(&s[2..]).map_err(|e| anyhow!("invalid hex {s}: {e}"))?,
+        ),
+        Value::String(s) => match scale_value::stringify::from_str(s) {
+            (Ok(value), rest) if rest.trim().is_empty() => value,
+            _ => ScaleValue::string(s.as_str()),
+        },
+        Value::Array(values) => ScaleValue::unnamed_composite(
+            values
+                .iter()
+                .map(to_scale_value)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Value::Object(map) => ScaleValue::named_composite(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), to_scale_value(v)?)))
+                .collect::<Result<Vec<_>, anyhow::Error>>()?,
+        ),
+    };
+
+    Ok(scale_value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn storage_value_with_raw_value_should_work() {
+        let spec: OverrideSpec = toml::from_str(
+            r#"
+pallet = "Sudo"
+item = "Key"
+raw_value = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+"#,
+        )
+        .unwrap();
+
+        assert!(!spec.needs_metadata());
+        let (key, value) = spec.resolve(None).unwrap();
+        assert_eq!(
+            key,
+            "5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b"
+        );
+        assert_eq!(
+            value,
+            "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+        );
+    }
+
+    #[test]
+    fn map_without_metadata_should_fail() {
+        let spec: OverrideSpec = toml::from_str(
+            r#"
+pallet = "Paras"
+item = "Heads"
+key = { para_id = 1000 }
+raw_value = "0x00"
+"#,
+        )
+        .unwrap();
+
+        assert!(spec.needs_metadata());
+        assert!(spec.resolve(None).is_err());
+    }
+
+    /// Para id as in the runtime (`Id(u32)`).
+    #[derive(scale_info::TypeInfo)]
+    #[allow(dead_code)]
+    struct Id(u32);
+
+    /// Metadata with `Sudo::Key` (account), `Paras::Heads` (para_id => bytes) and
+    /// `Paras::MostRecentContext` (Id => block number).
+    fn test_metadata() -> Metadata {
+        use codec::{Decode, Encode};
+        use frame_metadata::{v15::*, RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+        use scale_info::meta_type;
+
+        let pallet = |name, index, entries| PalletMetadata {
+            name,
+            storage: Some(PalletStorageMetadata {
+                prefix: name,
+                entries,
+            }),
+            calls: None,
+            event: None,
+            constants: vec![],
+            error: None,
+            index,
+            docs: vec![],
+        };
+        let entry = |name, ty| StorageEntryMetadata {
+            name,
+            modifier: StorageEntryModifier::Optional,
+            ty,
+            default: vec![0],
+            docs: vec![],
+        };
+        let pallets = vec![
+            pallet(
+                "Sudo",
+                0,
+                vec![entry(
+                    "Key",
+                    StorageEntryType::Plain(meta_type::<[u8; 32]>()),
+                )],
+            ),
+            pallet(
+                "Paras",
+                1,
+                vec![
+                    entry(
+                        "Heads",
+                        StorageEntryType::Map {
+                            hashers: vec![StorageHasher::Twox64Concat],
+                            key: meta_type::<u32>(),
+                            value: meta_type::<Vec<u8>>(),
+                        },
+                    ),
+                    entry(
+                        "MostRecentContext",
+                        StorageEntryType::Map {
+                            hashers: vec![StorageHasher::Twox64Concat],
+                            key: meta_type::<Id>(),
+                            value: meta_type::<u32>(),
+                        },
+                    ),
+                ],
+            ),
+        ];
+        let extrinsic = ExtrinsicMetadata {
+            version: 4,
+            address_ty: meta_type::<()>(),
+            call_ty: meta_type::<()>(),
+            signature_ty: meta_type::<()>(),
+            extra_ty: meta_type::<()>(),
+            signed_extensions: vec![],
+        };
+        let outer_enums = OuterEnums {
+            call_enum_ty: meta_type::<()>(),
+            event_enum_ty: meta_type::<()>(),
+            error_enum_ty: meta_type::<()>(),
+        };
+        let metadata = RuntimeMetadataV15::new(
+            pallets,
+            extrinsic,
+            meta_type::<()>(),
+            vec![],
+            outer_enums,
+            CustomMetadata {
+                map: Default::default(),
+            },
+        );
+        let encoded =
+            RuntimeMetadataPrefixed(META_RESERVED, RuntimeMetadata::V15(metadata)).encode();
+        Metadata::decode(&mut &encoded[..]).unwrap()
+    }
+
+    #[test]
+    fn specs_should_resolve_with_metadata() {
+        let metadata = test_metadata();
+        let spec: OverrideSpec = toml::from_str(
+            r#"
+pallet = "Sudo"
+item = "Key"
+value = "//Alice"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            spec.resolve(Some(&metadata)).unwrap(),
+            (
+                String::from("5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b"),
+                String::from("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+            )
+        );
+
+        let spec: OverrideSpec = toml::from_str(
+            r#"
+pallet = "Paras"
+item = "Heads"
+key = 1000
+value = "0x0102"
+"#,
+        )
+        .unwrap();
+        let (key, value) = spec.resolve(Some(&metadata)).unwrap();
+        assert_eq!(
+            key,
+            crate::utils::para_head_key(1000).trim_start_matches("0x")
+        );
+        assert_eq!(value, "080102");
+
+        // composite key (as in the module docs)
+        let spec: OverrideSpec = toml::from_str(
+            r#"
+pallet = "Paras"
+item = "MostRecentContext"
+key = { para_id = 1000 }
+value = 1
+inject = true
+"#,
+        )
+        .unwrap();
+        let (key, value) = spec.resolve(Some(&metadata)).unwrap();
+        let context_key = substorager::storage_n_map_key(
+            "Paras",
+            "MostRecentContext",
+            vec![(StorageHasher::Twox64Concat, 1000_u32.to_le_bytes().to_vec())],
+        );
+        assert_eq!(key, array_bytes::bytes2hex("", context_key));
+        assert_eq!(value, "01000000");
+
+        // items / keys not in the metadata
+        let spec: OverrideSpec =
+            toml::from_str("pallet = \"Paras\"\nitem = \"Parachains\"\nvalue = 1").unwrap();
+        assert!(spec.resolve(Some(&metadata)).is_err());
+        let spec: OverrideSpec =
+            toml::from_str("pallet = \"Paras\"\nitem = \"Heads\"\nvalue = \"0x00\"").unwrap();
+        assert!(spec.resolve(Some(&metadata)).is_err());
+    }
+
+    #[test]
+    fn dev_seed_to_scale_value_should_work() {
+        let value = to_scale_value(&json!("//Alice")).unwrap();
+        assert_eq!(
+            value,
+            ScaleValue::from_bytes(
+                hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn variant_to_scale_value_should_work() {
+        let value = to_scale_value(&json!("ForceNone()")).unwrap();
+        assert_eq!(value, ScaleValue::unnamed_variant("ForceNone", vec![]));
+    }
+}
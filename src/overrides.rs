@@ -1,100 +1,434 @@
 use crate::config::{Parachain, Relaychain};
-use crate::utils::ValidationCode;
+use crate::error::BiteError;
+use crate::keys::{encode_vec, NodeKeys};
+use crate::manifest::get_manifest;
+use crate::override_spec::OverrideSpec;
+use crate::utils::{fetch_metadata, get_block_hash, para_id_hash, ValidationCode};
+use anyhow::anyhow;
 use codec::Encode;
-use serde_json::{json, Value};
-use std::{env, path::PathBuf};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
 use tokio::fs;
+use tracing::info;
+
+const DEFAULT_ORIGIN: &str = "default";
+
+/// Paths of the user supplied overrides files, merged over the default maps.
+#[derive(Debug, Default, Clone)]
+pub struct CustomOverridesPaths {
+    pub rc: Option<PathBuf>,
+    pub paras: HashMap<u32, PathBuf>,
+}
+
+/// Content of a user supplied overrides file (json or toml).
+///
+/// `overrides` and `injects` are deep-merged over the defaults, a `null` value (json only)
+/// or an entry in the `remove` section deletes the key from the resulting map.
+/// `specs` are human-readable entries resolved using the runtime metadata of the live chain.
+#[derive(Debug, Default, Deserialize)]
+pub struct CustomOverrides {
+    #[serde(default)]
+    pub overrides: Map<String, Value>,
+    #[serde(default)]
+    pub injects: Map<String, Value>,
+    #[serde(default)]
+    pub remove: RemoveKeys,
+    #[serde(default)]
+    pub specs: Vec<OverrideSpec>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RemoveKeys {
+    #[serde(default)]
+    pub overrides: Vec<String>,
+    #[serde(default)]
+    pub injects: Vec<String>,
+}
+
+impl CustomOverrides {
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .await
+            .map_err(|e| anyhow!("Error reading overrides file {}: {e}", path.display()))?;
+
+        let custom = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content)
+                .map_err(|e| anyhow!("Error parsing overrides file {}: {e}", path.display()))?
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Error parsing overrides file {}: {e}", path.display()))?
+        };
+
+        Ok(custom)
+    }
+}
+
+/// Storage keys map (`overrides` or `injects`) keeping track of the origin of each key.
+struct KeysMap {
+    name: &'static str,
+    values: Map<String, Value>,
+    origins: BTreeMap<String, String>,
+}
+
+impl KeysMap {
+    fn with_defaults(name: &'static str, defaults: Value) -> Self {
+        let values = match defaults {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        let origins = values
+            .keys()
+            .map(|k| (k.clone(), DEFAULT_ORIGIN.to_string()))
+            .collect();
+
+        Self {
+            name,
+            values,
+            origins,
+        }
+    }
+
+    fn set(&mut self, key: impl AsRef<str>, value: Value, origin: impl Into<String>) {
+        let key = normalize_key(key.as_ref());
+        self.origins.insert(key.clone(), origin.into());
+        self.values.insert(key, value);
+    }
+
+    fn remove(&mut self, key: impl AsRef<str>) {
+        let key = normalize_key(key.as_ref());
+        self.values.remove(&key);
+        self.origins.remove(&key);
+    }
+
+    fn merge(&mut self, patch: Map<String, Value>, origin: &str) {
+        for (key, value) in patch {
+            let key = normalize_key(&key);
+            match (self.values.get_mut(&key), value) {
+                (_, Value::Null) => self.remove(&key),
+                (Some(current @ Value::Object(_)), patch @ Value::Object(_)) => {
+                    deep_merge(current, patch);
+                    self.origins.insert(key, origin.to_string());
+                }
+                (_, value) => self.set(key, normalize_value(value), origin),
+            }
+        }
+    }
+
+    fn log_origins(&self, chain: &str) {
+        for (key, origin) in &self.origins {
+            info!("[{chain}] {} key {key} from {origin}", self.name);
+        }
+    }
+}
+
+/// Keys in the generated maps are hex encoded without the `0x` prefix.
+fn normalize_key(key: &str) -> String {
+    key.trim_start_matches("0x").to_ascii_lowercase()
+}
+
+fn normalize_value(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.trim_start_matches("0x").to_string()),
+        other => other,
+    }
+}
+
+fn deep_merge(current: &mut Value, patch: Value) {
+    match (current, patch) {
+        (Value::Object(current), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    current.remove(&key);
+                } else {
+                    deep_merge(current.entry(key).or_insert(Value::Null), value);
+                }
+            }
+        }
+        (current, patch) => *current = patch,
+    }
+}
+
+/// Merge the custom overrides (if any) and write the resulting maps to `file_path`. The specs
+/// are resolved with the metadata of the block to bite (`maybe_at_block`), or the current one.
+async fn write_overrides_file(
+    file_path: PathBuf,
+    chain: &str,
+    mut overrides: KeysMap,
+    mut injects: KeysMap,
+    maybe_custom_path: Option<&Path>,
+    rpc_endpoints: &[String],
+    maybe_at_block: Option<u32>,
+) -> Result<PathBuf, anyhow::Error> {
+    if let Some(custom_path) = maybe_custom_path {
+        let custom = CustomOverrides::load(custom_path).await?;
+        let origin = custom_path.to_string_lossy();
+        overrides.merge(custom.overrides, &origin);
+        injects.merge(custom.injects, &origin);
+        for key in custom.remove.overrides {
+            overrides.remove(key);
+        }
+        for key in custom.remove.injects {
+            injects.remove(key);
+        }
+
+        let maybe_metadata = if custom.specs.iter().any(OverrideSpec::needs_metadata) {
+            if rpc_endpoints.is_empty() {
+                return Err(anyhow!(
+                    "rpc endpoint for {chain} is needed to resolve the specs in {origin}"
+                ));
+            }
+            let maybe_at_hash = match maybe_at_block {
+                Some(number) => Some(get_block_hash(number, rpc_endpoints).await?),
+                None => None,
+            };
+            Some(fetch_metadata(rpc_endpoints, maybe_at_hash.as_deref()).await?)
+        } else {
+            None
+        };
+
+        for spec in custom.specs {
+            let (key, value) = spec.resolve(maybe_metadata.as_ref())?;
+            let spec_origin = format!("{origin} ({}::{})", spec.pallet, spec.item);
+            if spec.inject {
+                injects.set(key, Value::String(value), spec_origin);
+            } else {
+                overrides.set(key, Value::String(value), spec_origin);
+            }
+        }
+    }
+
+    overrides.log_origins(chain);
+    injects.log_origins(chain);
+
+    let full_content = json!({
+        "overrides": overrides.values,
+        "injects": injects.values
+    });
+
+    let contents = serde_json::to_string_pretty(&full_content)?;
+    fs::write(&file_path, contents)
+        .await
+        .map_err(|e| anyhow!("Error writing overrides file {}: {e}", file_path.display()))?;
+    Ok(file_path)
+}
+
+/// Default `activeConfig` of the relaychain, `num_cores` is set from the number of paras.
+const ACTIVE_CONFIG: &str = "0000300000500000aaaa020000001000fbff0000100000000a000000403800005802000003000000020000000000500000c800008000000000e8764817000000000000000000000000e87648170000000000000000000000e80300000090010080000000009001000c01002000000600c4090000000000000601983a00000000000040380000000600000058020000030000001900000000000000020000000200000002000000140000000100000008030100000014000000040000000105000000010000000100000000000000f401000080b2e60e80c3c90180b2e60e00000000000000000000000005000000";
+
+/// Encoded tail of the `scheduler_params` in [`ACTIVE_CONFIG`]: num_cores (1),
+/// max_availability_timeouts, on_demand_queue_max_size, on_demand_target_queue_utilization,
+/// on_demand_fee_variability, on_demand_base_fee and ttl.
+const SCHEDULER_PARAMS_TAIL: &str =
+    "0100000000000000f401000080b2e60e80c3c90180b2e60e00000000000000000000000005000000";
+
+/// `activeConfig` with the supplied `num_cores` (scheduler_params), the tail of the config is
+/// checked before patching so a change in the layout fails instead of corrupting the config.
+fn active_config_with_cores(num_cores: u32) -> Result<String, anyhow::Error> {
+    let mut config = hex::decode(ACTIVE_CONFIG)?;
+    let tail = hex::decode(SCHEDULER_PARAMS_TAIL)?;
+    let offset = config
+        .len()
+        .checked_sub(tail.len())
+        .filter(|offset| config[*offset..] == tail[..])
+        .ok_or_else(|| anyhow!("unexpected scheduler_params in the default activeConfig"))?;
+    config[offset..offset + 4].copy_from_slice(&num_cores.to_le_bytes());
+    Ok(hex::encode(config))
+}
 
 pub async fn generate_default_overrides_for_rc(
     base_dir: &str,
     relay: &Relaychain,
     paras: &Vec<Parachain>,
-) -> PathBuf {
+    validators: &[String],
+    maybe_custom_path: Option<&Path>,
+    maybe_at_block: Option<u32>,
+) -> Result<PathBuf, anyhow::Error> {
+    let validators_keys: Vec<NodeKeys> = validators
+        .iter()
+        .map(|name| NodeKeys::from_name(name))
+        .collect();
+    let stashes = hex::encode(encode_vec(
+        &validators_keys.iter().map(|k| k.stash).collect::<Vec<_>>(),
+    ));
+    let queued_keys = hex::encode(encode_vec(
+        &validators_keys
+            .iter()
+            .map(|k| [&k.stash[..], &k.rc_session_keys()].concat())
+            .collect::<Vec<_>>(),
+    ));
+    let babe_authorities = hex::encode(encode_vec(
+        &validators_keys
+            .iter()
+            .map(|k| (k.sr, 1_u64).encode())
+            .collect::<Vec<_>>(),
+    ));
+    let grandpa_authorities = hex::encode(encode_vec(
+        &validators_keys
+            .iter()
+            .map(|k| (k.ed, 1_u64).encode())
+            .collect::<Vec<_>>(),
+    ));
+    let sr_keys = hex::encode(encode_vec(
+        &validators_keys.iter().map(|k| k.sr).collect::<Vec<_>>(),
+    ));
+    let validator_indices = hex::encode(encode_vec(
+        &(0..validators.len() as u32)
+            .map(|i| i.encode())
+            .collect::<Vec<_>>(),
+    ));
+
+    // one core per para (in order) and one group of validators per core
+    let num_cores = paras.len() as u32;
+    let mut para_ids: Vec<u32> = paras.iter().map(Parachain::id).collect();
+    para_ids.sort();
+    let parachains = hex::encode(encode_vec(
+        &para_ids.iter().map(|id| id.encode()).collect::<Vec<_>>(),
+    ));
+    let num_groups = (num_cores as usize).clamp(1, validators.len().max(1));
+    let validator_groups = hex::encode(encode_vec(
+        &(0..num_groups)
+            .map(|group| {
+                encode_vec(
+                    &(0..validators.len() as u32)
+                        .filter(|i| *i as usize % num_groups == group)
+                        .map(|i| i.encode())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>(),
+    ));
+    // all cores free
+    let availability_cores = hex::encode(encode_vec(&vec![[0_u8]; num_cores as usize]));
+    // empty claim queue for each core, will auto-fill
+    let claim_queue = hex::encode(encode_vec(
+        &(0..num_cores)
+            .map(|core| [core.encode(), vec![0]].concat())
+            .collect::<Vec<_>>(),
+    ));
+
     // Keys to inject (mostly storage maps that are not present in the current state)
     // <Pallet> < Item>
     let mut injects = json!({
-        // Session NextKeys (alice)
-        "cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb3e535263148daaf49be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25f": "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0eed43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1",
-        // Session NextKeys (bob)
-        "cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb30e5be00fbc2e15b5fe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860e": "d17c2d7823ebf260fd138f2d7e27d114c0145d968b5ff5006125f2414fadae698eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480390084fdbf27d2b79d26a4f13f0ccd982cb755a661969143c37cbc49ef5b91f27",
         // RcMigrator Manager (set //Alice by default) see: https://github.com/polkadot-fellows/runtimes/blob/22116f7d02c220db4f7187c6967dbd6bf89274cf/pallets/rc-migrator/src/lib.rs#L702-L707
         "2185d18cb42ae97242af0e70e6ad689012fcd13ee43ae32cc87f798eb5ed3295": "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
     });
 
+    // Session NextKeys (validators)
+    let next_keys_prefix = array_bytes::bytes2hex(
+        "",
+        substorager::storage_value_key(&b"Session"[..], b"NextKeys"),
+    );
+    for keys in &validators_keys {
+        let stash_hash = array_bytes::bytes2hex("", subhasher::twox64_concat(keys.stash));
+        injects[format!("{next_keys_prefix}{stash_hash}")] =
+            Value::String(hex::encode(keys.rc_session_keys()));
+    }
+
     // <Pallet> <Item>
     // e.g Validator Validators
 
-    let mut overrides = json!({
+    let overrides = json!({
         // Validator Validators
-        "7d9fe37370ac390779f35763d98106e888dcde934c658227ee1dfafcd6e16903": "08be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25ffe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860e",
-        // Session Validators (alice, bob)
-        "cec5070d609dd3497f72bde07fc96ba088dcde934c658227ee1dfafcd6e16903": "08be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25ffe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860e",
-        //  Session QueuedKeys (alice, bob)
-        "cec5070d609dd3497f72bde07fc96ba0e0cdd062e6eaf24295ad4ccfc41d4609": "08be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25f88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0eed43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1fe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860ed17c2d7823ebf260fd138f2d7e27d114c0145d968b5ff5006125f2414fadae698eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480390084fdbf27d2b79d26a4f13f0ccd982cb755a661969143c37cbc49ef5b91f27",
-        // Babe Authorities (alice, bob)
-        "1cb6f36e027abb2091cfb5110ab5087f5e0621c4869aa60c02be9adcc98a0d1d": "08d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d01000000000000008eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480100000000000000",
-        // Babe NextAuthorities (alice, bob)
-        "1cb6f36e027abb2091cfb5110ab5087faacf00b9b41fda7a9268821c2a2b3e4c": "08d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d01000000000000008eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480100000000000000",
-        // Grandpa Authorities (alice, bob)
-        "5f9cc45b7a00c5899361e1c6099678dc5e0621c4869aa60c02be9adcc98a0d1d": "0888dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee0100000000000000d17c2d7823ebf260fd138f2d7e27d114c0145d968b5ff5006125f2414fadae690100000000000000",
+        "7d9fe37370ac390779f35763d98106e888dcde934c658227ee1dfafcd6e16903": stashes,
+        // Session Validators (validators)
+        "cec5070d609dd3497f72bde07fc96ba088dcde934c658227ee1dfafcd6e16903": stashes,
+        //  Session QueuedKeys (validators)
+        "cec5070d609dd3497f72bde07fc96ba0e0cdd062e6eaf24295ad4ccfc41d4609": queued_keys,
+        // Babe Authorities (validators)
+        "1cb6f36e027abb2091cfb5110ab5087f5e0621c4869aa60c02be9adcc98a0d1d": babe_authorities,
+        // Babe NextAuthorities (validators)
+        "1cb6f36e027abb2091cfb5110ab5087faacf00b9b41fda7a9268821c2a2b3e4c": babe_authorities,
+        // Grandpa Authorities (validators)
+        "5f9cc45b7a00c5899361e1c6099678dc5e0621c4869aa60c02be9adcc98a0d1d": grandpa_authorities,
         // Staking ForceEra (ForceNone)
         // "5f3e4907f716ac89b6347d15ececedcaf7dad0317324aecae8744b87fc95f2f3": "02",
-        // Staking Invulnerables (alice, bob)
-        "5f3e4907f716ac89b6347d15ececedca5579297f4dfb9609e7e4c2ebab9ce40a": "08be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25ffe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860e",
-        // paras parachains (only 1000)
-        "cd710b30bd2eab0352ddcc26417aa1940b76934f4cc08dee01012d059e1b83ee": "04e8030000",
-        // paraScheduler validatorGroup (one group of 2 validators)
-        "94eadf0156a8ad5156507773d0471e4a16973e1142f5bd30d9464076794007db": "041000000000010000000200000003000000",
+        // Staking Invulnerables (validators)
+        "5f3e4907f716ac89b6347d15ececedca5579297f4dfb9609e7e4c2ebab9ce40a": stashes,
+        // paras parachains
+        "cd710b30bd2eab0352ddcc26417aa1940b76934f4cc08dee01012d059e1b83ee": parachains,
+        // paraScheduler validatorGroup (one group per core)
+        "94eadf0156a8ad5156507773d0471e4a16973e1142f5bd30d9464076794007db": validator_groups,
         // paraScheduler claimQueue (empty, will auto-fill)
-        "94eadf0156a8ad5156507773d0471e4a49f6c9aa90c04982c05388649310f22f": "040000000000",
-        // paraShared activeValidatorIndices (2 validators)
-        "b341e3a63e58a188839b242d17f8c9f82586833f834350b4d435d5fd269ecc8b": "080000000001000000",
-        // paraShared activeValidatorKeys (alice, bob)
-        "b341e3a63e58a188839b242d17f8c9f87a50c904b368210021127f9238883a6e": "08d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48",
-        // authorityDiscovery keys (alice, bob)
-        "2099d7f109d6e535fb000bba623fd4409f99a2ce711f3a31b2fc05604c93f179": "08d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48",
-        // authorityDiscovery nextKeys (alice, bob)
-        "2099d7f109d6e535fb000bba623fd4404c014e6bf8b8c2c011e7290b85696bb3": "08d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48",
-        // Core descriptor, ensure core 0 is asset-hub
-        "638595eebaa445ce03a13547bece90e704e6ac775a3245623103ffec2cb2c92fb4def25cfda6ef3ac02a707a7013b12ddc9c5f6a3e1994c51754be175bd6a3d4": "00010402e803000000e100e100010000e1",
-        // dmp downwardMessageQueueHeads (empty for para 1000)
-        "63f78c98723ddc9073523ef3beefda0c4d7fefc408aac59dbfe80a72ac8e3ce5b6ff6f7d467b87a9e8030000": "0000000000000000000000000000000000000000000000000000000000000000",
-        // hrmp hrmpIngressChannelsIndex (empty for para 1000)
-        "6a0da05ca59913bc38a8630590f2627c1d3719f5b0b12c7105c073c507445948b6ff6f7d467b87a9e8030000": "00",
-        // Configuration activeConfig
-        "06de3d8a54d27e44a9d5ce189618f22db4b49d95320d9021994c850f25b8e385": "0000300000500000aaaa020000001000fbff0000100000000a000000403800005802000003000000020000000000500000c800008000000000e8764817000000000000000000000000e87648170000000000000000000000e80300000090010080000000009001000c01002000000600c4090000000000000601983a00000000000040380000000600000058020000030000001900000000000000020000000200000002000000140000000100000008030100000014000000040000000105000000010000000100000000000000f401000080b2e60e80c3c90180b2e60e00000000000000000000000005000000",
-        // paraScheduler availabilityCores (1 core, free)
-        "94eadf0156a8ad5156507773d0471e4ab8ebad86f546c7e0b135a4212aace339": "0400",
+        "94eadf0156a8ad5156507773d0471e4a49f6c9aa90c04982c05388649310f22f": claim_queue,
+        // paraShared activeValidatorIndices (validators)
+        "b341e3a63e58a188839b242d17f8c9f82586833f834350b4d435d5fd269ecc8b": validator_indices,
+        // paraShared activeValidatorKeys (validators)
+        "b341e3a63e58a188839b242d17f8c9f87a50c904b368210021127f9238883a6e": sr_keys,
+        // authorityDiscovery keys (validators)
+        "2099d7f109d6e535fb000bba623fd4409f99a2ce711f3a31b2fc05604c93f179": sr_keys,
+        // authorityDiscovery nextKeys (validators)
+        "2099d7f109d6e535fb000bba623fd4404c014e6bf8b8c2c011e7290b85696bb3": sr_keys,
+        // Configuration activeConfig (with one core per para)
+        "06de3d8a54d27e44a9d5ce189618f22db4b49d95320d9021994c850f25b8e385": active_config_with_cores(num_cores)?,
+        // paraScheduler availabilityCores (one per para, free)
+        "94eadf0156a8ad5156507773d0471e4ab8ebad86f546c7e0b135a4212aace339": availability_cores,
         // Sudo Key (Alice)
         "5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b": "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
     });
 
+    let mut overrides = overrides;
+    for (core, para) in paras.iter().enumerate() {
+        let para_id_hash = para_id_hash(para.id());
+        // Core descriptor, ensure core <n> is assigned to the para
+        overrides[format!(
+            "638595eebaa445ce03a13547bece90e704e6ac775a3245623103ffec2cb2c92f{}",
+            hex::encode(subhasher::twox256((core as u32).encode()))
+        )] = Value::String(format!(
+            "00010402{}00e100e100010000e1",
+            hex::encode(para.id().encode())
+        ));
+        // dmp downwardMessageQueueHeads (empty)
+        overrides[format!(
+            "63f78c98723ddc9073523ef3beefda0c4d7fefc408aac59dbfe80a72ac8e3ce5{para_id_hash}"
+        )] = Value::String("0".repeat(64));
+        // hrmp hrmpIngressChannelsIndex (empty)
+        overrides[format!(
+            "6a0da05ca59913bc38a8630590f2627c1d3719f5b0b12c7105c073c507445948{para_id_hash}"
+        )] = Value::String(String::from("00"));
+    }
+
+    let mut overrides = KeysMap::with_defaults("overrides", overrides);
+    let mut injects = KeysMap::with_defaults("injects", injects);
+
     // update the overrides / injects map to use IFF the key is provided
-    if let Ok(sudo_key) = env::var("ZOMBIE_SUDO") {
+    if let Some(sudo_key) = get_manifest().sudo() {
         // Sudo Key
-        overrides["5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b"] =
-            Value::String(sudo_key.clone());
+        overrides.set(
+            "5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b",
+            Value::String(sudo_key.clone()),
+            "sudo (env ZOMBIE_SUDO / manifest)",
+        );
 
         // RcMigrator Manager
-        injects["2185d18cb42ae97242af0e70e6ad689012fcd13ee43ae32cc87f798eb5ed3295"] =
-            Value::String(sudo_key);
+        injects.set(
+            "2185d18cb42ae97242af0e70e6ad689012fcd13ee43ae32cc87f798eb5ed3295",
+            Value::String(sudo_key),
+            "sudo (env ZOMBIE_SUDO / manifest)",
+        );
     }
 
     if let Some(override_wasm) = relay.wasm_overrides() {
-        let wasm_content = fs::read(override_wasm).await.expect(&format!(
-            "Error reading override_wasm from path {}",
-            override_wasm
-        ));
-        overrides["3a636f6465"] = Value::String(hex::encode(wasm_content));
+        let wasm_content = fs::read(override_wasm)
+            .await
+            .map_err(|e| BiteError::io("reading override_wasm", override_wasm, e))?;
+        overrides.set(
+            "3a636f6465",
+            Value::String(hex::encode(wasm_content)),
+            format!("wasm override {override_wasm}"),
+        );
     }
 
     // also check if any parachain includes a wasm override
     for para in paras {
         if let Some(override_wasm) = para.wasm_overrides() {
-            let wasm_content = fs::read(override_wasm).await.expect(&format!(
-                "Error reading override_wasm from path {}",
-                override_wasm
-            ));
+            let wasm_content = fs::read(override_wasm)
+                .await
+                .map_err(|e| BiteError::io("reading override_wasm", override_wasm, e))?;
             let code_hash = hex::encode(subhasher::blake2_256(&wasm_content[..]));
 
             // we should now override
@@ -104,8 +438,12 @@ pub async fn generate_default_overrides_for_rc(
                 "",
                 substorager::storage_value_key(&b"Paras"[..], b"CurrentCodeHash"),
             );
-            overrides[&format!("{current_code_hash_prefix}{para_id_hash}")] =
-                Value::String(code_hash.clone());
+            let origin = format!("wasm override {override_wasm}");
+            overrides.set(
+                format!("{current_code_hash_prefix}{para_id_hash}"),
+                Value::String(code_hash.clone()),
+                &origin,
+            );
 
             // Paras.CodeByHash (should be injected since is have a reference to hash of the code itself)
             let code_by_hash_prefix = array_bytes::bytes2hex(
@@ -114,8 +452,11 @@ pub async fn generate_default_overrides_for_rc(
             );
             let validation_code: ValidationCode = ValidationCode(wasm_content);
             let validation_code_encoded = validation_code.encode();
-            injects[&format!("{code_by_hash_prefix}{code_hash}")] =
-                Value::String(hex::encode(validation_code_encoded));
+            injects.set(
+                format!("{code_by_hash_prefix}{code_hash}"),
+                Value::String(hex::encode(validation_code_encoded)),
+                &origin,
+            );
 
             // Paras.CodeByHashRefs (should be injected since is have a reference to hash of the code itself)
             let code_by_hash_prefix = array_bytes::bytes2hex(
@@ -123,50 +464,61 @@ pub async fn generate_default_overrides_for_rc(
                 substorager::storage_value_key(&b"Paras"[..], b"CodeByHashRefs"),
             );
             // hardcoded to 1 encoded
-            injects[&format!("{code_by_hash_prefix}{code_hash}")] =
-                Value::String("01000000".into());
+            injects.set(
+                format!("{code_by_hash_prefix}{code_hash}"),
+                Value::String("01000000".into()),
+                &origin,
+            );
         }
     }
 
-    let full_content = json!({
-        "overrides": overrides,
-        "injects": injects
-    });
-
     let file_path = PathBuf::from(format!("{base_dir}/rc_overrides.json"));
-    let contents = serde_json::to_string_pretty(&full_content).expect("Overrides should be valid.");
-    fs::write(&file_path, contents)
-        .await
-        .expect("write file should works.");
-    file_path
+    write_overrides_file(
+        file_path,
+        &relay.as_chain_string(),
+        overrides,
+        injects,
+        maybe_custom_path,
+        &relay.rpc_endpoints(),
+        maybe_at_block,
+    )
+    .await
 }
 
 pub async fn generate_default_overrides_for_para(
     base_dir: &str,
     para: &Parachain,
     relay: &Relaychain,
-) -> PathBuf {
+    maybe_custom_path: Option<&Path>,
+    maybe_at_block: Option<u32>,
+) -> Result<PathBuf, anyhow::Error> {
+    let collator_keys = NodeKeys::from_name(&para.collator_name());
     // asset-hub-polkadot use ed key
-    let key_to_use = if relay.as_chain_string() == "polkadot" {
-        "eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116"
-    } else {
-        "005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15"
-    };
+    let key_to_use =
+        if matches!(para, Parachain::AssetHub { .. }) && relay.as_chain_string() == "polkadot" {
+            hex::encode(collator_keys.ed)
+        } else {
+            hex::encode(collator_keys.sr)
+        };
 
     // Keys to inject (mostly storage maps that are not present in the current state)
-    let injects = json!({
-        // Session Nextkeys for `collator`
-        "cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb39af53646681828f1eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116": "eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116",
-        "cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb39af53646681828f1005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15": "005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15",
-
+    let mut injects = json!({});
+    for key in [collator_keys.ed, collator_keys.sr] {
+        // Session Nextkeys for the collator
+        injects[format!(
+            "cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb3{}",
+            hex::encode(subhasher::twox64_concat(key))
+        )] = Value::String(hex::encode(key));
         // Session KeyOwner
-        "cec5070d609dd3497f72bde07fc96ba0726380404683fc89e8233450c8aa1950eab3d4a1675d3d746175726180eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116": "eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116",
-        "cec5070d609dd3497f72bde07fc96ba0726380404683fc89e8233450c8aa1950eab3d4a1675d3d746175726180005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15": "005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15",
-    });
+        injects[format!(
+            "cec5070d609dd3497f72bde07fc96ba0726380404683fc89e8233450c8aa1950{}",
+            hex::encode(subhasher::twox64_concat((*b"aura", key.to_vec()).encode()))
+        )] = Value::String(hex::encode(key));
+    }
 
     // <Pallet> <Item>
     // e.g Validator Validators
-    let mut overrides = json!({
+    let overrides = json!({
         // Session Validators
         "cec5070d609dd3497f72bde07fc96ba088dcde934c658227ee1dfafcd6e16903": &format!("04{key_to_use}"),
         //	Session QueuedKeys
@@ -183,39 +535,256 @@ pub async fn generate_default_overrides_for_para(
         "15464cac3378d46f113cd5b7a4d71c84476f594316a7dfe49c1f352d95abdaf1": "01000000"
     });
 
+    let mut overrides = KeysMap::with_defaults("overrides", overrides);
+    let injects = KeysMap::with_defaults("injects", injects);
+
     if let Some(override_wasm) = para.wasm_overrides() {
-        let wasm_content = fs::read(override_wasm).await.expect(&format!(
-            "Error reading override_wasm from path {}",
-            override_wasm
-        ));
-        overrides["3a636f6465"] = Value::String(hex::encode(wasm_content));
+        let wasm_content = fs::read(override_wasm)
+            .await
+            .map_err(|e| BiteError::io("reading override_wasm", override_wasm, e))?;
+        overrides.set(
+            "3a636f6465",
+            Value::String(hex::encode(wasm_content)),
+            format!("wasm override {override_wasm}"),
+        );
     }
 
-    let full_content = json!({
-        "overrides": overrides,
-        "injects": injects
-    });
-
     let file_path = PathBuf::from(format!("{base_dir}/{}_overrides.json", para.id()));
-    let contents = serde_json::to_string_pretty(&full_content).expect("Overrides should be valid.");
-    fs::write(&file_path, contents)
-        .await
-        .expect("write file should works.");
-    file_path
+    write_overrides_file(
+        file_path,
+        &para.as_chain_string(&relay.as_chain_string()),
+        overrides,
+        injects,
+        maybe_custom_path,
+        para.rpc_endpoints(),
+        maybe_at_block,
+    )
+    .await
 }
 
 #[cfg(test)]
 mod test {
-    use super::generate_default_overrides_for_rc;
+    use super::*;
 
     #[tokio::test]
     async fn overrides_rc() {
         let paras = vec![];
         let _path = generate_default_overrides_for_rc(
             "/tmp",
-            &crate::config::Relaychain::new("polakdot"),
+            &crate::config::Relaychain::new("polkadot"),
             &paras,
+            &crate::keys::validator_names(2),
+            None,
+            None,
         )
-        .await;
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn custom_overrides_rc_are_merged() {
+        let base_dir = "/tmp/zombie-bite-custom-overrides";
+        fs::create_dir_all(base_dir).await.unwrap();
+        let custom_path = PathBuf::from(format!("{base_dir}/custom.toml"));
+        fs::write(
+            &custom_path,
+            r#"
+[overrides]
+# Sudo Key (Bob)
+"0x5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b" = "0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"
+
+[injects]
+"aabbcc" = "01"
+
+[remove]
+overrides = ["5f3e4907f716ac89b6347d15ececedca5579297f4dfb9609e7e4c2ebab9ce40a"]
+"#,
+        )
+        .await
+        .unwrap();
+
+        let path = generate_default_overrides_for_rc(
+            base_dir,
+            &Relaychain::new("polkadot"),
+            &vec![],
+            &crate::keys::validator_names(2),
+            Some(&custom_path),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let content: Value =
+            serde_json::from_str(&fs::read_to_string(path).await.unwrap()).unwrap();
+        assert_eq!(
+            content["overrides"]
+                ["5c0d1176a568c1f92944340dbfed9e9c530ebca703c85910e7164cb7d1c9e47b"],
+            "8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"
+        );
+        assert!(content["overrides"]
+            .get("5f3e4907f716ac89b6347d15ececedca5579297f4dfb9609e7e4c2ebab9ce40a")
+            .is_none());
+        assert_eq!(content["injects"]["aabbcc"], "01");
+        // defaults are still there
+        assert!(content["injects"]
+            .get("2185d18cb42ae97242af0e70e6ad689012fcd13ee43ae32cc87f798eb5ed3295")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn validators_keys_are_derived() {
+        let base_dir = "/tmp/zombie-bite-validators-overrides";
+        fs::create_dir_all(base_dir).await.unwrap();
+        let path = generate_default_overrides_for_rc(
+            base_dir,
+            &Relaychain::new("polkadot"),
+            &vec![],
+            &crate::keys::validator_names(2),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let content: Value =
+            serde_json::from_str(&fs::read_to_string(path).await.unwrap()).unwrap();
+        // Session NextKeys (bob)
+        assert_eq!(
+            content["injects"]["cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb30e5be00fbc2e15b5fe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860e"],
+            "d17c2d7823ebf260fd138f2d7e27d114c0145d968b5ff5006125f2414fadae698eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480390084fdbf27d2b79d26a4f13f0ccd982cb755a661969143c37cbc49ef5b91f27"
+        );
+        // Session QueuedKeys (alice, bob)
+        assert_eq!(
+            content["overrides"]["cec5070d609dd3497f72bde07fc96ba0e0cdd062e6eaf24295ad4ccfc41d4609"],
+            "08be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25f88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0eed43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27dd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1fe65717dad0447d715f660a0a58411de509b42e6efb8375f562f58a554d5860ed17c2d7823ebf260fd138f2d7e27d114c0145d968b5ff5006125f2414fadae698eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a488eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480390084fdbf27d2b79d26a4f13f0ccd982cb755a661969143c37cbc49ef5b91f27"
+        );
+        // Babe Authorities (alice, bob)
+        assert_eq!(
+            content["overrides"]["1cb6f36e027abb2091cfb5110ab5087f5e0621c4869aa60c02be9adcc98a0d1d"],
+            "08d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d01000000000000008eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a480100000000000000"
+        );
+        // Grandpa Authorities (alice, bob)
+        assert_eq!(
+            content["overrides"]["5f9cc45b7a00c5899361e1c6099678dc5e0621c4869aa60c02be9adcc98a0d1d"],
+            "0888dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee0100000000000000d17c2d7823ebf260fd138f2d7e27d114c0145d968b5ff5006125f2414fadae690100000000000000"
+        );
+        // paraShared activeValidatorIndices (alice, bob)
+        assert_eq!(
+            content["overrides"]
+                ["b341e3a63e58a188839b242d17f8c9f82586833f834350b4d435d5fd269ecc8b"],
+            "080000000001000000"
+        );
+        // paraScheduler validatorGroup (one group with alice, bob)
+        assert_eq!(
+            content["overrides"]
+                ["94eadf0156a8ad5156507773d0471e4a16973e1142f5bd30d9464076794007db"],
+            "04080000000001000000"
+        );
+    }
+
+    #[test]
+    fn active_config_cores_should_be_patched() {
+        assert_eq!(active_config_with_cores(1).unwrap(), ACTIVE_CONFIG);
+        let config = hex::decode(active_config_with_cores(3).unwrap()).unwrap();
+        assert_eq!(config.len(), 238);
+        assert_eq!(&config[198..202], &3_u32.to_le_bytes());
+        assert_eq!(hex::encode(&config[202..]), &SCHEDULER_PARAMS_TAIL[8..]);
+    }
+
+    #[tokio::test]
+    async fn paras_get_one_core_each() {
+        let base_dir = "/tmp/zombie-bite-paras-overrides";
+        fs::create_dir_all(base_dir).await.unwrap();
+        let paras = vec![Parachain::new("asset-hub"), Parachain::new("coretime")];
+        let path = generate_default_overrides_for_rc(
+            base_dir,
+            &Relaychain::new("polkadot"),
+            &paras,
+            &crate::keys::validator_names(2),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let content: Value =
+            serde_json::from_str(&fs::read_to_string(path).await.unwrap()).unwrap();
+        let overrides = &content["overrides"];
+        // paras parachains (1000, 1005)
+        assert_eq!(
+            overrides["cd710b30bd2eab0352ddcc26417aa1940b76934f4cc08dee01012d059e1b83ee"],
+            "08e8030000ed030000"
+        );
+        // Core descriptor (core 0 is asset-hub)
+        assert_eq!(
+            overrides["638595eebaa445ce03a13547bece90e704e6ac775a3245623103ffec2cb2c92fb4def25cfda6ef3ac02a707a7013b12ddc9c5f6a3e1994c51754be175bd6a3d4"],
+            "00010402e803000000e100e100010000e1"
+        );
+        // paraScheduler availabilityCores (2 cores, free)
+        assert_eq!(
+            overrides["94eadf0156a8ad5156507773d0471e4ab8ebad86f546c7e0b135a4212aace339"],
+            "080000"
+        );
+        // paraScheduler validatorGroup (alice / bob)
+        assert_eq!(
+            overrides["94eadf0156a8ad5156507773d0471e4a16973e1142f5bd30d9464076794007db"],
+            "0804000000000401000000"
+        );
+        let active_config = hex::decode(
+            overrides["06de3d8a54d27e44a9d5ce189618f22db4b49d95320d9021994c850f25b8e385"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(active_config.len(), 238);
+        assert_eq!(
+            active_config[active_config.len() - 40..active_config.len() - 36],
+            [2, 0, 0, 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn collator_keys_are_derived() {
+        let base_dir = "/tmp/zombie-bite-collator-overrides";
+        fs::create_dir_all(base_dir).await.unwrap();
+        let path = generate_default_overrides_for_para(
+            base_dir,
+            &Parachain::new("asset-hub"),
+            &Relaychain::new("polkadot"),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let content: Value =
+            serde_json::from_str(&fs::read_to_string(path).await.unwrap()).unwrap();
+        // Session KeyOwner (ed)
+        assert_eq!(
+            content["injects"]["cec5070d609dd3497f72bde07fc96ba0726380404683fc89e8233450c8aa1950eab3d4a1675d3d746175726180eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116"],
+            "eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116"
+        );
+        // Session Nextkeys (sr)
+        assert_eq!(
+            content["injects"]["cec5070d609dd3497f72bde07fc96ba04c014e6bf8b8c2c011e7290b85696bb39af53646681828f1005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15"],
+            "005025ef7c9934c33534cbff35c9c5f0c1d30128e64f076c76942f49788eec15"
+        );
+        // Aura authorities (ed in asset-hub-polkadot)
+        assert_eq!(
+            content["overrides"]
+                ["57f8dc2f5ab09467896f47300f0424385e0621c4869aa60c02be9adcc98a0d1d"],
+            "04eb2f4b5e6f0bfa7ba42aa4b7eb2f43ba6c42061dbfc765bca066e51bb09f9116"
+        );
+    }
+
+    #[test]
+    fn null_value_removes_key() {
+        let mut overrides = KeysMap::with_defaults("overrides", json!({ "aa": "01", "bb": "02" }));
+        let patch = json!({ "0xAA": null, "cc": "0x03" });
+        overrides.merge(patch.as_object().unwrap().clone(), "test");
+        assert!(overrides.values.get("aa").is_none());
+        assert_eq!(overrides.values["cc"], "03");
+        assert_eq!(overrides.origins["cc"], "test");
+        assert_eq!(overrides.origins["bb"], DEFAULT_ORIGIN);
     }
 }
@@ -0,0 +1,373 @@
+//! Progress of the sync nodes (best vs target, blocks/sec, warp phase and eta), rendered as
+//! one progress bar per chain in a tty or as periodic log lines otherwise.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    io::{self, IsTerminal, Write},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use tracing::info;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Window used to compute the sync rate.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// How often we log the progress (no tty).
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncPhase {
+    /// Waiting for peers
+    Connecting,
+    /// Warp sync, downloading the finality proofs
+    DownloadingProofs,
+    /// Warp sync, downloading the state of the target block
+    DownloadingState,
+    /// Warp sync, importing the downloaded state
+    ImportingState,
+    /// Importing blocks
+    Syncing,
+    /// Downloading the history (blocks before the warp target)
+    GapSync,
+    Synced,
+}
+
+impl fmt::Display for SyncPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            SyncPhase::Connecting => "connecting",
+            SyncPhase::DownloadingProofs => "downloading proofs",
+            SyncPhase::DownloadingState => "downloading state",
+            SyncPhase::ImportingState => "state import",
+            SyncPhase::Syncing => "syncing",
+            SyncPhase::GapSync => "gap sync",
+            SyncPhase::Synced => "synced",
+        };
+        write!(f, "{phase}")
+    }
+}
+
+impl SyncPhase {
+    /// Phase from the (informant) log line of the node, e.g.
+    /// `⏩ Warping, Downloading finality proofs, 2.34 Mib (8 peers), best: #0 (0x...)`
+    pub fn from_log_line(line: &str) -> Option<Self> {
+        if line.contains("Warping, Downloading finality proofs") {
+            Some(SyncPhase::DownloadingProofs)
+        } else if line.contains("Warping, Downloading state") {
+            Some(SyncPhase::DownloadingState)
+        } else if line.contains("Warping, Importing state") {
+            Some(SyncPhase::ImportingState)
+        } else if line.contains("Block history") {
+            Some(SyncPhase::GapSync)
+        } else if line.contains("⚙️  Syncing") || line.contains("⚙️  Preparing") {
+            Some(SyncPhase::Syncing)
+        } else {
+            None
+        }
+    }
+
+    /// Progress of the warp sync phase (e.g. `2.34 Mib` in `Warping, Downloading finality proofs, 2.34 Mib (8 peers)`),
+    /// changes while the warp sync is advancing.
+    pub fn warp_progress(line: &str) -> Option<&str> {
+        let status = &line[line.find("Warping, ")?..];
+        status.split(", ").nth(2)?.split(" (").next()
+    }
+
+    /// Last phase reported in the logs (IFF any).
+    pub fn from_logs(logs: &str) -> Option<Self> {
+        logs.lines().rev().find_map(Self::from_log_line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncProgress {
+    pub chain: String,
+    pub phase: SyncPhase,
+    pub best: u64,
+    pub target: u64,
+    /// Blocks per second
+    pub rate: Option<f64>,
+    pub eta: Option<Duration>,
+}
+
+impl SyncProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.phase == SyncPhase::Synced {
+            return 1_f64;
+        }
+        if self.target == 0 {
+            return 0_f64;
+        }
+        (self.best as f64 / self.target as f64).min(1_f64)
+    }
+
+    fn bar(&self) -> String {
+        let filled = (self.fraction() * BAR_WIDTH as f64) as usize;
+        format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled))
+    }
+
+    fn rate_str(&self) -> String {
+        self.rate
+            .map(|rate| format!("{rate:.1} blk/s"))
+            .unwrap_or_else(|| String::from("- blk/s"))
+    }
+
+    fn eta_str(&self) -> String {
+        self.eta
+            .map(format_duration)
+            .unwrap_or_else(|| String::from("--:--:--"))
+    }
+
+    /// Progress bar line, e.g.
+    /// `polkadot [######--------]  45.2% #12345/27000 312.4 blk/s ETA 00:47:12 (downloading state)`
+    pub fn line(&self, chain_width: usize) -> String {
+        format!(
+            "{:<chain_width$} [{}] {:>5.1}% #{}/{} {} ETA {} ({})",
+            self.chain,
+            self.bar(),
+            self.fraction() * 100_f64,
+            self.best,
+            self.target,
+            self.rate_str(),
+            self.eta_str(),
+            self.phase
+        )
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+/// Tracks the best block samples of a chain, to compute the rate and eta.
+#[derive(Debug, Default)]
+pub struct RateTracker {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RateTracker {
+    /// Add the sample and return the (rate, eta) to reach the target.
+    pub fn sample(
+        &mut self,
+        at: Instant,
+        best: u64,
+        target: u64,
+    ) -> (Option<f64>, Option<Duration>) {
+        self.samples.push_back((at, best));
+        while let Some((first_at, _)) = self.samples.front() {
+            if at.duration_since(*first_at) > RATE_WINDOW {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let (first_at, first_best) = self.samples.front().copied().unwrap_or((at, best));
+        let elapsed = at.duration_since(first_at).as_secs_f64();
+        if elapsed <= 0_f64 || best <= first_best {
+            return (None, None);
+        }
+        let rate = (best - first_best) as f64 / elapsed;
+        let eta = Duration::from_secs_f64(target.saturating_sub(best) as f64 / rate);
+        (Some(rate), Some(eta))
+    }
+}
+
+#[derive(Debug, Default)]
+struct RenderState {
+    chains: BTreeMap<String, SyncProgress>,
+    /// Lines drawn in the last render (tty)
+    lines_drawn: usize,
+    last_log: HashMap<String, Instant>,
+}
+
+static STATE: OnceLock<Mutex<RenderState>> = OnceLock::new();
+
+/// Report the progress of a chain, all the syncs (running concurrently) share the same output.
+pub fn report(progress: SyncProgress) {
+    let state = STATE.get_or_init(Default::default);
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+
+    if io::stdout().is_terminal() {
+        state.chains.insert(progress.chain.clone(), progress);
+        let mut stdout = io::stdout().lock();
+        // move to the first line of the previous render
+        if state.lines_drawn > 0 {
+            let _ = write!(stdout, "\x1b[{}A", state.lines_drawn);
+        }
+        draw(&state, &mut stdout);
+        state.lines_drawn = state.chains.len();
+        // start a new render in the next sync
+        if state.chains.values().all(|p| p.phase == SyncPhase::Synced) {
+            state.chains.clear();
+            state.lines_drawn = 0;
+        }
+    } else {
+        let now = Instant::now();
+        let should_log = progress.phase == SyncPhase::Synced
+            || state
+                .last_log
+                .get(&progress.chain)
+                .is_none_or(|last| now.duration_since(*last) >= LOG_INTERVAL);
+        if should_log {
+            state.last_log.insert(progress.chain.clone(), now);
+            // the log writer also locks the state
+            drop(state);
+            info!(
+                chain = progress.chain,
+                phase = %progress.phase,
+                best = progress.best,
+                target = progress.target,
+                rate = progress.rate.map(|r| format!("{r:.1}")).unwrap_or_default(),
+                eta = progress.eta_str(),
+                "sync progress {:.1}%",
+                progress.fraction() * 100_f64
+            );
+        }
+    }
+}
+
+fn draw(state: &RenderState, out: &mut impl Write) {
+    let chain_width = state
+        .chains
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or_default();
+    for progress in state.chains.values() {
+        let _ = writeln!(out, "\x1b[2K{}", progress.line(chain_width));
+    }
+    let _ = out.flush();
+}
+
+/// Write the log line above the progress bars (IFF drawn): clear the bars, write the log and
+/// draw them again, so the next redraw doesn't overwrite the log.
+fn write_log(state: &RenderState, out: &mut impl Write, log: &[u8]) -> io::Result<()> {
+    if state.lines_drawn > 0 {
+        write!(out, "\x1b[{}A\x1b[J", state.lines_drawn)?;
+        out.write_all(log)?;
+        draw(state, out);
+        Ok(())
+    } else {
+        out.write_all(log)?;
+        out.flush()
+    }
+}
+
+/// Writer for the tracing logs (stdout) that suspends the progress bars around each log line.
+pub struct LogWriter;
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogLine(Vec::new())
+    }
+}
+
+/// Buffered log line, written (in one go) on drop.
+pub struct LogLine(Vec<u8>);
+
+impl Write for LogLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogLine {
+    fn drop(&mut self) {
+        if self.0.is_empty() {
+            return;
+        }
+        let state = STATE.get_or_init(Default::default);
+        let state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = write_log(&state, &mut io::stdout().lock(), &self.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phase_should_be_parsed_from_logs() {
+        let logs = "2025-08-01 ⚙️  Preparing  0.0 bps, target=#27000 (8 peers)\n\
+            2025-08-01 ⏩ Warping, Downloading finality proofs, 2.34 Mib (8 peers), best: #0 (0x91b1…90c3)\n\
+            2025-08-01 ⏩ Warping, Importing state, 1.02 Gib (8 peers), best: #0 (0x91b1…90c3)\n\
+            2025-08-01 💤 Idle (8 peers)";
+        assert_eq!(SyncPhase::from_logs(logs), Some(SyncPhase::ImportingState));
+        assert_eq!(
+            SyncPhase::from_log_line("⏩ Block history, #12 (8 peers)"),
+            Some(SyncPhase::GapSync)
+        );
+        assert_eq!(SyncPhase::from_logs("💤 Idle (8 peers)"), None);
+        assert_eq!(
+            SyncPhase::warp_progress(logs.lines().nth(2).unwrap()),
+            Some("1.02 Gib")
+        );
+        assert_eq!(SyncPhase::warp_progress("⚙️  Syncing 10.2 bps"), None);
+    }
+
+    #[test]
+    fn rate_and_eta_should_work() {
+        let mut tracker = RateTracker::default();
+        let start = Instant::now();
+        assert_eq!(tracker.sample(start, 100, 1100), (None, None));
+        let (rate, eta) = tracker.sample(start + Duration::from_secs(10), 200, 1100);
+        assert_eq!(rate, Some(10_f64));
+        assert_eq!(eta, Some(Duration::from_secs(90)));
+
+        let progress = SyncProgress {
+            chain: String::from("polkadot"),
+            phase: SyncPhase::Syncing,
+            best: 200,
+            target: 1000,
+            rate,
+            eta,
+        };
+        assert_eq!(
+            progress.line(8),
+            "polkadot [######------------------------]  20.0% #200/1000 10.0 blk/s ETA 00:01:30 (syncing)"
+        );
+    }
+
+    #[test]
+    fn logs_should_be_written_above_the_bars() {
+        let mut state = RenderState::default();
+        let mut out = Vec::new();
+        write_log(&state, &mut out, b"log 1\n").unwrap();
+        assert_eq!(out, b"log 1\n");
+
+        let progress = SyncProgress {
+            chain: String::from("polkadot"),
+            phase: SyncPhase::Syncing,
+            best: 200,
+            target: 1000,
+            rate: None,
+            eta: None,
+        };
+        let line = progress.line(8);
+        state.chains.insert(progress.chain.clone(), progress);
+        state.lines_drawn = 1;
+        let mut out = Vec::new();
+        write_log(&state, &mut out, b"log 2\n").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("\x1b[1A\x1b[Jlog 2\n\x1b[2K{line}\n")
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Event log (`events.jsonl` in the step dir) of the running network, with the checks, block
+//! height samples, restarts and failures, and the final report (`report.json` / `report.md`)
+//! generated from it at teardown.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::{debug, info, warn};
+use zombienet_sdk::{LocalFileSystem, Network};
+
+use crate::error::BiteError;
+use crate::metrics;
+use crate::utils::move_file;
+
+pub const EVENTS_FILE: &str = "events.jsonl";
+pub const REPORT_FILE: &str = "report.json";
+pub const REPORT_MD_FILE: &str = "report.md";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// Result of a monitor health check
+    Check {
+        node: String,
+        check: String,
+        healthy: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Block heights of the node
+    Sample {
+        node: String,
+        best: u64,
+        finalized: u64,
+    },
+    Restart {
+        node: String,
+        reason: String,
+    },
+    Failure {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        node: Option<String>,
+        reason: String,
+    },
+    /// The network was signaled to stop
+    Stop {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Unix timestamp (millis)
+    pub ts: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Append only log of the events of a step.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    step_dir: PathBuf,
+    /// Unix timestamp (millis) of the start of the step
+    started_at: u64,
+}
+
+impl EventLog {
+    pub fn new(step_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            step_dir: step_dir.into(),
+            started_at: now_ms(),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.step_dir.join(EVENTS_FILE)
+    }
+
+    /// Append the event to the log, errors are only logged (the log should never stop the network).
+    pub async fn append(&self, event: Event) {
+        let record = EventRecord {
+            ts: now_ms(),
+            event,
+        };
+        if let Err(e) = self.write_record(&record).await {
+            warn!("Error writing event to {}: {e}", self.path().display());
+        }
+    }
+
+    async fn write_record(&self, record: &EventRecord) -> Result<(), anyhow::Error> {
+        let line = format!("{}\n", serde_json::to_string(record)?);
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path())
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Sample the best / finalized block heights of all the nodes.
+    pub async fn sample(&self, network: &Network<LocalFileSystem>) {
+        for node in network.nodes() {
+            let best = node.reports("block_height{status=\"best\"}").await;
+            let finalized = node.reports("block_height{status=\"finalized\"}").await;
+            match (best, finalized) {
+                (Ok(best), Ok(finalized)) => {
+                    metrics::observe_heights(node.name(), best, finalized);
+                    self.append(Event::Sample {
+                        node: node.name().to_string(),
+                        best: best as u64,
+                        finalized: finalized as u64,
+                    })
+                    .await
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    debug!("Error sampling {} block heights: {e}", node.name())
+                }
+            }
+        }
+    }
+
+    pub async fn load(&self) -> Result<Vec<EventRecord>, BiteError> {
+        let path = self.path();
+        let content = match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(BiteError::io("reading events log", &path, e)),
+        };
+        // skip invalid lines (e.g. a partial write)
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Generate the report from the log, and write it (json and markdown) in the step dir.
+    pub async fn write_report(&self, step: &str, artifacts: bool) -> Result<RunReport, BiteError> {
+        let events = self.load().await?;
+        let report = RunReport::from_events(step, self.started_at, now_ms(), artifacts, &events);
+
+        let json_path = self.step_dir.join(REPORT_FILE);
+        let content = serde_json::to_string_pretty(&report)
+            .map_err(|e| BiteError::Io(format!("Error serializing report: {e}")))?;
+        fs::write(&json_path, content)
+            .await
+            .map_err(|e| BiteError::io("writing", &json_path, e))?;
+        let md_path = self.step_dir.join(REPORT_MD_FILE);
+        fs::write(&md_path, report.to_markdown())
+            .await
+            .map_err(|e| BiteError::io("writing", &md_path, e))?;
+        info!("📊 report written to {}", md_path.display());
+
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeReport {
+    pub name: String,
+    /// Blocks produced (best block progress) while the network was running
+    pub blocks: u64,
+    /// Average block time (secs)
+    pub avg_block_time: Option<f64>,
+    /// Average / max best - finalized
+    pub avg_finality_lag: Option<f64>,
+    pub max_finality_lag: Option<u64>,
+    pub restarts: u32,
+    pub failed_checks: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub step: String,
+    /// Unix timestamps (secs)
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub duration_secs: u64,
+    pub stop_reason: Option<String>,
+    /// If the artifacts for the next step were generated
+    pub artifacts: bool,
+    pub nodes: Vec<NodeReport>,
+    pub failures: Vec<String>,
+}
+
+/// Report of the node (added IFF is not present yet).
+fn node_mut<'a>(nodes: &'a mut Vec<NodeReport>, name: &str) -> &'a mut NodeReport {
+    let idx = match nodes.iter().position(|n| n.name == name) {
+        Some(idx) => idx,
+        None => {
+            nodes.push(NodeReport {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            nodes.len() - 1
+        }
+    };
+    &mut nodes[idx]
+}
+
+impl RunReport {
+    pub fn from_events(
+        step: &str,
+        started_at: u64,
+        finished_at: u64,
+        artifacts: bool,
+        events: &[EventRecord],
+    ) -> Self {
+        let mut nodes: Vec<NodeReport> = vec![];
+        let mut stop_reason = None;
+        let mut failures = vec![];
+        for record in events {
+            match &record.event {
+                Event::Check {
+                    node,
+                    healthy: false,
+                    ..
+                } => node_mut(&mut nodes, node).failed_checks += 1,
+                Event::Restart { node, .. } => node_mut(&mut nodes, node).restarts += 1,
+                Event::Failure { node, reason } => failures.push(match node {
+                    Some(node) => format!("{node}: {reason}"),
+                    None => reason.clone(),
+                }),
+                Event::Stop { reason } => stop_reason = Some(reason.clone()),
+                Event::Sample { node, .. } => {
+                    node_mut(&mut nodes, node);
+                }
+                Event::Check { .. } => {}
+            }
+        }
+
+        for node in nodes.iter_mut() {
+            // (ts, best, finalized)
+            let samples: Vec<(u64, u64, u64)> = events
+                .iter()
+                .filter_map(|record| match &record.event {
+                    Event::Sample {
+                        node: name,
+                        best,
+                        finalized,
+                    } if *name == node.name => Some((record.ts, *best, *finalized)),
+                    _ => None,
+                })
+                .collect();
+            let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+                continue;
+            };
+
+            node.blocks = last.1.saturating_sub(first.1);
+            if node.blocks > 0 {
+                node.avg_block_time =
+                    Some((last.0 - first.0) as f64 / 1000_f64 / node.blocks as f64);
+            }
+            let lags: Vec<u64> = samples
+                .iter()
+                .map(|(_, best, finalized)| best.saturating_sub(*finalized))
+                .collect();
+            node.avg_finality_lag = Some(lags.iter().sum::<u64>() as f64 / lags.len() as f64);
+            node.max_finality_lag = lags.iter().max().copied();
+        }
+
+        Self {
+            step: step.to_string(),
+            started_at: started_at / 1000,
+            finished_at: finished_at / 1000,
+            duration_secs: finished_at.saturating_sub(started_at) / 1000,
+            stop_reason,
+            artifacts,
+            nodes,
+            failures,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let d = self.duration_secs;
+        let mut md = format!("# zombie-bite report ({})\n\n", self.step);
+        md.push_str(&format!(
+            "- Duration: {}h {}m {}s\n",
+            d / 3600,
+            (d % 3600) / 60,
+            d % 60
+        ));
+        md.push_str(&format!(
+            "- Stopped by: {}\n",
+            self.stop_reason.as_deref().unwrap_or("unknown")
+        ));
+        md.push_str(&format!(
+            "- Artifacts: {}\n\n",
+            if self.artifacts {
+                "generated"
+            } else {
+                "discarded"
+            }
+        ));
+
+        md.push_str("| Node | Blocks | Avg block time (s) | Avg finality lag | Max finality lag | Restarts | Failed checks |\n");
+        md.push_str("|------|--------|--------------------|------------------|------------------|----------|---------------|\n");
+        let or_dash = |v: Option<String>| v.unwrap_or_else(|| String::from("-"));
+        for node in &self.nodes {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\n",
+                node.name,
+                node.blocks,
+                or_dash(node.avg_block_time.map(|t| format!("{t:.2}"))),
+                or_dash(node.avg_finality_lag.map(|l| format!("{l:.1}"))),
+                or_dash(node.max_finality_lag.map(|l| l.to_string())),
+                node.restarts,
+                node.failed_checks
+            ));
+        }
+
+        if !self.failures.is_empty() {
+            md.push_str("\n## Failures\n\n");
+            for failure in &self.failures {
+                md.push_str(&format!("- {failure}\n"));
+            }
+        }
+
+        md
+    }
+}
+
+/// Move the events log (IFF exist) between dirs, e.g. when the step dir is cleaned up.
+pub async fn move_events_log(from_dir: &Path, to_dir: &Path) -> Result<(), BiteError> {
+    let from = from_dir.join(EVENTS_FILE);
+    if let Ok(true) = fs::try_exists(&from).await {
+        move_file(&from, to_dir.join(EVENTS_FILE)).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(ts: u64, event: Event) -> EventRecord {
+        EventRecord { ts, event }
+    }
+
+    fn sample(ts: u64, node: &str, best: u64, finalized: u64) -> EventRecord {
+        record(
+            ts,
+            Event::Sample {
+                node: node.to_string(),
+                best,
+                finalized,
+            },
+        )
+    }
+
+    #[test]
+    fn events_should_serialize_as_flat_json() {
+        let r = record(
+            1,
+            Event::Restart {
+                node: String::from("alice"),
+                reason: String::from("stalled"),
+            },
+        );
+        let line = serde_json::to_string(&r).unwrap();
+        assert_eq!(
+            line,
+            r#"{"ts":1,"event":"restart","node":"alice","reason":"stalled"}"#
+        );
+        assert_eq!(serde_json::from_str::<EventRecord>(&line).unwrap(), r);
+    }
+
+    #[test]
+    fn report_should_summarize_events() {
+        let events = vec![
+            sample(0, "alice", 100, 98),
+            sample(0, "bob", 100, 98),
+            sample(60_000, "alice", 110, 100),
+            record(
+                61_000,
+                Event::Check {
+                    node: String::from("bob"),
+                    check: String::from("best-progress"),
+                    healthy: false,
+                    reason: Some(String::from("stalled")),
+                },
+            ),
+            record(
+                61_000,
+                Event::Restart {
+                    node: String::from("bob"),
+                    reason: String::from("stalled"),
+                },
+            ),
+            record(
+                62_000,
+                Event::Stop {
+                    reason: String::from("stop file"),
+                },
+            ),
+        ];
+        let report = RunReport::from_events("spawn", 0, 3_723_000, true, &events);
+        assert_eq!(report.duration_secs, 3723);
+        assert_eq!(report.stop_reason.as_deref(), Some("stop file"));
+
+        let alice = &report.nodes[0];
+        assert_eq!(alice.blocks, 10);
+        assert_eq!(alice.avg_block_time, Some(6.0));
+        assert_eq!(alice.avg_finality_lag, Some(6.0));
+        assert_eq!(alice.max_finality_lag, Some(10));
+
+        let bob = &report.nodes[1];
+        assert_eq!((bob.blocks, bob.avg_block_time), (0, None));
+        assert_eq!((bob.restarts, bob.failed_checks), (1, 1));
+
+        let md = report.to_markdown();
+        assert!(md.contains("- Duration: 1h 2m 3s"));
+        assert!(md.contains("| alice | 10 | 6.00 | 6.0 | 10 | 0 | 0 |"));
+    }
+}
@@ -0,0 +1,1037 @@
+//! Snapshots of the nodes dbs (tar of the `data` dir), compressed with the selected codec
+//! in blocking threads (so the snapshots of the nodes can run concurrently).
+//!
+//! zombienet only loads gzip snapshots (`db_snapshot`), so gzip is the default and is
+//! compressed in parallel (pigz like, a single gzip member). The zstd / uncompressed
+//! snapshots are extracted by us in the node dir before the spawn.
+//!
+//! With `[snapshot] dir = true` the db is also kept uncompressed next to the snapshot
+//! (`<name>-snap.d/data`) and the spawn clones it in the node dir (see `cow`), falling back
+//! to the snapshot if the clone fails.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use flate2::{read::GzDecoder, Compress, Compression, FlushCompress};
+use futures::future::{join_all, try_join_all};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::{Archive, Builder};
+use tokio::fs;
+use tracing::{debug, info, trace, warn};
+
+use crate::cow;
+use crate::doctor::{Check, CheckStatus, Report};
+use crate::error::BiteError;
+use crate::manifest::get_manifest;
+use crate::metrics;
+
+/// Size of the chunks compressed in parallel (gzip).
+const GZIP_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+const ZSTD_LEVEL: i32 = 3;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    /// Compatible with the zombienet `db_snapshot` loader
+    #[default]
+    Gzip,
+    /// Multithreaded zstd
+    Zstd,
+    /// Uncompressed tar
+    None,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 3] = [Codec::Gzip, Codec::Zstd, Codec::None];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Gzip => "tgz",
+            Codec::Zstd => "tar.zst",
+            Codec::None => "tar",
+        }
+    }
+
+    /// Codec from the snapshot file name (e.g. `alice-polkadot-snap.tar.zst`).
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let name = path.as_ref().to_string_lossy();
+        Self::ALL
+            .into_iter()
+            .find(|codec| name.ends_with(&format!(".{}", codec.extension())))
+            .unwrap_or_default()
+    }
+
+    /// Codec from the content of the snapshot (magic bytes).
+    pub fn detect(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut magic = [0_u8; 4];
+        let read = File::open(path)?.read(&mut magic)?;
+        Ok(if read >= 2 && magic[..2] == GZIP_MAGIC {
+            Codec::Gzip
+        } else if read == 4 && magic == ZSTD_MAGIC {
+            Codec::Zstd
+        } else {
+            Codec::None
+        })
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let codec = match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+            Codec::None => "none",
+        };
+        write!(f, "{codec}")
+    }
+}
+
+impl FromStr for Codec {
+    type Err = BiteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Codec::Gzip),
+            "zstd" => Ok(Codec::Zstd),
+            "none" => Ok(Codec::None),
+            _ => Err(BiteError::Config(format!("invalid snapshot codec '{s}'"))),
+        }
+    }
+}
+
+/// Snapshot file name for `name` with the codec of the manifest (e.g. `polkadot-snap.tgz`).
+pub fn snap_file(name: &str) -> String {
+    format!(
+        "{name}-snap.{}",
+        get_manifest().snapshot.codec().extension()
+    )
+}
+
+/// Snapshot file (any codec) for `name` in `dir` (IFF exist).
+pub fn find_snap(dir: &Path, name: &str) -> Option<String> {
+    Codec::ALL
+        .iter()
+        .map(|codec| format!("{name}-snap.{}", codec.extension()))
+        .find(|file| dir.join(file).exists())
+}
+
+/// Uncompressed db kept next to the snapshot (e.g. `polkadot-snap.tgz` -> `polkadot-snap.d`).
+pub fn snap_dir(snap_path: &Path) -> PathBuf {
+    let name = file_name(snap_path);
+    let ext = format!(".{}", Codec::from_path(snap_path).extension());
+    let stem = name.strip_suffix(&ext).unwrap_or(&name);
+    snap_path.with_file_name(format!("{stem}.d"))
+}
+
+/// Suffix of the sidecar manifest of the snapshots (e.g. `polkadot-snap.tgz.manifest.json`).
+pub const SNAP_MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Chain and block (IFF known) of the snapshot.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapSource {
+    pub chain: String,
+    pub block_number: Option<u64>,
+    pub block_hash: Option<String>,
+}
+
+impl SnapSource {
+    pub fn new(chain: impl Into<String>) -> Self {
+        Self {
+            chain: chain.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Sidecar manifest of a snapshot, to verify it before spawn (`zombie-bite verify`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapManifest {
+    /// Snapshot file name
+    pub file: String,
+    pub codec: Codec,
+    pub size: u64,
+    pub sha256: String,
+    pub chain: String,
+    /// Block of the bite (or the last observed best block of the node in the next steps)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+    /// rocksdb or paritydb
+    pub database: String,
+    pub zombie_bite_version: String,
+}
+
+impl SnapManifest {
+    /// Path of the sidecar manifest of the snapshot.
+    pub fn path(snap_path: &Path) -> PathBuf {
+        PathBuf::from(format!("{}{SNAP_MANIFEST_SUFFIX}", snap_path.display()))
+    }
+
+    /// Load the sidecar manifest of the snapshot (IFF exist).
+    pub async fn load(snap_path: &Path) -> Result<Option<Self>, BiteError> {
+        let path = Self::path(snap_path);
+        let content = match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(BiteError::io("reading", &path, e)),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| BiteError::Snapshot(format!("invalid manifest {}: {e}", path.display())))
+    }
+
+    async fn write(&self, snap_path: &Path) -> Result<(), BiteError> {
+        let path = Self::path(snap_path);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| BiteError::Snapshot(format!("Error serializing manifest: {e}")))?;
+        fs::write(&path, content)
+            .await
+            .map_err(|e| BiteError::io("writing", &path, e))
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Database backend of the node data dir (`chains/<id>/paritydb` or `chains/<id>/db`).
+fn database_of(data_path: &Path) -> String {
+    let is_paritydb = std::fs::read_dir(data_path.join("chains"))
+        .map(|chains| {
+            chains
+                .flatten()
+                .any(|chain| chain.path().join("paritydb").exists())
+        })
+        .unwrap_or_default();
+    String::from(if is_paritydb { "paritydb" } else { "rocksdb" })
+}
+
+/// Writer that computes the sha256 (and size) of the written data.
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(data)?;
+        self.hasher.update(&data[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Sha256 (hex) of the file (blocking).
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = HashWriter::new(io::sink());
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(hex::encode(hasher.hasher.finalize()))
+}
+
+/// Generate the snapshot of `data_path` (as `data` dir) in `snap_path` and its sidecar manifest,
+/// the codec is inferred from the extension of `snap_path`.
+pub async fn generate_snap(
+    data_path: &str,
+    snap_path: &str,
+    source: SnapSource,
+) -> Result<(), anyhow::Error> {
+    let codec = Codec::from_path(snap_path);
+    let threads = get_manifest().snapshot.threads();
+    info!("\n📝 Generating snapshot file {snap_path} ({codec}) with data_path {data_path}...");
+
+    let (data, snap) = (data_path.to_string(), snap_path.to_string());
+    let (size, sha256) =
+        tokio::task::spawn_blocking(move || write_snap(&data, &snap, codec, threads))
+            .await?
+            .map_err(|e| {
+                BiteError::Snapshot(format!(
+                    "Error generating snapshot {snap_path} from {data_path}: {e}"
+                ))
+            })?;
+    metrics::inc(metrics::SNAPSHOT_BYTES, &[], size as f64);
+
+    let manifest = SnapManifest {
+        file: file_name(Path::new(snap_path)),
+        codec,
+        size,
+        sha256,
+        chain: source.chain,
+        block_number: source.block_number,
+        block_hash: source.block_hash,
+        database: database_of(Path::new(data_path)),
+        zombie_bite_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    manifest.write(Path::new(snap_path)).await?;
+
+    if get_manifest().snapshot.dir() {
+        let dir = snap_dir(Path::new(snap_path));
+        // from a previous run
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .await
+                .map_err(|e| BiteError::io("removing", &dir, e))?;
+        }
+        let stats = cow::clone_dir(Path::new(data_path), &dir.join("data"))
+            .await
+            .map_err(|e| BiteError::io(&format!("cloning {data_path} to"), &dir, e))?;
+        info!("📂 uncompressed db in {} ({stats})", dir.display());
+    }
+
+    info!("✅ generated with path {snap_path}");
+    Ok(())
+}
+
+/// Write the snapshot (blocking), returns the size and sha256 of the file. Written in a tmp
+/// file and renamed, so a previous snapshot in `snap_path` (hardlinked to the store) is not
+/// overwritten.
+fn write_snap(
+    data_path: &str,
+    snap_path: &str,
+    codec: Codec,
+    threads: usize,
+) -> io::Result<(u64, String)> {
+    let tmp_path = format!("{snap_path}.tmp");
+    let written = write_snap_to(data_path, &tmp_path, codec, threads);
+    match written {
+        Ok(written) => {
+            std::fs::rename(&tmp_path, snap_path)?;
+            Ok(written)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_snap_to(
+    data_path: &str,
+    snap_path: &str,
+    codec: Codec,
+    threads: usize,
+) -> io::Result<(u64, String)> {
+    let file = BufWriter::new(HashWriter::new(File::create(snap_path)?));
+    let file = match codec {
+        Codec::Gzip => {
+            let encoder = ParGzEncoder::new(file, Compression::fast(), threads);
+            tar_data(encoder, data_path)?.finish()?
+        }
+        Codec::Zstd => {
+            let mut encoder = zstd::Encoder::new(file, ZSTD_LEVEL)?;
+            encoder.multithread(threads as u32)?;
+            tar_data(encoder, data_path)?.finish()?
+        }
+        Codec::None => tar_data(file, data_path)?,
+    };
+    let hashed = file.into_inner().map_err(|e| e.into_error())?;
+    hashed.inner.sync_all()?;
+    Ok((hashed.size, hex::encode(hashed.hasher.finalize())))
+}
+
+fn tar_data<W: Write>(writer: W, data_path: &str) -> io::Result<W> {
+    let mut archive = Builder::new(writer);
+    archive.append_dir_all("data", data_path)?;
+    archive.into_inner()
+}
+
+/// Extract the snapshot (any codec) in `dest` (the node dir, the snapshot contains the `data` dir).
+pub async fn extract_snap(snap_path: &Path, dest: &Path) -> Result<(), anyhow::Error> {
+    info!(
+        "📦 extracting {} in {}",
+        snap_path.display(),
+        dest.display()
+    );
+    let (snap, to) = (snap_path.to_path_buf(), dest.to_path_buf());
+    tokio::task::spawn_blocking(move || -> io::Result<()> {
+        let reader = BufReader::new(File::open(&snap)?);
+        std::fs::create_dir_all(&to)?;
+        match Codec::detect(&snap)? {
+            Codec::Gzip => Archive::new(GzDecoder::new(reader)).unpack(&to),
+            Codec::Zstd => Archive::new(zstd::Decoder::with_buffer(reader)?).unpack(&to),
+            Codec::None => Archive::new(reader).unpack(&to),
+        }
+    })
+    .await?
+    .map_err(|e| {
+        BiteError::Snapshot(format!(
+            "Error extracting snapshot {} in {}: {e}",
+            snap_path.display(),
+            dest.display()
+        ))
+    })?;
+
+    Ok(())
+}
+
+/// Prepare the dbs of the nodes in their dirs (inside `base_dir`), cloning the uncompressed
+/// dbs or extracting the snapshots that zombienet can't load (not gzip), and remove those
+/// snapshots from the network config. Returns the config to spawn.
+pub async fn prepare_spawn_config(
+    config_file: &str,
+    base_dir: &str,
+) -> Result<String, anyhow::Error> {
+    let content = fs::read_to_string(config_file)
+        .await
+        .map_err(|e| BiteError::io("reading config", config_file, e))?;
+    let mut config: toml::Value = toml::from_str(&content)
+        .map_err(|e| BiteError::Config(format!("invalid config {config_file}: {e}")))?;
+
+    let mut nodes = vec![];
+    for_each_chain(&mut config, |chain, nodes_key| {
+        nodes.extend(take_node_snaps(chain, nodes_key))
+    });
+    let prepared = try_join_all(
+        nodes
+            .iter()
+            .map(|(node, snap)| prepare_node(node, snap, Path::new(base_dir))),
+    )
+    .await?;
+
+    // (node, snapshot) to be loaded by zombienet
+    let total = nodes.len();
+    let to_load: HashMap<String, String> = nodes
+        .into_iter()
+        .zip(prepared)
+        .filter_map(|(node_snap, prepared)| (!prepared).then_some(node_snap))
+        .collect();
+    // the gzip ones are linked where zombienet copies them (skip the copy of each spawn)
+    for snap in to_load.values() {
+        link_in_namespace(snap, Path::new(base_dir)).await;
+    }
+
+    if to_load.len() == total {
+        return Ok(config_file.to_string());
+    }
+
+    for_each_chain(&mut config, |chain, nodes_key| {
+        restore_node_snaps(chain, nodes_key, &to_load)
+    });
+    fs::create_dir_all(base_dir)
+        .await
+        .map_err(|e| BiteError::io("creating", base_dir, e))?;
+    let spawn_config_file = format!("{base_dir}/spawn-config.toml");
+    let spawn_config = toml::to_string(&config)
+        .map_err(|e| BiteError::Config(format!("invalid config {config_file}: {e}")))?;
+    fs::write(&spawn_config_file, spawn_config)
+        .await
+        .map_err(|e| BiteError::io("writing", &spawn_config_file, e))?;
+
+    Ok(spawn_config_file)
+}
+
+/// (chain, snapshot) of the network config, from the `default_db_snapshot` and `db_snapshot`
+/// of the relaychain and parachains.
+fn config_snapshots(config: &toml::Value) -> Vec<(String, String)> {
+    let chains = config
+        .get("relaychain")
+        .map(|relay| (relay, "nodes"))
+        .into_iter()
+        .chain(
+            config
+                .get("parachains")
+                .and_then(|p| p.as_array())
+                .into_iter()
+                .flatten()
+                .map(|para| (para, "collators")),
+        );
+
+    let mut snaps: Vec<(String, String)> = vec![];
+    for (chain, nodes_key) in chains {
+        let chain_name = chain
+            .get("chain")
+            .and_then(|c| c.as_str())
+            .unwrap_or_default();
+        let node_snaps = chain
+            .get(nodes_key)
+            .and_then(|n| n.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node.get("db_snapshot"));
+        for snap in chain
+            .get("default_db_snapshot")
+            .into_iter()
+            .chain(node_snaps)
+            .filter_map(|snap| snap.as_str())
+        {
+            if !snaps.iter().any(|(_, s)| s == snap) {
+                snaps.push((chain_name.to_string(), snap.to_string()));
+            }
+        }
+    }
+    snaps
+}
+
+/// Hardlink the (local) snapshot in the namespace dir with the name used by zombienet
+/// (`<sha256 of the location>.tgz`), so zombienet don't need to copy it. Best effort.
+async fn link_in_namespace(snap: &str, ns_dir: &Path) {
+    if !Path::new(snap).exists() {
+        return;
+    }
+    let ns_snap = ns_dir.join(format!("{}.tgz", hex::encode(Sha256::digest(snap))));
+    if ns_snap.exists() {
+        return;
+    }
+    let linked = match fs::create_dir_all(ns_dir).await {
+        Ok(_) => fs::hard_link(snap, &ns_snap).await,
+        Err(e) => Err(e),
+    };
+    match linked {
+        Ok(_) => trace!("{snap} linked in {}", ns_snap.display()),
+        Err(e) => debug!("{snap} can't be linked in {}: {e}", ns_snap.display()),
+    }
+}
+
+/// Relaychain (`nodes`) and parachains (`collators`) of the network config.
+fn for_each_chain(config: &mut toml::Value, mut f: impl FnMut(&mut toml::Value, &str)) {
+    if let Some(relay) = config.get_mut("relaychain") {
+        f(relay, "nodes");
+    }
+    if let Some(paras) = config.get_mut("parachains").and_then(|p| p.as_array_mut()) {
+        for para in paras {
+            f(para, "collators");
+        }
+    }
+}
+
+fn chain_nodes<'a>(
+    chain: &'a mut toml::Value,
+    nodes_key: &str,
+) -> impl Iterator<Item = &'a mut toml::Table> {
+    chain
+        .get_mut(nodes_key)
+        .and_then(|n| n.as_array_mut())
+        .into_iter()
+        .flatten()
+        .filter_map(|node| node.as_table_mut())
+}
+
+/// Remove the snapshots (node or default one) from the chain config, returns the
+/// (node, snapshot) of each node.
+fn take_node_snaps(chain: &mut toml::Value, nodes_key: &str) -> Vec<(String, String)> {
+    let default_snap = chain
+        .as_table_mut()
+        .and_then(|chain| chain.remove("default_db_snapshot"));
+    let mut snaps = vec![];
+    for node in chain_nodes(chain, nodes_key) {
+        let snap = node.remove("db_snapshot").or_else(|| default_snap.clone());
+        let name = node.get("name").and_then(|n| n.as_str());
+        if let (Some(name), Some(snap)) = (name, snap.as_ref().and_then(|s| s.as_str())) {
+            snaps.push((name.to_string(), snap.to_string()));
+        }
+    }
+    snaps
+}
+
+/// Set back the snapshots (as `db_snapshot`) of the nodes not prepared by us.
+fn restore_node_snaps(chain: &mut toml::Value, nodes_key: &str, snaps: &HashMap<String, String>) {
+    for node in chain_nodes(chain, nodes_key) {
+        let snap = node
+            .get("name")
+            .and_then(|n| n.as_str())
+            .and_then(|name| snaps.get(name));
+        if let Some(snap) = snap {
+            node.insert(String::from("db_snapshot"), snap.clone().into());
+        }
+    }
+}
+
+/// Prepare the db of the node in its dir, cloning the uncompressed db (IFF exist) or extracting
+/// the snapshot if zombienet can't load it. Returns `false` if zombienet should load it.
+async fn prepare_node(node: &str, snap: &str, base_dir: &Path) -> Result<bool, anyhow::Error> {
+    let snap = Path::new(snap);
+    let node_dir = base_dir.join(node);
+    let db_dir = snap_dir(snap).join("data");
+    if db_dir.is_dir() {
+        match cow::clone_dir(&db_dir, &node_dir.join("data")).await {
+            Ok(stats) => {
+                info!("⚡ {node} db cloned from {} ({stats})", db_dir.display());
+                return Ok(true);
+            }
+            Err(e) => {
+                warn!(
+                    "Error cloning {} for {node}, using the snapshot: {e}",
+                    db_dir.display()
+                );
+                let _ = fs::remove_dir_all(node_dir.join("data")).await;
+            }
+        }
+    }
+
+    // urls (or missing files) are handled by zombienet
+    match Codec::detect(snap) {
+        Ok(codec) if codec != Codec::Gzip => {
+            trace!("{node} snapshot {} will be extracted", snap.display());
+            extract_snap(snap, &node_dir).await?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Verify the snapshots referenced by the network config of the step dir (checksums, codec,
+/// chain and database), to catch corrupted or mismatched snapshots before the spawn.
+pub async fn verify(step_dir: &Path) -> Result<Report, BiteError> {
+    let config_file = step_dir.join("config.toml");
+    let content = fs::read_to_string(&config_file)
+        .await
+        .map_err(|e| BiteError::io("reading config", &config_file, e))?;
+    let config: toml::Value = toml::from_str(&content)
+        .map_err(|e| BiteError::Config(format!("invalid config {}: {e}", config_file.display())))?;
+
+    // (snapshot, expected chain)
+    let mut snaps: Vec<(PathBuf, String)> = vec![];
+    for (chain, snap) in config_snapshots(&config) {
+        // same as `localize_config`, the snapshots could be moved with the config
+        let mut path = PathBuf::from(&snap);
+        if !path.exists() {
+            path = step_dir.join(file_name(&path));
+        }
+        if !snaps.iter().any(|(p, _)| p == &path) {
+            snaps.push((path, chain));
+        }
+    }
+
+    let results = join_all(snaps.into_iter().map(|(path, chain)| async move {
+        let manifest = SnapManifest::load(&path).await;
+        tokio::task::spawn_blocking(move || verify_snap(&path, &chain, manifest))
+            .await
+            .unwrap_or_else(|e| {
+                (
+                    Check::new("snapshot", CheckStatus::Fail, e.to_string()),
+                    None,
+                )
+            })
+    }))
+    .await;
+
+    let mut report = Report::default();
+    let mut databases = vec![];
+    for (check, manifest) in results {
+        report.checks.push(check);
+        if let Some(manifest) = manifest {
+            if !databases.contains(&manifest.database) {
+                databases.push(manifest.database);
+            }
+        }
+    }
+    if databases.len() > 1 {
+        report.checks.push(Check::new(
+            "database",
+            CheckStatus::Fail,
+            format!(
+                "snapshots with different databases ({})",
+                databases.join(", ")
+            ),
+        ));
+    }
+
+    Ok(report)
+}
+
+/// Verify the snapshot against its manifest (blocking).
+fn verify_snap(
+    path: &Path,
+    chain: &str,
+    manifest: Result<Option<SnapManifest>, BiteError>,
+) -> (Check, Option<SnapManifest>) {
+    let name = file_name(path);
+    let fail = |detail: String| (Check::new(&name, CheckStatus::Fail, detail), None);
+
+    let size = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return fail(format!("{} not readable: {e}", path.display())),
+    };
+    let codec = match Codec::detect(path) {
+        Ok(codec) => codec,
+        Err(e) => return fail(format!("{} not readable: {e}", path.display())),
+    };
+    let manifest = match manifest {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => {
+            return (
+                Check::new(
+                    &name,
+                    CheckStatus::Warn,
+                    "no manifest (generated by an older version?), checksum not verified",
+                ),
+                None,
+            )
+        }
+        Err(e) => return fail(e.to_string()),
+    };
+
+    if manifest.size != size {
+        return fail(format!("size {size}, expected {}", manifest.size));
+    }
+    if manifest.codec != codec {
+        return fail(format!("{codec} content, expected {}", manifest.codec));
+    }
+    if !chain.is_empty() && manifest.chain != chain {
+        return fail(format!(
+            "snapshot of {}, but used for {chain}",
+            manifest.chain
+        ));
+    }
+    match sha256_file(path) {
+        Ok(sha256) if sha256 == manifest.sha256 => {}
+        Ok(sha256) => return fail(format!("sha256 {sha256}, expected {}", manifest.sha256)),
+        Err(e) => return fail(format!("{} not readable: {e}", path.display())),
+    }
+
+    let block = manifest
+        .block_number
+        .map(|n| format!(" #{n}"))
+        .unwrap_or_default();
+    let version = env!("CARGO_PKG_VERSION");
+    let (status, detail) = if manifest.zombie_bite_version == version {
+        (
+            CheckStatus::Pass,
+            format!(
+                "{}{block} ({}), checksum ok",
+                manifest.chain, manifest.database
+            ),
+        )
+    } else {
+        (
+            CheckStatus::Warn,
+            format!(
+                "{}{block} ({}), checksum ok but generated with zombie-bite {}",
+                manifest.chain, manifest.database, manifest.zombie_bite_version
+            ),
+        )
+    };
+    (Check::new(&name, status, detail), Some(manifest))
+}
+
+/// Gzip encoder that compresses chunks of the input in parallel (as pigz), the output
+/// is a single gzip member (the zombienet loader only reads the first one).
+pub struct ParGzEncoder<W: Write> {
+    inner: W,
+    level: Compression,
+    threads: usize,
+    chunk_size: usize,
+    buf: Vec<u8>,
+    chunks: Vec<Vec<u8>>,
+    crc: crc32fast::Hasher,
+    size: u64,
+    header_written: bool,
+}
+
+impl<W: Write> ParGzEncoder<W> {
+    pub fn new(inner: W, level: Compression, threads: usize) -> Self {
+        Self::with_chunk_size(inner, level, threads, GZIP_CHUNK_SIZE)
+    }
+
+    fn with_chunk_size(inner: W, level: Compression, threads: usize, chunk_size: usize) -> Self {
+        Self {
+            inner,
+            level,
+            threads: threads.max(1),
+            chunk_size,
+            buf: Vec::with_capacity(chunk_size),
+            chunks: vec![],
+            crc: crc32fast::Hasher::new(),
+            size: 0,
+            header_written: false,
+        }
+    }
+
+    /// Compress (in parallel) and write the pending chunks.
+    fn write_chunks(&mut self) -> io::Result<()> {
+        if !self.header_written {
+            // magic, deflate, no flags, no mtime, no extra flags, unknown os
+            self.inner
+                .write_all(&[0x1f, 0x8b, 0x08, 0, 0, 0, 0, 0, 0, 0xff])?;
+            self.header_written = true;
+        }
+
+        let level = self.level;
+        let compressed = std::thread::scope(|s| {
+            let handles: Vec<_> = self
+                .chunks
+                .iter()
+                .map(|chunk| s.spawn(move || deflate_chunk(chunk, level)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| {
+                    h.join()
+                        .unwrap_or_else(|_| Err(io::Error::other("panicked")))
+                })
+                .collect::<io::Result<Vec<_>>>()
+        })?;
+        for chunk in compressed {
+            self.inner.write_all(&chunk)?;
+        }
+        self.chunks.clear();
+        Ok(())
+    }
+
+    /// Write the pending data and the gzip trailer, returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.buf.is_empty() {
+            self.chunks.push(std::mem::take(&mut self.buf));
+        }
+        self.write_chunks()?;
+        // final (empty) block
+        self.inner.write_all(&[0x03, 0x00])?;
+        self.inner
+            .write_all(&self.crc.clone().finalize().to_le_bytes())?;
+        self.inner.write_all(&(self.size as u32).to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ParGzEncoder<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let len = data.len().min(self.chunk_size - self.buf.len());
+        self.buf.extend_from_slice(&data[..len]);
+        self.crc.update(&data[..len]);
+        self.size += len as u64;
+
+        if self.buf.len() == self.chunk_size {
+            let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(self.chunk_size));
+            self.chunks.push(chunk);
+            if self.chunks.len() == self.threads {
+                self.write_chunks()?;
+            }
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Raw deflate of the chunk, ended with a sync flush (byte aligned and not final) to
+/// concatenate the chunks.
+fn deflate_chunk(chunk: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+    let mut compress = Compress::new(level, false);
+    let mut out = Vec::with_capacity(chunk.len() / 2 + 1024);
+    loop {
+        let consumed = compress.total_in() as usize;
+        compress
+            .compress_vec(&chunk[consumed..], &mut out, FlushCompress::Sync)
+            .map_err(io::Error::other)?;
+        // all the input consumed and space left (the flush is completed)
+        if compress.total_in() as usize == chunk.len() && out.len() < out.capacity() {
+            break;
+        }
+        out.reserve(out.capacity().max(64 * 1024));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_data() -> Vec<u8> {
+        (0..200_000_u32)
+            .flat_map(|i| format!("block #{i} 0x{:08x}\n", i.wrapping_mul(2654435761)).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn par_gzip_should_be_a_single_gzip_member() {
+        let data = test_data();
+        let mut encoder = ParGzEncoder::with_chunk_size(vec![], Compression::fast(), 3, 64 * 1024);
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < data.len());
+
+        // same decoder used by zombienet (only reads the first member)
+        let mut decoded = vec![];
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+
+        // empty input is still valid
+        let empty = ParGzEncoder::new(vec![], Compression::fast(), 2)
+            .finish()
+            .unwrap();
+        let mut decoded = vec![];
+        GzDecoder::new(&empty[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn snapshots_should_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("zombie-bite-snap-{}", std::process::id()));
+        let data_dir = dir.join("alice").join("data");
+        std::fs::create_dir_all(data_dir.join("chains")).unwrap();
+        std::fs::write(data_dir.join("chains").join("db"), test_data()).unwrap();
+
+        for codec in Codec::ALL {
+            let snap = dir.join(format!("alice-snap.{}", codec.extension()));
+            assert_eq!(Codec::from_path(&snap), codec);
+            write_snap(
+                &data_dir.to_string_lossy(),
+                &snap.to_string_lossy(),
+                codec,
+                2,
+            )
+            .unwrap();
+            assert_eq!(Codec::detect(&snap).unwrap(), codec);
+
+            let dest = dir.join(format!("extracted-{codec}"));
+            tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(extract_snap(&snap, &dest))
+                .unwrap();
+            assert_eq!(
+                std::fs::read(dest.join("data").join("chains").join("db")).unwrap(),
+                test_data()
+            );
+        }
+        assert_eq!(
+            find_snap(&dir, "alice"),
+            Some(String::from("alice-snap.tgz"))
+        );
+        assert_eq!(find_snap(&dir, "bob"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn verify_should_catch_corrupted_snapshots() {
+        let dir = std::env::temp_dir().join(format!("zombie-bite-verify-{}", std::process::id()));
+        let data_dir = dir.join("alice").join("data");
+        std::fs::create_dir_all(data_dir.join("chains").join("polkadot").join("paritydb")).unwrap();
+        std::fs::write(data_dir.join("chains").join("db"), test_data()).unwrap();
+        let step_dir = dir.join("spawn");
+        std::fs::create_dir_all(&step_dir).unwrap();
+
+        let snap = step_dir.join("alice-polkadot-snap.tgz");
+        let source = SnapSource {
+            block_number: Some(42),
+            ..SnapSource::new("polkadot")
+        };
+        generate_snap(&data_dir.to_string_lossy(), &snap.to_string_lossy(), source)
+            .await
+            .unwrap();
+        let manifest = SnapManifest::load(&snap).await.unwrap().unwrap();
+        assert_eq!(manifest.database, "paritydb");
+        assert_eq!(manifest.block_number, Some(42));
+        assert_eq!(manifest.size, std::fs::metadata(&snap).unwrap().len());
+
+        // snapshot moved with the config (e.g. other machine)
+        let config = |chain: &str| {
+            format!(
+                "[relaychain]\nchain = \"{chain}\"\ndefault_db_snapshot = \"/moved/alice-polkadot-snap.tgz\"\n\n[[relaychain.nodes]]\nname = \"alice\"\n"
+            )
+        };
+        std::fs::write(step_dir.join("config.toml"), config("polkadot")).unwrap();
+        let report = verify(&step_dir).await.unwrap();
+        assert!(report.passed());
+        assert_eq!(report.checks[0].status, CheckStatus::Pass);
+
+        std::fs::write(step_dir.join("config.toml"), config("kusama")).unwrap();
+        assert!(!verify(&step_dir).await.unwrap().passed());
+
+        // corrupted (same size)
+        std::fs::write(step_dir.join("config.toml"), config("polkadot")).unwrap();
+        let mut content = std::fs::read(&snap).unwrap();
+        let last = content.len() - 10;
+        content[last] ^= 0xff;
+        std::fs::write(&snap, content).unwrap();
+        let report = verify(&step_dir).await.unwrap();
+        assert!(!report.passed());
+        assert!(report.checks[0].detail.starts_with("sha256"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn spawn_should_clone_uncompressed_dbs() {
+        let dir = std::env::temp_dir().join(format!("zombie-bite-prepare-{}", std::process::id()));
+        let data_dir = dir.join("data");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("000042.sst"), test_data()).unwrap();
+        let (rc_snap, bob_snap) = (dir.join("polkadot-snap.tgz"), dir.join("bob-snap.tgz"));
+        for snap in [&rc_snap, &bob_snap] {
+            write_snap(
+                &data_dir.to_string_lossy(),
+                &snap.to_string_lossy(),
+                Codec::Gzip,
+                2,
+            )
+            .unwrap();
+        }
+        assert_eq!(snap_dir(&rc_snap), dir.join("polkadot-snap.d"));
+        // only the default one has the uncompressed db
+        cow::clone_dir(&data_dir, &snap_dir(&rc_snap).join("data"))
+            .await
+            .unwrap();
+
+        let config_file = dir.join("config.toml");
+        std::fs::write(
+            &config_file,
+            format!(
+                "[relaychain]\nchain = \"polkadot\"\ndefault_db_snapshot = \"{}\"\n\n[[relaychain.nodes]]\nname = \"alice\"\n\n[[relaychain.nodes]]\nname = \"bob\"\ndb_snapshot = \"{}\"\n",
+                rc_snap.display(),
+                bob_snap.display()
+            ),
+        )
+        .unwrap();
+        let base_dir = dir.join("spawn");
+        let spawn_config =
+            prepare_spawn_config(&config_file.to_string_lossy(), &base_dir.to_string_lossy())
+                .await
+                .unwrap();
+        assert_eq!(
+            PathBuf::from(&spawn_config),
+            base_dir.join("spawn-config.toml")
+        );
+        assert_eq!(
+            std::fs::read(base_dir.join("alice").join("data").join("000042.sst")).unwrap(),
+            test_data()
+        );
+
+        let mut spawn_config: toml::Value =
+            toml::from_str(&std::fs::read_to_string(&spawn_config).unwrap()).unwrap();
+        let relay = spawn_config.get_mut("relaychain").unwrap();
+        assert!(relay.get("default_db_snapshot").is_none());
+        let nodes = take_node_snaps(relay, "nodes");
+        // bob is loaded by zombienet
+        assert_eq!(
+            nodes,
+            vec![(String::from("bob"), bob_snap.to_string_lossy().to_string())]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
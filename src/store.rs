@@ -0,0 +1,435 @@
+//! Content addressed store of the snapshots (`<base_path>/store` by default). The snapshots in
+//! the step dirs are hardlinks (or symlinks if the store is in other device) to the store object
+//! (`<sha256>.<ext>`), so the same snapshot is only stored once. `gc` prunes the objects not
+//! referenced anymore.
+//!
+//! A shared store (e.g. `[snapshot] store`) keeps the base paths using it in `refs/`, so `gc`
+//! from one base path also keeps the objects referenced by the others (the symlinks from other
+//! device don't count as links of the object).
+
+use std::{
+    collections::HashSet,
+    io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+use crate::error::BiteError;
+use crate::manifest::get_manifest;
+use crate::snapshot::{sha256_file, SnapManifest, SNAP_MANIFEST_SUFFIX};
+use crate::utils::move_file;
+
+/// Default store dir (in the base path).
+pub const STORE_DIR: &str = "store";
+/// Dir (in the store) with the base paths using the store.
+const REFS_DIR: &str = "refs";
+/// Max depth (from the base path) to look for snapshots manifests, e.g.
+/// `<base_path>/spawn.0/snapshots/<ts>/alice-snap.tgz.manifest.json`.
+const MAX_REFS_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GcReport {
+    pub removed: Vec<PathBuf>,
+    /// Bytes freed (or to free in dry run)
+    pub freed: u64,
+    pub kept: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Store {
+    dir: PathBuf,
+    /// Base path using the store (registered in `refs/` on `put`)
+    base_path: Option<PathBuf>,
+}
+
+impl Store {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            base_path: None,
+        }
+    }
+
+    /// Store of the base path (from the manifest / env or the default one).
+    pub fn for_base_path(base_path: &Path) -> Self {
+        let store = match get_manifest().snapshot.store() {
+            Some(dir) => Self::new(dir),
+            None => Self::new(base_path.join(STORE_DIR)),
+        };
+        store.with_base_path(base_path)
+    }
+
+    pub fn with_base_path(mut self, base_path: &Path) -> Self {
+        self.base_path = Some(base_path.to_path_buf());
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn object_path(&self, manifest: &SnapManifest) -> PathBuf {
+        self.dir.join(format!(
+            "{}.{}",
+            manifest.sha256,
+            manifest.codec.extension()
+        ))
+    }
+
+    /// Move the snapshot to the store (IFF is not already there) and link it back.
+    pub async fn put(&self, snap_path: &Path) -> Result<(), anyhow::Error> {
+        let manifest = SnapManifest::load(snap_path).await?.ok_or_else(|| {
+            BiteError::Snapshot(format!(
+                "snapshot {} without manifest, can't be stored",
+                snap_path.display()
+            ))
+        })?;
+        fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| BiteError::io("creating", &self.dir, e))?;
+        self.register().await?;
+
+        let object = self.object_path(&manifest);
+        if fs::try_exists(&object).await.unwrap_or_default()
+            && !object_matches(&object, &manifest.sha256).await
+        {
+            warn!(
+                "store object {} doesn't match its sha256, replacing it",
+                object.display()
+            );
+            fs::remove_file(&object)
+                .await
+                .map_err(|e| BiteError::io("removing", &object, e))?;
+        }
+        if fs::try_exists(&object).await.unwrap_or_default() {
+            info!(
+                "♻️  {} already in the store ({})",
+                snap_path.display(),
+                manifest.sha256
+            );
+            fs::remove_file(snap_path)
+                .await
+                .map_err(|e| BiteError::io("removing", snap_path, e))?;
+        } else {
+            move_file(snap_path, &object).await?;
+        }
+
+        link(&object, snap_path).await.map_err(|e| {
+            BiteError::io(&format!("linking {} to", object.display()), snap_path, e)
+        })?;
+        debug!("{} -> {}", snap_path.display(), object.display());
+        Ok(())
+    }
+
+    /// Register the base path in `refs/` (IFF not already there).
+    async fn register(&self) -> Result<(), BiteError> {
+        let Some(base_path) = &self.base_path else {
+            return Ok(());
+        };
+        let base_path = fs::canonicalize(base_path)
+            .await
+            .unwrap_or_else(|_| base_path.clone());
+        let base_path = base_path.to_string_lossy().to_string();
+        let refs_dir = self.dir.join(REFS_DIR);
+        let ref_file = refs_dir.join(&hex::encode(Sha256::digest(&base_path))[..16]);
+        if fs::try_exists(&ref_file).await.unwrap_or_default() {
+            return Ok(());
+        }
+        fs::create_dir_all(&refs_dir)
+            .await
+            .map_err(|e| BiteError::io("creating", &refs_dir, e))?;
+        fs::write(&ref_file, base_path)
+            .await
+            .map_err(|e| BiteError::io("writing", &ref_file, e))
+    }
+
+    /// Base paths registered in `refs/` (the ones removed are unregistered, unless `dry_run`).
+    async fn registered_base_paths(&self, dry_run: bool) -> Vec<PathBuf> {
+        let mut base_paths = vec![];
+        let Ok(mut entries) = fs::read_dir(self.dir.join(REFS_DIR)).await else {
+            return base_paths;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(base_path) = fs::read_to_string(entry.path()).await else {
+                continue;
+            };
+            let base_path = PathBuf::from(base_path);
+            if fs::try_exists(&base_path).await.unwrap_or_default() {
+                base_paths.push(base_path);
+            } else if !dry_run {
+                debug!("{} removed, unregistering it", base_path.display());
+                let _ = fs::remove_file(entry.path()).await;
+            }
+        }
+        base_paths
+    }
+
+    /// Remove the objects not referenced by the snapshots in `base_path` nor in the other base
+    /// paths using the store (or hardlinked from other place).
+    pub async fn gc(&self, base_path: &Path, dry_run: bool) -> Result<GcReport, BiteError> {
+        let mut referenced = referenced_hashes(base_path, &self.dir).await;
+        for other in self.registered_base_paths(dry_run).await {
+            referenced.extend(referenced_hashes(&other, &self.dir).await);
+        }
+        let mut report = GcReport::default();
+
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => return Err(BiteError::io("reading", &self.dir, e)),
+        };
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| BiteError::io("reading", &self.dir, e))?
+        {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let hash = name.split('.').next().unwrap_or_default();
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            // e.g. refs/
+            if !metadata.is_file() {
+                continue;
+            }
+            if referenced.contains(hash) || metadata.nlink() > 1 {
+                report.kept += 1;
+                continue;
+            }
+
+            if !dry_run {
+                fs::remove_file(&path)
+                    .await
+                    .map_err(|e| BiteError::io("removing", &path, e))?;
+            }
+            report.freed += metadata.len();
+            report.removed.push(path);
+        }
+
+        Ok(report)
+    }
+}
+
+/// The content of the object (still) has the sha256 of its name.
+async fn object_matches(object: &Path, sha256: &str) -> bool {
+    let path = object.to_path_buf();
+    tokio::task::spawn_blocking(move || sha256_file(&path))
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .is_some_and(|hash| hash == sha256)
+}
+
+/// Hardlink the object, or symlink if the store is in other device.
+async fn link(object: &Path, dest: &Path) -> io::Result<()> {
+    match fs::hard_link(object, dest).await {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let object = fs::canonicalize(object).await?;
+            fs::symlink(object, dest).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Sha256 of the snapshots (with manifest) in the base path (any step dir).
+async fn referenced_hashes(base_path: &Path, store_dir: &Path) -> HashSet<String> {
+    let mut hashes = HashSet::new();
+    let mut dirs = vec![(base_path.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        let Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let is_dir = entry
+                .file_type()
+                .await
+                .map(|t| t.is_dir())
+                .unwrap_or_default();
+            if is_dir {
+                if depth < MAX_REFS_DEPTH && path != store_dir {
+                    dirs.push((path, depth + 1));
+                }
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(snap) = name.strip_suffix(SNAP_MANIFEST_SUFFIX) else {
+                continue;
+            };
+            // only if the snapshot is still there
+            let snap_path = dir.join(snap);
+            if !fs::try_exists(&snap_path).await.unwrap_or_default() {
+                continue;
+            }
+            if let Ok(Some(manifest)) = SnapManifest::load(&snap_path).await {
+                hashes.insert(manifest.sha256);
+            }
+        }
+    }
+    hashes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::snapshot::{generate_snap, SnapSource};
+
+    #[tokio::test]
+    async fn store_should_dedup_and_gc() {
+        let base_path =
+            std::env::temp_dir().join(format!("zombie-bite-store-{}", std::process::id()));
+        let data_dir = base_path.join("data");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("db"), b"some state").unwrap();
+        let store = Store::new(base_path.join(STORE_DIR));
+
+        // same snapshot in two steps
+        let mut snaps = vec![];
+        for step in ["spawn", "spawn.0"] {
+            let step_dir = base_path.join(step);
+            std::fs::create_dir_all(&step_dir).unwrap();
+            let snap = step_dir.join("asset-hub-polkadot-snap.tar");
+            generate_snap(
+                &data_dir.to_string_lossy(),
+                &snap.to_string_lossy(),
+                SnapSource::new("asset-hub-polkadot"),
+            )
+            .await
+            .unwrap();
+            store.put(&snap).await.unwrap();
+            snaps.push(snap);
+        }
+
+        let objects: Vec<_> = std::fs::read_dir(store.dir()).unwrap().flatten().collect();
+        assert_eq!(objects.len(), 1);
+        // the object and the two links
+        assert_eq!(objects[0].metadata().unwrap().nlink(), 3);
+        assert_eq!(
+            std::fs::read(&snaps[0]).unwrap(),
+            std::fs::read(&snaps[1]).unwrap()
+        );
+
+        // still referenced by spawn.0
+        std::fs::remove_dir_all(base_path.join("spawn")).unwrap();
+        let report = store.gc(&base_path, false).await.unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(report.kept, 1);
+
+        std::fs::remove_dir_all(base_path.join("spawn.0")).unwrap();
+        let report = store.gc(&base_path, true).await.unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert!(report.freed > 0);
+        // dry run
+        assert!(report.removed[0].exists());
+        store.gc(&base_path, false).await.unwrap();
+        assert!(!report.removed[0].exists());
+
+        std::fs::remove_dir_all(&base_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn regenerated_snapshots_should_not_corrupt_the_store() {
+        let base_path =
+            std::env::temp_dir().join(format!("zombie-bite-store-regen-{}", std::process::id()));
+        let (data_dir, other_data_dir) = (base_path.join("data"), base_path.join("other"));
+        let step_dir = base_path.join("spawn");
+        for (dir, state) in [(&data_dir, "some state"), (&other_data_dir, "other state")] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("db"), state).unwrap();
+        }
+        std::fs::create_dir_all(&step_dir).unwrap();
+        let store = Store::new(base_path.join(STORE_DIR));
+        let generate = |data_dir: &PathBuf, snap: PathBuf| {
+            let data_dir = data_dir.clone();
+            async move {
+                generate_snap(
+                    &data_dir.to_string_lossy(),
+                    &snap.to_string_lossy(),
+                    SnapSource::new("polkadot"),
+                )
+                .await
+                .unwrap();
+            }
+        };
+
+        let snap = step_dir.join("polkadot-snap.tar");
+        generate(&data_dir, snap.clone()).await;
+        store.put(&snap).await.unwrap();
+        let manifest = SnapManifest::load(&snap).await.unwrap().unwrap();
+        let object = store.object_path(&manifest);
+        let content = std::fs::read(&object).unwrap();
+
+        // regenerated in the same path (e.g. resume), the object is not overwritten
+        generate(&other_data_dir, snap.clone()).await;
+        assert_eq!(std::fs::read(&object).unwrap(), content);
+        assert!(object_matches(&object, &manifest.sha256).await);
+
+        // corrupted object is replaced
+        std::fs::remove_file(&object).unwrap();
+        std::fs::write(&object, b"corrupted").unwrap();
+        let snap = base_path.join("spawn.0-polkadot-snap.tar");
+        generate(&data_dir, snap.clone()).await;
+        assert_eq!(
+            SnapManifest::load(&snap).await.unwrap().unwrap().sha256,
+            manifest.sha256
+        );
+        store.put(&snap).await.unwrap();
+        assert!(object_matches(&object, &manifest.sha256).await);
+
+        std::fs::remove_dir_all(&base_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gc_should_keep_objects_of_other_base_paths() {
+        let dir =
+            std::env::temp_dir().join(format!("zombie-bite-store-shared-{}", std::process::id()));
+        let (base_a, base_b) = (dir.join("a"), dir.join("b"));
+        let data_dir = dir.join("data");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("db"), b"some state").unwrap();
+
+        // snapshot of `b` in the shared store
+        let store_dir = dir.join("shared-store");
+        let step_dir = base_b.join("bite");
+        std::fs::create_dir_all(&step_dir).unwrap();
+        std::fs::create_dir_all(&base_a).unwrap();
+        let snap = step_dir.join("polkadot-snap.tar");
+        generate_snap(
+            &data_dir.to_string_lossy(),
+            &snap.to_string_lossy(),
+            SnapSource::new("polkadot"),
+        )
+        .await
+        .unwrap();
+        let store = Store::new(&store_dir).with_base_path(&base_b);
+        store.put(&snap).await.unwrap();
+        // as if the store was in other device (symlink)
+        let object = store.object_path(&SnapManifest::load(&snap).await.unwrap().unwrap());
+        std::fs::remove_file(&snap).unwrap();
+        std::os::unix::fs::symlink(&object, &snap).unwrap();
+
+        // gc from `a` keeps the object used by `b`
+        let store_a = Store::new(&store_dir).with_base_path(&base_a);
+        let report = store_a.gc(&base_a, false).await.unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(report.kept, 1);
+
+        // until `b` is removed
+        std::fs::remove_dir_all(&base_b).unwrap();
+        let report = store_a.gc(&base_a, false).await.unwrap();
+        assert_eq!(report.removed, vec![object]);
+        assert_eq!(
+            std::fs::read_dir(store_dir.join(REFS_DIR)).unwrap().count(),
+            0
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
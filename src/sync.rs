@@ -2,37 +2,47 @@
 // TODO: don't allow dead_code
 
 use std::{
-    io::{self, Cursor, Write},
-    path::PathBuf,
-    time::Duration,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use crate::config::get_state_pruning_config;
-use crate::utils::get_random_port;
+use crate::config::{get_state_pruning_config, DEFAULT_EPOCH_DURATION};
+use crate::error::BiteError;
+use crate::manifest::get_manifest;
+use crate::metrics;
+use crate::progress::{self, RateTracker, SyncPhase, SyncProgress};
+use crate::utils::{get_random_port, LogTail};
 
+use anyhow::anyhow;
 use reqwest::Url;
 use tracing::{debug, info, trace};
 use zombienet_orchestrator::metrics::{Metrics, MetricsHelper};
 use zombienet_provider::{types::SpawnNodeOptions, DynNamespace, DynNode};
 use zombienet_support::net::wait_ws_ready;
 
+/// Max consecutive errors (one per sec) fetching the sync node metrics.
+const MAX_METRICS_ERRORS: u32 = 30;
+
 const PASEO_ASSET_HUB_SPEC_URL: &str =
     "https://paseo-r2.zondax.ch/chain-specs/paseo-asset-hub.json";
 
+#[allow(clippy::too_many_arguments)]
 pub async fn sync_relay_only(
     ns: DynNamespace,
     cmd: impl AsRef<str>,
     chain: impl AsRef<str>,
+    epoch_duration: u64,
     para_heads_env: Vec<(String, String)>,
     overrides_path: PathBuf,
     info_path: impl AsRef<str>,
     maybe_target_header: Option<String>,
     database: &str,
-) -> Result<(DynNode, String, String), ()> {
+) -> Result<(DynNode, String, String), anyhow::Error> {
     debug!("paras: \n {:?}", para_heads_env);
     let sync_db_path = format!("{}/sync-db", ns.base_dir().to_string_lossy());
 
-    let mut env = if std::env::var("ZOMBIE_DUMP").is_ok() {
+    let mut env = if get_manifest().dump() {
         [
             para_heads_env,
             vec![("ZOMBIE_DUMP".to_string(), "1".to_string())],
@@ -50,8 +60,11 @@ pub async fn sync_relay_only(
     env.push(("ZOMBIE_RC_OVERRIDES_PATH".to_string(), rc_overrides_path));
     env.push(("RUST_LOG".into(), "doppelganger=debug".into()));
     env.push(("ZOMBIE_INFO_PATH".into(), info_path.as_ref().into()));
-    if chain.as_ref() == "paseo" || chain.as_ref() == "kusama" {
-        env.push(("ZOMBIE_RC_EPOCH_DURATION".into(), "600".into()));
+    if epoch_duration != DEFAULT_EPOCH_DURATION {
+        env.push((
+            "ZOMBIE_RC_EPOCH_DURATION".into(),
+            epoch_duration.to_string(),
+        ));
     }
 
     trace!("env: {env:?}");
@@ -77,31 +90,30 @@ pub async fn sync_relay_only(
         .env(env);
 
     info!("🔎 sync node opts: {:?}", opts);
-    let sync_node = ns.spawn_node(&opts).await.unwrap();
+    let sync_node = spawn_sync_node(&ns, &opts, chain.as_ref()).await?;
     let metrics_url = format!("http://127.0.0.1:{metrics_random_port}/metrics");
 
     debug!("prometheus link http://127.0.0.1:{metrics_random_port}/metrics");
     info!("📓 sync node logs: {}", sync_node.log_cmd());
 
-    wait_ws_ready(&metrics_url).await.unwrap();
-    let url = reqwest::Url::try_from(metrics_url.as_str()).unwrap();
-    wait_sync(url).await.unwrap();
+    wait_synced(&metrics_url, chain.as_ref(), sync_node.log_path()).await?;
     info!("✅ Synced (chain: {})", chain.as_ref());
     // we should just paused
     Ok((sync_node, sync_db_path, chain.as_ref().to_string()))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn sync_para(
     ns: DynNamespace,
     cmd: impl AsRef<str>,
     chain: impl AsRef<str>,
     relaychain: impl AsRef<str>,
-    relaychain_endpoint: impl AsRef<str>,
+    relaychain_endpoints: Vec<String>,
     overrides_path: PathBuf,
     info_path: impl AsRef<str>,
     maybe_target_header: Option<String>,
     database: &str,
-) -> Result<(DynNode, String, String, String), ()> {
+) -> Result<(DynNode, String, String, String), anyhow::Error> {
     let sync_db_path = format!(
         "{}/paras/{}/sync-db",
         ns.base_dir().to_string_lossy(),
@@ -116,7 +128,7 @@ pub async fn sync_para(
 
     let rpc_random_port = get_random_port().await;
     let metrics_random_port = get_random_port().await;
-    let mut env = if std::env::var("ZOMBIE_DUMP").is_ok() {
+    let mut env = if get_manifest().dump() {
         vec![("ZOMBIE_DUMP", "1")]
     } else {
         vec![]
@@ -130,63 +142,67 @@ pub async fn sync_para(
     env.push(("ZOMBIE_PARA_OVERRIDES_PATH", &para_overrides_path));
     env.push(("ZOMBIE_PARA_HEAD_PATH", &para_head_path));
     env.push(("RUST_LOG", "doppelganger=debug"));
-    env.push(("ZOMBIE_INFO_PATH".into(), info_path.as_ref().into()));
+    env.push(("ZOMBIE_INFO_PATH", info_path.as_ref()));
 
     trace!("env: {env:?}");
 
     let dest_for_paseo = format!("{}/asset-hub-paseo.json", ns.base_dir().to_string_lossy(),);
     let chain_arg = if chain.as_ref() == "asset-hub-paseo" {
         // get chain spec from https://paseo-r2.zondax.ch/chain-specs/paseo-asset-hub.json
-        let response = reqwest::get(PASEO_ASSET_HUB_SPEC_URL)
-            .await
-            .expect(&format!(
-                "Download paseo-asset-hub.json from {PASEO_ASSET_HUB_SPEC_URL} should work."
-            ));
+        let bytes = download_spec(PASEO_ASSET_HUB_SPEC_URL).await?;
         let mut file = std::fs::File::create(&dest_for_paseo)
-            .expect(&format!("Create file {dest_for_paseo} should work"));
-        let mut content = Cursor::new(response.bytes().await.expect("Create cursor should works."));
-        std::io::copy(&mut content, &mut file).expect("Copy bytes should works.");
+            .map_err(|e| BiteError::io("creating", &dest_for_paseo, e))?;
+        std::io::copy(&mut Cursor::new(bytes), &mut file)
+            .map_err(|e| BiteError::io("writing", &dest_for_paseo, e))?;
         dest_for_paseo.as_str()
     } else {
         chain.as_ref()
     };
 
-    let opts = SpawnNodeOptions::new("sync-node-para", cmd.as_ref())
-        .args(vec![
-            "--chain",
-            chain_arg,
-            "--sync",
-            "warp",
-            "-d",
-            &sync_db_path,
-            "--rpc-port",
-            &rpc_random_port.to_string(),
-            "--prometheus-port",
-            &metrics_random_port.to_string(),
-            "--relay-chain-rpc-url",
-            relaychain_endpoint.as_ref(),
-            "--no-hardware-benchmarks",
-            // needed to not drop the pre-migration state
-            "--state-pruning",
-            get_state_pruning_config().as_str(),
-            "--database",
-            database,
-            "--",
-            "--chain",
-            relaychain.as_ref(),
-        ])
+    let rpc_port = rpc_random_port.to_string();
+    let metrics_port = metrics_random_port.to_string();
+    let state_pruning = get_state_pruning_config();
+    let mut args = vec![
+        "--chain",
+        chain_arg,
+        "--sync",
+        "warp",
+        "-d",
+        &sync_db_path,
+        "--rpc-port",
+        &rpc_port,
+        "--prometheus-port",
+        &metrics_port,
+    ];
+    // the collator will failover to the next url if the current one is not available
+    for endpoint in &relaychain_endpoints {
+        args.extend(["--relay-chain-rpc-url", endpoint.as_str()]);
+    }
+    args.extend([
+        "--no-hardware-benchmarks",
+        // needed to not drop the pre-migration state
+        "--state-pruning",
+        state_pruning.as_str(),
+        "--database",
+        database,
+        "--",
+        "--chain",
+        relaychain.as_ref(),
+    ]);
+
+    let sync_node_name = format!("sync-node-{}", chain.as_ref());
+    let opts = SpawnNodeOptions::new(sync_node_name.as_str(), cmd.as_ref())
+        .args(args)
         .env(env);
 
     info!("🔎 sync para opts: {:?}", opts);
-    let sync_node = ns.spawn_node(&opts).await.unwrap();
+    let sync_node = spawn_sync_node(&ns, &opts, chain.as_ref()).await?;
     let metrics_url = format!("http://127.0.0.1:{metrics_random_port}/metrics");
 
     debug!("prometheus link http://127.0.0.1:{metrics_random_port}/metrics");
     info!("📓 sync para logs: {}", sync_node.log_cmd());
 
-    wait_ws_ready(&metrics_url).await.unwrap();
-    let url = reqwest::Url::try_from(metrics_url.as_str()).unwrap();
-    wait_sync(url).await.unwrap();
+    wait_synced(&metrics_url, chain.as_ref(), sync_node.log_path()).await?;
     info!("✅ Synced (chain: {}), stopping node.", chain.as_ref());
     // we should just paused
     // sync_node.destroy().await.unwrap();
@@ -199,42 +215,168 @@ pub async fn sync_para(
     ))
 }
 
-// TODO: FIX terminal output on multiple tasks
-async fn wait_sync(url: impl Into<Url>) -> Result<(), anyhow::Error> {
-    const TERMINAL_WIDTH: u32 = 80;
+async fn download_spec(url: &str) -> Result<Vec<u8>, BiteError> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| BiteError::Spec(format!("Error downloading chain-spec from {url}: {e}")))?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| BiteError::Spec(format!("Error downloading chain-spec from {url}: {e}")))?;
+    Ok(bytes.to_vec())
+}
+
+async fn spawn_sync_node(
+    ns: &DynNamespace,
+    opts: &SpawnNodeOptions,
+    chain: &str,
+) -> Result<DynNode, BiteError> {
+    ns.spawn_node(opts).await.map_err(|e| {
+        BiteError::Sync(format!(
+            "Error spawning sync node ({}) for {chain}: {e}",
+            opts.program
+        ))
+    })
+}
+
+/// Wait until the node metrics are available and the node is synced.
+async fn wait_synced(metrics_url: &str, chain: &str, log_path: &Path) -> Result<(), BiteError> {
+    wait_ws_ready(metrics_url).await.map_err(|e| {
+        BiteError::Sync(format!(
+            "sync node for {chain} not ready (metrics at {metrics_url}): {e}"
+        ))
+    })?;
+    let url = reqwest::Url::try_from(metrics_url)
+        .map_err(|e| BiteError::Sync(format!("invalid metrics url {metrics_url}: {e}")))?;
+    let sync = &get_manifest().sync;
+    let limits = SyncLimits {
+        timeout: sync.timeout().map(Duration::from_secs),
+        stall_timeout: Duration::from_secs(sync.stall_timeout()),
+    };
+    let result = wait_sync(url, chain, log_path, &limits).await;
+    metrics::set(metrics::SYNCING, &[("chain", chain)], 0_f64);
+    result.map_err(|e| BiteError::Sync(format!("Error syncing {chain}: {e}")))
+}
+
+#[derive(Debug, Clone)]
+struct SyncLimits {
+    timeout: Option<Duration>,
+    stall_timeout: Duration,
+}
+
+/// Wait until the node is synced, reporting the progress (from the metrics and the node logs).
+/// Fails if the metrics are not available (e.g. the node crashed), the sync stalls or the timeout is reached.
+async fn wait_sync(
+    url: impl Into<Url>,
+    chain: &str,
+    log_path: &Path,
+    limits: &SyncLimits,
+) -> Result<(), anyhow::Error> {
     let url = url.into();
+    let mut tracker = RateTracker::default();
+    let mut logs = LogTail::new(log_path);
+    let mut phase = None;
+    let mut warp_progress = None;
+    let started = Instant::now();
+    let mut last_progress = (Instant::now(), None);
+    let mut metrics_errors = 0;
 
-    print!("Syncing");
-    let mut q = TERMINAL_WIDTH;
-    // remove the first message
-    q -= 7;
-
-    while is_syncing(url.clone()).await {
-        if q == 0 {
-            print!("\x1b[2K"); // Clear the whole line
-            print!("\x1b[80D"); // Move to the start of the line
-            print!("Syncing");
-            io::stdout().flush().unwrap();
-            q = TERMINAL_WIDTH - 7;
+    metrics::set(metrics::SYNCING, &[("chain", chain)], 1_f64);
+    let best = loop {
+        let now = Instant::now();
+        if let Some(timeout) = limits.timeout {
+            if now.duration_since(started) > timeout {
+                return Err(anyhow!("not synced in {}s (timeout)", timeout.as_secs()));
+            }
         }
-        print!(".");
-        io::stdout().flush().unwrap();
-        q -= 1;
-        tokio::time::sleep(Duration::from_secs(1)).await;
-    }
 
-    // ensure new line
-    println!();
+        let syncing = match is_syncing(url.clone()).await {
+            Ok(syncing) => {
+                metrics_errors = 0;
+                syncing
+            }
+            Err(e) => {
+                metrics_errors += 1;
+                if metrics_errors >= MAX_METRICS_ERRORS {
+                    return Err(anyhow!(
+                        "metrics of the sync node not available ({e}), the node may have crashed"
+                    ));
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let best = sync_metric(&url, "block_height{status=\"best\"}").await;
+        // not syncing before importing any block means the sync didn't start yet
+        if !syncing && best > 0 {
+            break best;
+        }
+
+        let target = sync_metric(&url, "block_height{status=\"sync_target\"}").await;
+        let peers = sync_metric(&url, "sub_libp2p_peers_count").await;
+        if let Ok(new_logs) = logs.read_new().await {
+            phase = SyncPhase::from_logs(&new_logs).or(phase);
+            if let Some(progress) = new_logs.lines().rev().find_map(SyncPhase::warp_progress) {
+                warp_progress = Some(progress.to_string());
+            }
+        }
+        let (rate, eta) = tracker.sample(now, best, target);
 
+        metrics::set(metrics::SYNC_BEST_BLOCK, &[("chain", chain)], best as f64);
+        metrics::set(
+            metrics::SYNC_TARGET_BLOCK,
+            &[("chain", chain)],
+            target as f64,
+        );
+        let phase = match phase {
+            Some(phase) => phase,
+            None if peers == 0 => SyncPhase::Connecting,
+            None => SyncPhase::Syncing,
+        };
+        progress::report(SyncProgress {
+            chain: chain.to_string(),
+            phase,
+            best,
+            target,
+            rate,
+            eta,
+        });
+
+        // stall detection
+        let marker = Some((best, phase, warp_progress.clone()));
+        if last_progress.1 != marker {
+            last_progress = (now, marker);
+        } else if now.duration_since(last_progress.0) > limits.stall_timeout {
+            return Err(anyhow!(
+                "sync stalled, no progress in the last {}s (best #{best}, {phase})",
+                limits.stall_timeout.as_secs()
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    };
+
+    progress::report(SyncProgress {
+        chain: chain.to_string(),
+        phase: SyncPhase::Synced,
+        best,
+        target: best,
+        rate: None,
+        eta: None,
+    });
     Ok(())
 }
 
-async fn is_syncing(url: Url) -> bool {
-    let metric = Metrics::metric_with_url("substrate_sub_libp2p_is_major_syncing", url).await;
-    if let Ok(m) = metric {
-        m == 1_f64
-    } else {
-        // Error getting metric
-        false
-    }
+/// Value of the metric (0 if not available).
+async fn sync_metric(url: &Url, name: &str) -> u64 {
+    Metrics::metric_with_url(name, url.clone())
+        .await
+        .unwrap_or_default() as u64
+}
+
+/// If the node is major syncing, errors fetching the metrics are returned (not handled as synced).
+async fn is_syncing(url: Url) -> Result<bool, anyhow::Error> {
+    let metric = Metrics::metric_with_url("substrate_sub_libp2p_is_major_syncing", url).await?;
+    Ok(metric == 1_f64)
 }
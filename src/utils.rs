@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 // TODO: don't allow dead_code
 
+use std::future::Future;
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use serde::de::DeserializeOwned;
@@ -10,11 +13,14 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sp_core::bytes;
 use tokio::fs::{self, File};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::net::TcpListener;
 
+use crate::error::BiteError;
+
 use codec::{CompactAs, Decode, Encode, MaxEncodedLen};
-use tracing::trace;
+use tracing::{trace, warn};
+use zombienet_sdk::subxt::Metadata;
 
 /// Parachain id.
 ///
@@ -154,14 +160,16 @@ pub fn para_id_hash(para_id: u32) -> String {
 
 pub async fn localize_config(config_path: impl AsRef<str>) -> Result<(), anyhow::Error> {
     let config_path = PathBuf::from_str(config_path.as_ref())?;
-    let base_path = config_path.parent().unwrap();
+    let base_path = config_path
+        .parent()
+        .ok_or_else(|| BiteError::Config(format!("invalid config path {config_path:?}")))?;
 
     let mut localized = false;
 
     // read config
     let config_content = fs::read_to_string(&config_path)
         .await
-        .expect("read config should works");
+        .map_err(|e| BiteError::io("reading config", &config_path, e))?;
     let mut config_modified = vec![];
     for line in config_content.lines() {
         match line {
@@ -171,7 +179,7 @@ pub async fn localize_config(config_path: impl AsRef<str>) -> Result<(), anyhow:
             {
                 let parts: Vec<&str> = l.split("=").collect();
                 let value_as_path = PathBuf::from_str(parts.last().unwrap())
-                    .expect(&format!("value {:?} should be a valid path", parts.last()));
+                    .unwrap_or_else(|_| panic!("value {:?} should be a valid path", parts.last()));
                 let maybe_mod_line = if let Ok(false) = fs::try_exists(&value_as_path).await {
                     // localize!
                     localized = true;
@@ -201,46 +209,287 @@ pub async fn localize_config(config_path: impl AsRef<str>) -> Result<(), anyhow:
 
     if localized {
         // rename original
-        fs::rename(
+        move_file(
             &config_path,
             &format!("{}/original-config.toml", &base_path.to_string_lossy()),
         )
-        .await
-        .expect("rename should works");
+        .await?;
         fs::write(&config_path, config_modified.join("\n"))
             .await
-            .expect("write should works");
+            .map_err(|e| BiteError::io("writing config", &config_path, e))?;
     }
 
     Ok(())
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct GetBlockHashRpcResponse {
-    id: u32,
-    result: String // result contains only the hash
+/// Incremental reader of a (log) file, returns only the content added since the last read.
+#[derive(Debug, Clone)]
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl LogTail {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            offset: 0,
+        }
+    }
+
+    pub async fn read_new(&mut self) -> Result<String, anyhow::Error> {
+        let mut file = File::open(&self.path).await?;
+        let len = file.metadata().await?.len();
+        if len < self.offset {
+            // truncated / rotated
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset)).await?;
+        let mut content = vec![];
+        file.read_to_end(&mut content).await?;
+        self.offset += content.len() as u64;
+        Ok(String::from_utf8_lossy(&content).to_string())
+    }
+}
+
+/// Move the file, falling back to copy + remove if `from` and `to` are in different devices.
+pub async fn move_file(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), BiteError> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    match fs::rename(from, to).await {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            trace!(
+                "{} and {} are in different devices, copying",
+                from.display(),
+                to.display()
+            );
+            fs::copy(from, to)
+                .await
+                .map_err(|e| BiteError::io(&format!("copying {} to", from.display()), to, e))?;
+            fs::remove_file(from)
+                .await
+                .map_err(|e| BiteError::io("removing", from, e))
+        }
+        Err(e) => Err(BiteError::io(
+            &format!("moving {} to", from.display()),
+            to,
+            e,
+        )),
+    }
+}
+
+/// Http(s) url to use for rpc calls (our endpoints are set as ws(s)).
+pub fn as_http_url(endpoint: &str) -> String {
+    if let Some(rest) = endpoint.strip_prefix("wss://") {
+        format!("https://{rest}")
+    } else if let Some(rest) = endpoint.strip_prefix("ws://") {
+        format!("http://{rest}")
+    } else {
+        endpoint.to_string()
+    }
+}
+
+/// Rounds over the list of endpoints before giving up.
+const RPC_RETRY_ROUNDS: u32 = 3;
+/// Delay between rounds (multiplied by the round number).
+const RPC_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+/// Timeout for each rpc request.
+const RPC_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Call `f` with each endpoint (in order) until one succeed, retrying the whole list
+/// (with backoff) up to `RPC_RETRY_ROUNDS` times. Only transport errors and timeouts are
+/// retried, other errors (e.g. json-rpc error responses) are returned immediately.
+pub async fn with_rpc_failover<T, F, Fut>(endpoints: &[String], f: F) -> Result<T, anyhow::Error>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, anyhow::Error>>,
+{
+    if endpoints.is_empty() {
+        return Err(BiteError::Rpc(String::from("no rpc endpoints available")).into());
+    }
+
+    let mut last_err = None;
+    for round in 1..=RPC_RETRY_ROUNDS {
+        for endpoint in endpoints {
+            match f(endpoint.clone()).await {
+                Ok(res) => return Ok(res),
+                Err(e) if !is_transport_error(&e) => {
+                    return Err(e.context(BiteError::Rpc(format!("rpc call to {endpoint} failed"))));
+                }
+                Err(e) => {
+                    warn!("rpc call to {endpoint} failed (round {round}/{RPC_RETRY_ROUNDS}): {e}");
+                    last_err = Some(e);
+                }
+            }
+        }
+        if round < RPC_RETRY_ROUNDS {
+            tokio::time::sleep(RPC_RETRY_BACKOFF * round).await;
+        }
+    }
+
+    Err(BiteError::Rpc(format!(
+        "all rpc endpoints ({}) failed, last error: {}",
+        endpoints.join(", "),
+        last_err.expect("at least one endpoint was tried. qed")
+    ))
+    .into())
+}
+
+/// Errors sending the request or reading the response (e.g. connection refused or timeout).
+fn is_transport_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<reqwest::Error>().is_some())
+}
+
+fn rpc_client() -> Result<reqwest::Client, anyhow::Error> {
+    Ok(reqwest::ClientBuilder::new()
+        .timeout(RPC_REQUEST_TIMEOUT)
+        .build()?)
+}
+
+/// Fetch the runtime metadata of the chain at the block with the supplied hash (the current one
+/// if not set), using the first working endpoint.
+pub async fn fetch_metadata(
+    endpoints: &[String],
+    at_hash: Option<&str>,
+) -> Result<Metadata, anyhow::Error> {
+    with_rpc_failover(endpoints, |endpoint| async move {
+        fetch_metadata_from(&endpoint, at_hash).await
+    })
+    .await
+}
+
+async fn fetch_metadata_from(
+    endpoint: &str,
+    at_hash: Option<&str>,
+) -> Result<Metadata, anyhow::Error> {
+    let params = match at_hash {
+        Some(hash) => json!([hash]),
+        None => json!([]),
+    };
+    let metadata_hex = rpc_call(endpoint, "state_getMetadata", params)
+        .await?
+        .and_then(|metadata| metadata.as_str().map(str::to_string))
+        .ok_or_else(|| anyhow!("invalid metadata response from {endpoint}"))?;
+    let metadata_bytes = hex::decode(metadata_hex.trim_start_matches("0x"))?;
+    let metadata = Metadata::decode(&mut &metadata_bytes[..])
+        .map_err(|e| anyhow!("Error decoding metadata from {endpoint}: {e}"))?;
+    trace!("metadata fetched from {endpoint}");
+
+    Ok(metadata)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct GetHeaderRpcResponse {
-    id: u32,
-    result: serde_json::Value // result contains an Object with the header
+struct RpcResponse {
+    result: Option<serde_json::Value>,
+    error: Option<serde_json::Value>,
 }
 
-pub async fn get_header_from_block(block_number: u32, endpoint: &str) -> Result<serde_json::Value, anyhow::Error> {
-    let client = reqwest::ClientBuilder::new().build().unwrap();
+/// Error response of a json-rpc call (the node answered, so is not retried).
+#[derive(Debug)]
+pub struct RpcResponseError {
+    pub method: String,
+    pub error: serde_json::Value,
+}
 
-    let res = client.post(endpoint).json(&json!({"method":"chain_getBlockHash","params":[block_number],"id":1,"jsonrpc":"2.0"})).send().await?;
-    let hash = res.json::<GetBlockHashRpcResponse>().await?.result;
-    trace!("block: {block_number} -> hash: {}", hash);
+impl std::fmt::Display for RpcResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed: {}", self.method, self.error)
+    }
+}
 
-    let res = client.post(endpoint).json(&json!({"method":"chain_getHeader","params":[hash],"id":1,"jsonrpc":"2.0"})).send().await?;
-    let header = res.json::<GetHeaderRpcResponse>().await?.result;
+impl std::error::Error for RpcResponseError {}
+
+/// Make a json-rpc call to the endpoint, a `null` result is returned as `None`.
+pub async fn rpc_call(
+    endpoint: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<Option<serde_json::Value>, anyhow::Error> {
+    let res = rpc_client()?
+        .post(as_http_url(endpoint))
+        .json(&json!({"method":method,"params":params,"id":1,"jsonrpc":"2.0"}))
+        .send()
+        .await?
+        .json::<RpcResponse>()
+        .await?;
+    if let Some(error) = res.error {
+        return Err(RpcResponseError {
+            method: method.to_string(),
+            error,
+        }
+        .into());
+    }
+
+    Ok(res.result.filter(|result| !result.is_null()))
+}
+
+/// Get the hash of the block (using the first working endpoint).
+pub async fn get_block_hash(
+    block_number: u32,
+    endpoints: &[String],
+) -> Result<String, anyhow::Error> {
+    with_rpc_failover(endpoints, |endpoint| async move {
+        rpc_call(&endpoint, "chain_getBlockHash", json!([block_number]))
+            .await?
+            .and_then(|hash| hash.as_str().map(str::to_string))
+            .ok_or_else(|| anyhow!("block {block_number} not found"))
+    })
+    .await
+}
+
+/// Get the header of the block with the supplied hash (using the first working endpoint).
+pub async fn get_header_from_hash(
+    hash: &str,
+    endpoints: &[String],
+) -> Result<serde_json::Value, anyhow::Error> {
+    let header = with_rpc_failover(endpoints, |endpoint| async move {
+        rpc_call(&endpoint, "chain_getHeader", json!([hash]))
+            .await?
+            .ok_or_else(|| anyhow!("block {hash} not found"))
+    })
+    .await?;
     trace!("hash: {} -> header: {:?}", hash, header);
 
     Ok(header)
 }
 
+/// Get the header of the block (using the first working endpoint).
+pub async fn get_header_from_block(
+    block_number: u32,
+    endpoints: &[String],
+) -> Result<serde_json::Value, anyhow::Error> {
+    let hash = get_block_hash(block_number, endpoints).await?;
+    trace!("block: {block_number} -> hash: {}", hash);
+    get_header_from_hash(&hash, endpoints).await
+}
+
+/// Get the (raw) storage value at the block with the supplied hash (using the first working endpoint).
+pub async fn get_storage_at(
+    key: &str,
+    hash: &str,
+    endpoints: &[String],
+) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    let value = with_rpc_failover(endpoints, |endpoint| async move {
+        rpc_call(&endpoint, "state_getStorage", json!([key, hash])).await
+    })
+    .await?;
+
+    value
+        .and_then(|value| value.as_str().map(str::to_string))
+        .map(|value| hex::decode(value.trim_start_matches("0x")).map_err(Into::into))
+        .transpose()
+}
+
+/// Block number from the (json) header.
+pub fn header_number(header: &serde_json::Value) -> Result<u32, anyhow::Error> {
+    let number = header["number"]
+        .as_str()
+        .ok_or_else(|| anyhow!("invalid header, number not present"))?;
+    u32::from_str_radix(number.trim_start_matches("0x"), 16)
+        .map_err(|e| anyhow!("invalid header number {number}: {e}"))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -248,13 +497,64 @@ mod test {
     #[tokio::test]
     async fn get_header_from_block_should_work() {
         let expeted_header = json!({"parentHash":"0xc890131fe604ae48cd385eb91be80f70ad99307bdc9e79d5969b8e8b507ad138","number":"0x1c6b060","stateRoot":"0x1c5bfccd40794b5a0bd286aa8a5bc95d399399881b86428a3dff50c01cec41d1","extrinsicsRoot":"0x0815e5a6dc935c696e0619181fa26d28481205e9dc378ab380d92a36a97672a1","digest":{"logs":["0x0642414245b50103340100003cd6711100000000c2b1b49c3abff403a816c9f9f4ca96ce914a48e2c2579dbf050e58cbe478197c12d22ca51a2f42237b40472bf9a2de48df2968eb89a10ebcc4e6e046b9be470316c1bce81d762e343e78516c6d114295d6f338788f44dbd3f9718b7b80710703","0x04424545468403889f6e18a352a24d88267f842803fbe97262bf72d87c8a2f91b59f646693d3c5","0x0446524e4b2403d900000000000000","0x04424142451402d9000000","0x04424545461402d9000000","0x0446524e4b24037602000000000000","0x0442414245140276020000","0x0442454546140276020000","0x05424142450101e8c608382a1eb7d08a7d252b58d74f157834c1b98c754f0b3554fe7fcbfa056a17c1005d7e989635f6545b35cda5643e8797f709b9d6f49670e909c326c5e282"]}});
-        let header = get_header_from_block(29798496, "https://kusama-rpc.dwellir.com").await.unwrap();
+        let header =
+            get_header_from_block(29798496, &[String::from("https://kusama-rpc.dwellir.com")])
+                .await
+                .unwrap();
         assert_eq!(expeted_header["parentHash"], header["parentHash"]);
         assert_eq!(expeted_header["stateRoot"], header["stateRoot"]);
         assert_eq!(expeted_header["number"], header["number"]);
         assert_eq!(expeted_header["extrinsicsRoot"], header["extrinsicsRoot"]);
     }
 
+    #[tokio::test]
+    async fn rpc_failover_should_use_next_endpoint() {
+        // nothing listening there
+        let endpoints = vec![
+            String::from("http://127.0.0.1:1"),
+            String::from("http://up"),
+        ];
+        let res = with_rpc_failover(&endpoints, |endpoint| async move {
+            if endpoint.ends_with(":1") {
+                rpc_call(&endpoint, "chain_getFinalizedHead", json!([]))
+                    .await
+                    .map(|_| endpoint)
+            } else {
+                Ok(endpoint)
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(res, "http://up");
+
+        assert!(with_rpc_failover(&[], |_| async { Ok(()) }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rpc_failover_should_not_retry_error_responses() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let endpoints = vec![String::from("http://pruned"), String::from("http://up")];
+        let err = with_rpc_failover(&endpoints, |endpoint| {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if endpoint.ends_with("pruned") {
+                    Err(RpcResponseError {
+                        method: String::from("state_getStorage"),
+                        error: json!({"code": 4003, "message": "State already discarded"}),
+                    }
+                    .into())
+                } else {
+                    Ok(endpoint)
+                }
+            }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(crate::error::exit_code(&err), 3);
+        assert!(err.downcast_ref::<RpcResponseError>().is_some());
+    }
+
     #[test]
     fn para_head_key_should_work() {
         let para_id = 1000_u32;
@@ -284,9 +584,9 @@ mod test {
         let config_path = "./testing/config-paseo.toml";
         let config_path_bkp = "./testing/config-paseo.toml.bkp";
         let _ = fs::copy(&config_path_bkp, config_path).await;
-        let _ = localize_config(config_path).await.unwrap();
+        localize_config(config_path).await.unwrap();
         let network_config =
-            zombienet_configuration::NetworkConfig::load_from_toml(&config_path).unwrap();
+            zombienet_configuration::NetworkConfig::load_from_toml(config_path).unwrap();
         let _alice_db = network_config
             .relaychain()
             .nodes()